@@ -8,11 +8,33 @@ _peon_completions() {
   prev="${COMP_WORDS[COMP_CWORD-1]}"
 
   # Top-level subcommands
-  opts="pause resume toggle status packs pack sounds play pull upgrade help"
+  opts="pause resume toggle status packs pack sounds play pull outdated remove credits upgrade config help"
 
   # Subcommand-specific completions
   case "$prev" in
-    pack|sounds)
+    validate)
+      COMPREPLY=( $(compgen -W "--json --max-duration" -- "$cur") )
+      return 0
+      ;;
+    import)
+      COMPREPLY=( $(compgen -d -- "$cur") $(compgen -W "--name --display-name --mapping --interactive --force" -- "$cur") )
+      return 0
+      ;;
+    install)
+      COMPREPLY=( $(compgen -f -- "$cur") $(compgen -W "--force" -- "$cur") )
+      return 0
+      ;;
+    pack)
+      # Complete pack subcommands and pack names
+      packs_dir="${PEON_PACKS:-${CLAUDE_PEON_DIR:-$HOME/.claude/hooks/peon-ping}/packs}"
+      local names=""
+      if [ -d "$packs_dir" ]; then
+        names=$(find "$packs_dir" -maxdepth 2 -name manifest.json -exec dirname {} \; 2>/dev/null | xargs -I{} basename {} | sort)
+      fi
+      COMPREPLY=( $(compgen -W "new import export install validate $names" -- "$cur") )
+      return 0
+      ;;
+    sounds|credits|remove)
       # Complete pack names by scanning manifest files
       packs_dir="${PEON_PACKS:-${CLAUDE_PEON_DIR:-$HOME/.claude/hooks/peon-ping}/packs}"
       if [ -d "$packs_dir" ]; then
@@ -22,15 +44,31 @@ _peon_completions() {
       fi
       return 0
       ;;
+    packs)
+      COMPREPLY=( $(compgen -W "--remote" -- "$cur") )
+      return 0
+      ;;
+    config)
+      COMPREPLY=( $(compgen -W "show" -- "$cur") )
+      return 0
+      ;;
+    upgrade)
+      COMPREPLY=( $(compgen -W "--force --version --channel --list --check --rollback" -- "$cur") )
+      return 0
+      ;;
+    --channel)
+      COMPREPLY=( $(compgen -W "stable prerelease" -- "$cur") )
+      return 0
+      ;;
     play)
       # Complete category names
       COMPREPLY=( $(compgen -W "greeting acknowledge complete error permission resource_limit annoyed" -- "$cur") )
       return 0
       ;;
     pull)
-      # Complete with --all flag or pack names from GitHub (fall back to local)
+      # Complete with --all/--update flags or pack names from GitHub (fall back to local)
       if [[ "$cur" == -* ]]; then
-        COMPREPLY=( $(compgen -W "--all" -- "$cur") )
+        COMPREPLY=( $(compgen -W "--all --update" -- "$cur") )
       else
         packs_dir="${PEON_PACKS:-${CLAUDE_PEON_DIR:-$HOME/.claude/hooks/peon-ping}/packs}"
         if [ -d "$packs_dir" ]; then
@@ -0,0 +1,49 @@
+use crate::types::Config;
+
+/// Whether to play `acknowledge` for a prompt at `now`.
+///
+/// Never within `acknowledge_cooldown_seconds` of the session's `last` acknowledgement;
+/// otherwise with probability `acknowledge_chance`, decided by `roll` (uniform in `[0, 1)`).
+pub fn should_acknowledge(config: &Config, last: Option<f64>, now: f64, roll: f64) -> bool {
+    let cooling_down = last.is_some_and(|last| now - last < config.acknowledge_cooldown_seconds);
+    !cooling_down && roll < config.acknowledge_chance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(chance: f64, cooldown: f64) -> Config {
+        Config {
+            acknowledge_chance: chance,
+            acknowledge_cooldown_seconds: cooldown,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chance_decides_outside_cooldown() {
+        let config = config(0.5, 60.0);
+        assert!(should_acknowledge(&config, None, 100.0, 0.2));
+        assert!(!should_acknowledge(&config, None, 100.0, 0.7));
+        assert!(should_acknowledge(&config, Some(30.0), 100.0, 0.2));
+    }
+
+    #[test]
+    fn cooldown_blocks_repeat_acknowledgements() {
+        let config = config(1.0, 60.0);
+        assert!(!should_acknowledge(&config, Some(50.0), 100.0, 0.0));
+        assert!(should_acknowledge(&config, Some(40.0), 100.0, 0.0));
+    }
+
+    #[test]
+    fn zero_chance_never_acknowledges() {
+        assert!(!should_acknowledge(&config(0.0, 0.0), None, 100.0, 0.0));
+        assert!(should_acknowledge(
+            &config(1.0, 0.0),
+            Some(100.0),
+            100.0,
+            0.999
+        ));
+    }
+}
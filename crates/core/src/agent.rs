@@ -1,44 +1,247 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 
-/// Returns `true` if the session should be treated as an agent (suppressed).
+use crate::glob_match;
+use crate::types::{Action, AgentBehavior, AgentRule, AgentSession, HookEvent};
+
+/// Classify the event's session as agent activity.
 ///
-/// A session is considered an agent if:
-/// - Its `permission_mode` is `"delegate"`, OR
-/// - It was previously seen with delegate mode (recorded in `agent_sessions`).
-pub fn is_agent_session(
-    agent_sessions: &HashSet<String>,
-    session_id: &str,
-    permission_mode: &str,
-) -> bool {
-    permission_mode == "delegate" || agent_sessions.contains(session_id)
+/// A session recorded in `agent_sessions` keeps its classification. Otherwise the first
+/// rule in `rules` that matches decides; `env` looks up environment variables. Returns
+/// the classification and whether it should be recorded for the rest of the session,
+/// which is the case unless the rule only matched a sub-agent event.
+pub fn classify_agent(
+    rules: &[AgentRule],
+    agent_sessions: &HashMap<String, AgentSession>,
+    event: &HookEvent,
+    env: impl Fn(&str) -> Option<String>,
+) -> Option<(AgentSession, bool)> {
+    if let Some(session) = agent_sessions.get(event.session_id()) {
+        return Some((session.clone(), false));
+    }
+    let rule = rules.iter().find(|rule| rule_matches(rule, event, &env))?;
+    let session = AgentSession {
+        reason: rule_reason(rule),
+        behavior: rule.behavior.clone(),
+    };
+    Some((session, !rule.subagent))
+}
+
+fn rule_matches(rule: &AgentRule, event: &HookEvent, env: impl Fn(&str) -> Option<String>) -> bool {
+    let conditions = [
+        rule.permission_mode
+            .as_ref()
+            .map(|mode| event.permission_mode() == mode),
+        rule.subagent
+            .then_some(matches!(event, HookEvent::SubagentStop { .. })),
+        rule.cwd
+            .as_ref()
+            .map(|pattern| glob_match(pattern, event.cwd())),
+        rule.env.as_ref().map(|marker| env_matches(marker, &env)),
+        rule.session_prefix
+            .as_ref()
+            .map(|prefix| event.session_id().starts_with(prefix.as_str())),
+    ];
+    let mut set = conditions.into_iter().flatten().peekable();
+    set.peek().is_some() && set.all(|holds| holds)
+}
+
+/// `NAME` holds when the variable is set and non-empty, `NAME=glob` when its value matches.
+fn env_matches(marker: &str, env: impl Fn(&str) -> Option<String>) -> bool {
+    match marker.split_once('=') {
+        Some((name, pattern)) => env(name).is_some_and(|value| glob_match(pattern, &value)),
+        None => env(marker).is_some_and(|value| !value.is_empty()),
+    }
+}
+
+/// The rule's name, or its conditions spelled out.
+fn rule_reason(rule: &AgentRule) -> String {
+    if let Some(name) = &rule.name {
+        return name.clone();
+    }
+    let mut parts = Vec::new();
+    if let Some(mode) = &rule.permission_mode {
+        parts.push(format!("permission_mode {mode}"));
+    }
+    if rule.subagent {
+        parts.push("subagent".to_string());
+    }
+    if let Some(cwd) = &rule.cwd {
+        parts.push(format!("cwd {cwd}"));
+    }
+    if let Some(env) = &rule.env {
+        parts.push(format!("env {env}"));
+    }
+    if let Some(prefix) = &rule.session_prefix {
+        parts.push(format!("session {prefix}*"));
+    }
+    parts.join(", ")
+}
+
+/// Restrict routed actions to what an agent's behaviour allows.
+pub fn apply_agent_behavior(actions: Vec<Action>, behavior: &AgentBehavior) -> Vec<Action> {
+    match behavior {
+        AgentBehavior::Silent => Vec::new(),
+        AgentBehavior::NotifyOnly => actions
+            .into_iter()
+            .filter(|action| !matches!(action, Action::PlaySound { .. }))
+            .collect(),
+        AgentBehavior::Category(category) => actions
+            .into_iter()
+            .map(|action| match action {
+                Action::PlaySound { .. } => Action::PlaySound {
+                    category: category.clone(),
+                },
+                other => other,
+            })
+            .collect(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Config;
+
+    fn event(name: &str, session_id: &str, permission_mode: &str, cwd: &str) -> HookEvent {
+        let json = format!(
+            r#"{{"hook_event_name":"{name}","cwd":"{cwd}","session_id":"{session_id}","permission_mode":"{permission_mode}"}}"#
+        );
+        serde_json::from_str(&json).expect("valid event JSON")
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn classify(rules: &[AgentRule], event: &HookEvent) -> Option<(AgentSession, bool)> {
+        classify_agent(rules, &HashMap::new(), event, no_env)
+    }
 
     #[test]
-    fn delegate_mode_is_agent() {
-        let sessions = HashSet::new();
-        assert!(is_agent_session(&sessions, "s1", "delegate"));
+    fn delegate_mode_is_agent_by_default() {
+        let rules = Config::default().agent_rules;
+        let (session, record) =
+            classify(&rules, &event("SessionStart", "s1", "delegate", "/tmp")).unwrap();
+        assert_eq!(session.reason, "permission_mode delegate");
+        assert_eq!(session.behavior, AgentBehavior::Silent);
+        assert!(record);
     }
 
     #[test]
     fn default_mode_not_agent() {
-        let sessions = HashSet::new();
-        assert!(!is_agent_session(&sessions, "s1", "default"));
+        let rules = Config::default().agent_rules;
+        assert!(classify(&rules, &event("Stop", "s1", "default", "/tmp")).is_none());
+        assert!(classify(&rules, &event("Stop", "s1", "acceptEdits", "/tmp")).is_none());
+    }
+
+    #[test]
+    fn subagent_events_are_not_recorded() {
+        let rules = Config::default().agent_rules;
+        let (session, record) =
+            classify(&rules, &event("SubagentStop", "s1", "default", "/tmp")).unwrap();
+        assert_eq!(session.reason, "subagent");
+        assert!(!record);
+    }
+
+    #[test]
+    fn previously_seen_session_keeps_classification() {
+        let mut sessions = HashMap::new();
+        sessions.insert(
+            "s1".to_string(),
+            AgentSession {
+                reason: "ci".into(),
+                behavior: AgentBehavior::NotifyOnly,
+            },
+        );
+        let (session, record) = classify_agent(
+            &[],
+            &sessions,
+            &event("Stop", "s1", "default", "/tmp"),
+            no_env,
+        )
+        .unwrap();
+        assert_eq!(session.behavior, AgentBehavior::NotifyOnly);
+        assert!(!record);
+    }
+
+    #[test]
+    fn all_conditions_of_a_rule_must_hold() {
+        let rules = vec![AgentRule {
+            cwd: Some("/srv/ci/*".into()),
+            session_prefix: Some("ci-".into()),
+            behavior: AgentBehavior::NotifyOnly,
+            ..Default::default()
+        }];
+        let hit = classify(&rules, &event("Stop", "ci-42", "default", "/srv/ci/app"));
+        assert_eq!(hit.unwrap().0.reason, "cwd /srv/ci/*, session ci-*");
+        assert!(classify(&rules, &event("Stop", "ci-42", "default", "/home/me/app")).is_none());
+        assert!(classify(&rules, &event("Stop", "s1", "default", "/srv/ci/app")).is_none());
+        assert!(classify(&[AgentRule::default()], &event("Stop", "s1", "", "/")).is_none());
+    }
+
+    #[test]
+    fn env_markers_match_presence_or_glob() {
+        let env = |name: &str| match name {
+            "CI" => Some("true".to_string()),
+            "CLAUDE_CODE_ENTRYPOINT" => Some("sdk-cli".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        assert!(env_matches("CI", env));
+        assert!(!env_matches("EMPTY", env));
+        assert!(!env_matches("MISSING", env));
+        assert!(env_matches("CLAUDE_CODE_ENTRYPOINT=sdk-*", env));
+        assert!(!env_matches("CLAUDE_CODE_ENTRYPOINT=cli", env));
+
+        let rules = vec![AgentRule {
+            name: Some("headless".into()),
+            env: Some("CLAUDE_CODE_ENTRYPOINT=sdk-*".into()),
+            ..Default::default()
+        }];
+        let (session, _) = classify_agent(
+            &rules,
+            &HashMap::new(),
+            &event("Stop", "s1", "default", "/tmp"),
+            env,
+        )
+        .unwrap();
+        assert_eq!(session.reason, "headless");
     }
 
     #[test]
-    fn previously_seen_delegate_is_agent() {
-        let mut sessions = HashSet::new();
-        sessions.insert("s1".to_string());
-        assert!(is_agent_session(&sessions, "s1", "default"));
+    fn behaviors_filter_actions() {
+        let actions = vec![
+            Action::SetTabTitle { title: "t".into() },
+            Action::PlaySound {
+                category: "complete".into(),
+            },
+        ];
+        assert!(apply_agent_behavior(actions.clone(), &AgentBehavior::Silent).is_empty());
+        assert_eq!(
+            apply_agent_behavior(actions.clone(), &AgentBehavior::NotifyOnly),
+            vec![Action::SetTabTitle { title: "t".into() }]
+        );
+        assert_eq!(
+            apply_agent_behavior(actions, &AgentBehavior::Category("subagent".into()))[1],
+            Action::PlaySound {
+                category: "subagent".into()
+            }
+        );
     }
 
     #[test]
-    fn accept_edits_not_agent() {
-        let sessions = HashSet::new();
-        assert!(!is_agent_session(&sessions, "s1", "acceptEdits"));
+    fn behavior_deserializes_from_config() {
+        let rules: Vec<AgentRule> = serde_json::from_str(
+            r#"[{"env": "CI", "behavior": "notify_only"},
+                {"subagent": true, "behavior": {"category": "subagent"}},
+                {"session_prefix": "bot-"}]"#,
+        )
+        .unwrap();
+        assert_eq!(rules[0].behavior, AgentBehavior::NotifyOnly);
+        assert_eq!(
+            rules[1].behavior,
+            AgentBehavior::Category("subagent".into())
+        );
+        assert_eq!(rules[2].behavior, AgentBehavior::Silent);
     }
 }
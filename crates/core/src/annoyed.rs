@@ -1,52 +1,208 @@
-/// Check if the user is "annoyed" — sending too many prompts in a short window.
-///
-/// Returns `true` if the number of timestamps within `window_secs` of `now`
-/// meets or exceeds `threshold`.
-pub fn check_annoyed(timestamps: &[f64], threshold: u32, window_secs: f64, now: f64) -> bool {
-    let cutoff = now - window_secs;
-    let recent_count = timestamps.iter().filter(|&&t| t > cutoff).count();
-    recent_count >= threshold as usize
+use crate::types::{Annoyance, Config};
+
+/// Category played for annoyance tier `tier` (1-based); packs without it fall back to
+/// `annoyed`.
+pub fn annoyed_category(tier: u32) -> String {
+    format!("annoyed_{tier}")
+}
+
+/// The tier of an `annoyed_<n>` category, if `category` is one.
+pub fn annoyed_tier(category: &str) -> Option<u32> {
+    let digits = category.strip_prefix("annoyed_")?;
+    if digits.starts_with('0') || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// The annoyance score at `now`: it drains by one point per `window_secs`, so a single
+/// prompt is forgotten after one window. Never negative.
+pub fn decayed_score(annoyance: &Annoyance, window_secs: f64, now: f64) -> f64 {
+    if window_secs <= 0.0 {
+        return 0.0;
+    }
+    let elapsed = (now - annoyance.updated_at).max(0.0);
+    (annoyance.score - elapsed / window_secs).max(0.0)
+}
+
+/// Which tier (1 to `levels`) a score has reached: tier `n` starts at `n × threshold`.
+pub fn annoyance_tier(score: f64, threshold: u32, levels: u32) -> Option<u32> {
+    let threshold = f64::from(threshold.max(1));
+    let tier = (score / threshold).floor().min(f64::from(levels));
+    (tier >= 1.0).then_some(tier as u32)
+}
+
+/// Register a prompt at `now`: decay the session's previous annoyance, add one point and
+/// return the new annoyance with the tier reached, if any. Reaching the top tier lets the
+/// peon calm down, resetting the score so the next outburst builds up from scratch.
+pub fn register_prompt(
+    previous: Option<&Annoyance>,
+    config: &Config,
+    now: f64,
+) -> (Annoyance, Option<u32>) {
+    let window = config.annoyed_window_seconds;
+    let score = previous.map_or(0.0, |a| decayed_score(a, window, now)) + 1.0;
+    let levels = config.annoyed_levels.max(1);
+    let tier = annoyance_tier(score, config.annoyed_threshold, levels);
+    let score = if tier == Some(levels) { 0.0 } else { score };
+    (
+        Annoyance {
+            score,
+            updated_at: now,
+        },
+        tier,
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
-    #[test]
-    fn below_threshold_not_annoyed() {
-        let timestamps = vec![100.0, 105.0];
-        assert!(!check_annoyed(&timestamps, 3, 10.0, 108.0));
+    fn config(threshold: u32, window: f64, levels: u32) -> Config {
+        Config {
+            annoyed_threshold: threshold,
+            annoyed_window_seconds: window,
+            annoyed_levels: levels,
+            ..Default::default()
+        }
+    }
+
+    /// Tiers reached by prompts at the given times.
+    fn burst(config: &Config, times: &[f64]) -> Vec<Option<u32>> {
+        let mut annoyance = None;
+        times
+            .iter()
+            .map(|&now| {
+                let (next, tier) = register_prompt(annoyance.as_ref(), config, now);
+                annoyance = Some(next);
+                tier
+            })
+            .collect()
     }
 
     #[test]
-    fn at_threshold_is_annoyed() {
-        let timestamps = vec![100.0, 103.0, 106.0];
-        assert!(check_annoyed(&timestamps, 3, 10.0, 108.0));
+    fn rapid_prompts_climb_the_tiers_then_calm_down() {
+        let tiers = burst(&config(3, 10.0, 3), &[100.0; 10]);
+        assert_eq!(
+            tiers,
+            vec![
+                None,
+                None,
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(2),
+                Some(2),
+                Some(2),
+                Some(3),
+                None
+            ]
+        );
     }
 
     #[test]
-    fn expired_timestamps_ignored() {
-        let timestamps = vec![1.0, 2.0, 100.0, 103.0];
-        assert!(!check_annoyed(&timestamps, 3, 10.0, 108.0));
+    fn slow_prompts_never_annoy() {
+        let times: Vec<f64> = (0..20).map(|i| f64::from(i) * 10.0).collect();
+        assert!(burst(&config(3, 10.0, 3), &times)
+            .iter()
+            .all(Option::is_none));
     }
 
     #[test]
-    fn empty_timestamps() {
-        assert!(!check_annoyed(&[], 3, 10.0, 100.0));
+    fn score_decays_over_the_window() {
+        let annoyance = Annoyance {
+            score: 2.0,
+            updated_at: 100.0,
+        };
+        assert_eq!(decayed_score(&annoyance, 10.0, 105.0), 1.5);
+        assert_eq!(decayed_score(&annoyance, 10.0, 200.0), 0.0);
+        assert_eq!(decayed_score(&annoyance, 10.0, 90.0), 2.0);
+        assert_eq!(decayed_score(&annoyance, 0.0, 100.0), 0.0);
     }
 
     #[test]
-    fn threshold_of_one() {
-        let timestamps = vec![99.5];
-        assert!(check_annoyed(&timestamps, 1, 10.0, 100.0));
+    fn parses_tier_categories() {
+        assert_eq!(annoyed_category(2), "annoyed_2");
+        assert_eq!(annoyed_tier("annoyed_2"), Some(2));
+        assert_eq!(annoyed_tier("annoyed_12"), Some(12));
+        for category in [
+            "annoyed",
+            "annoyed_",
+            "annoyed_0",
+            "annoyed_01",
+            "annoyed_x",
+        ] {
+            assert_eq!(annoyed_tier(category), None, "{category}");
+        }
     }
 
     #[test]
-    fn exact_boundary_excluded() {
-        // Timestamp at exactly cutoff (now - window) should NOT count
-        let timestamps = vec![90.0, 95.0, 98.0];
-        assert!(check_annoyed(&timestamps, 2, 10.0, 100.0));
-        // 90.0 is exactly at cutoff (100 - 10), should not count (> not >=)
-        assert!(!check_annoyed(&[90.0], 1, 10.0, 100.0));
+    fn single_level_behaves_like_a_threshold() {
+        let tiers = burst(&config(2, 10.0, 1), &[0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(tiers, vec![None, Some(1), None, Some(1)]);
+    }
+
+    proptest! {
+        #[test]
+        fn decay_never_increases_or_goes_negative(
+            score in 0.0..100.0f64,
+            updated_at in 0.0..1e6f64,
+            window in 0.0..600.0f64,
+            later in 0.0..1e4f64,
+            more in 0.0..1e4f64,
+        ) {
+            let annoyance = Annoyance { score, updated_at };
+            let first = decayed_score(&annoyance, window, updated_at + later);
+            let second = decayed_score(&annoyance, window, updated_at + later + more);
+            prop_assert!(first >= 0.0 && first <= score);
+            prop_assert!(second <= first);
+        }
+
+        #[test]
+        fn tiers_are_bounded_and_monotonic(
+            score in 0.0..100.0f64,
+            extra in 0.0..100.0f64,
+            threshold in 0u32..10,
+            levels in 1u32..6,
+        ) {
+            let tier = annoyance_tier(score, threshold, levels);
+            let higher = annoyance_tier(score + extra, threshold, levels);
+            prop_assert!(tier.is_none_or(|t| (1..=levels).contains(&t)));
+            prop_assert!(higher >= tier);
+        }
+
+        #[test]
+        fn prompts_add_at_most_one_point(
+            score in 0.0..50.0f64,
+            elapsed in 0.0..100.0f64,
+            threshold in 1u32..6,
+            levels in 1u32..5,
+            window in 1.0..60.0f64,
+        ) {
+            let config = config(threshold, window, levels);
+            let previous = Annoyance { score, updated_at: 0.0 };
+            let (next, tier) = register_prompt(Some(&previous), &config, elapsed);
+            prop_assert!(next.score <= score + 1.0);
+            prop_assert_eq!(next.updated_at, elapsed);
+            if tier == Some(levels) {
+                prop_assert_eq!(next.score, 0.0);
+            } else {
+                prop_assert_eq!(tier, annoyance_tier(next.score, threshold, levels));
+            }
+        }
+
+        #[test]
+        fn quiet_sessions_start_over(
+            score in 0.0..50.0f64,
+            threshold in 1u32..6,
+            window in 1.0..60.0f64,
+        ) {
+            let config = config(threshold, window, 3);
+            let previous = Annoyance { score, updated_at: 0.0 };
+            let quiet = score * window + 1.0;
+            let (next, _) = register_prompt(Some(&previous), &config, quiet);
+            prop_assert_eq!(next.score, 1.0);
+        }
     }
 }
@@ -0,0 +1,287 @@
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::types::Manifest;
+
+/// File extension of portable pack archives.
+pub const ARCHIVE_EXTENSION: &str = "peonpack";
+
+/// Name of the checksum list inside an archive, in `sha256sum` format.
+pub const CHECKSUMS_FILE: &str = "checksums.txt";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ArchiveError {
+    #[error("unsafe path in archive: {0}")]
+    UnsafePath(String),
+    #[error("archive has no {0}")]
+    MissingFile(String),
+    #[error("invalid {CHECKSUMS_FILE} line {line}: {text}")]
+    BadChecksumLine { line: usize, text: String },
+    #[error("checksum mismatch for {0}")]
+    ChecksumMismatch(String),
+    #[error("{0} is not listed in {CHECKSUMS_FILE}")]
+    Unlisted(String),
+    #[error("invalid manifest.json: {0}")]
+    Manifest(String),
+    #[error("invalid pack name \"{0}\"")]
+    InvalidName(String),
+    #[error("manifest references missing sound file {0}")]
+    MissingSound(String),
+}
+
+/// Lower-case hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Pack names are used as directory names, so only allow a safe subset.
+pub fn is_valid_pack_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Reject archive entry paths that could escape the pack directory.
+///
+/// Paths must be relative, use `/` separators and contain no `.`/`..` or empty components.
+pub fn check_entry_path(path: &str) -> Result<(), ArchiveError> {
+    let unsafe_path = path.is_empty()
+        || path.starts_with('/')
+        || path.contains('\\')
+        || path.contains(':')
+        || path
+            .split('/')
+            .any(|part| part.is_empty() || part == "." || part == "..");
+    if unsafe_path {
+        Err(ArchiveError::UnsafePath(path.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Build the checksum list for an archive's files (every file except the list itself).
+pub fn format_checksums(files: &BTreeMap<String, Vec<u8>>) -> String {
+    files
+        .iter()
+        .filter(|(path, _)| path.as_str() != CHECKSUMS_FILE)
+        .map(|(path, bytes)| format!("{}  {path}\n", sha256_hex(bytes)))
+        .collect()
+}
+
+/// Parse a `sha256sum`-style list into path → hex digest.
+pub fn parse_checksums(text: &str) -> Result<BTreeMap<String, String>, ArchiveError> {
+    let mut checksums = BTreeMap::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let bad_line = || ArchiveError::BadChecksumLine {
+            line: index + 1,
+            text: line.to_string(),
+        };
+        let (digest, path) = line.split_once("  ").ok_or_else(bad_line)?;
+        let valid_digest = digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit());
+        if !valid_digest || path.is_empty() {
+            return Err(bad_line());
+        }
+        checksums.insert(path.to_string(), digest.to_ascii_lowercase());
+    }
+    Ok(checksums)
+}
+
+/// Check the contents of an unpacked archive and return its manifest.
+///
+/// Every file must be listed in [`CHECKSUMS_FILE`] with a matching digest, every listed
+/// file must be present, and the pack must pass [`check_pack_contents`].
+pub fn verify_archive(files: &BTreeMap<String, Vec<u8>>) -> Result<Manifest, ArchiveError> {
+    for path in files.keys() {
+        check_entry_path(path)?;
+    }
+
+    let checksums = files
+        .get(CHECKSUMS_FILE)
+        .ok_or_else(|| ArchiveError::MissingFile(CHECKSUMS_FILE.to_string()))?;
+    check_checksums(
+        files,
+        &parse_checksums(&String::from_utf8_lossy(checksums))?,
+    )?;
+    check_pack_contents(files)
+}
+
+/// Check files against a checksum list: every file (other than the list itself) must be
+/// listed with a matching digest, and every listed file must be present.
+pub fn check_checksums(
+    files: &BTreeMap<String, Vec<u8>>,
+    checksums: &BTreeMap<String, String>,
+) -> Result<(), ArchiveError> {
+    for (path, bytes) in files {
+        if path == CHECKSUMS_FILE {
+            continue;
+        }
+        let expected = checksums
+            .get(path)
+            .ok_or_else(|| ArchiveError::Unlisted(path.clone()))?;
+        if *expected != sha256_hex(bytes) {
+            return Err(ArchiveError::ChecksumMismatch(path.clone()));
+        }
+    }
+    if let Some(missing) = checksums.keys().find(|path| !files.contains_key(*path)) {
+        return Err(ArchiveError::MissingFile(missing.clone()));
+    }
+    Ok(())
+}
+
+/// Check a pack's files and return its manifest.
+///
+/// Every path must be safe and `manifest.json` must parse with a name usable as a
+/// directory. Every sound the manifest lists from the pack itself must be present under
+/// `sounds/`.
+pub fn check_pack_contents(files: &BTreeMap<String, Vec<u8>>) -> Result<Manifest, ArchiveError> {
+    for path in files.keys() {
+        check_entry_path(path)?;
+    }
+
+    let manifest_json = files
+        .get("manifest.json")
+        .ok_or_else(|| ArchiveError::MissingFile("manifest.json".to_string()))?;
+    let manifest: Manifest =
+        serde_json::from_slice(manifest_json).map_err(|e| ArchiveError::Manifest(e.to_string()))?;
+    if !is_valid_pack_name(&manifest.name) {
+        return Err(ArchiveError::InvalidName(manifest.name));
+    }
+    for sound in manifest.categories.values().flat_map(|c| &c.sounds) {
+        if !sound.has_safe_location() {
+            return Err(ArchiveError::UnsafePath(sound.file.clone()));
+        }
+        let (pack, file) = sound.location(&manifest.name);
+        let path = format!("sounds/{file}");
+        if pack == manifest.name && !files.contains_key(&path) {
+            return Err(ArchiveError::MissingSound(path));
+        }
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(entries: &[(&str, &[u8])]) -> BTreeMap<String, Vec<u8>> {
+        let mut files: BTreeMap<String, Vec<u8>> = entries
+            .iter()
+            .map(|(path, bytes)| (path.to_string(), bytes.to_vec()))
+            .collect();
+        let checksums = format_checksums(&files);
+        files.insert(CHECKSUMS_FILE.to_string(), checksums.into_bytes());
+        files
+    }
+
+    const MANIFEST: &[u8] = br#"{"name": "mine", "categories": {}}"#;
+
+    #[test]
+    fn sha256_of_empty_input() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn round_trip_verifies() {
+        let files = archive(&[("manifest.json", MANIFEST), ("sounds/Hi.wav", b"RIFF")]);
+        assert_eq!(verify_archive(&files).unwrap().name, "mine");
+    }
+
+    #[test]
+    fn tampered_file_is_rejected() {
+        let mut files = archive(&[("manifest.json", MANIFEST), ("sounds/Hi.wav", b"RIFF")]);
+        files.insert("sounds/Hi.wav".into(), b"evil".to_vec());
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::ChecksumMismatch("sounds/Hi.wav".into()))
+        );
+    }
+
+    #[test]
+    fn unlisted_and_missing_files_are_rejected() {
+        let mut files = archive(&[("manifest.json", MANIFEST), ("sounds/Hi.wav", b"RIFF")]);
+        files.insert("sounds/Extra.wav".into(), b"x".to_vec());
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::Unlisted("sounds/Extra.wav".into()))
+        );
+
+        let mut files = archive(&[("manifest.json", MANIFEST), ("sounds/Hi.wav", b"RIFF")]);
+        files.remove("sounds/Hi.wav");
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::MissingFile("sounds/Hi.wav".into()))
+        );
+    }
+
+    #[test]
+    fn missing_checksums_is_rejected() {
+        let mut files = archive(&[("manifest.json", MANIFEST)]);
+        files.remove(CHECKSUMS_FILE);
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::MissingFile(CHECKSUMS_FILE.into()))
+        );
+    }
+
+    #[test]
+    fn traversal_paths_are_rejected() {
+        for path in [
+            "../evil.wav",
+            "sounds/../../evil",
+            "/etc/passwd",
+            "sounds//a.wav",
+            "./manifest.json",
+            "sounds\\..\\a",
+            "C:/a",
+        ] {
+            assert!(check_entry_path(path).is_err(), "{path}");
+        }
+        assert!(check_entry_path("sounds/Hi.wav").is_ok());
+
+        let files = archive(&[("manifest.json", MANIFEST), ("../x", b"x")]);
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::UnsafePath("../x".into()))
+        );
+    }
+
+    #[test]
+    fn unsafe_pack_name_is_rejected() {
+        let files = archive(&[("manifest.json", br#"{"name": "../up"}"#)]);
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::InvalidName("../up".into()))
+        );
+    }
+
+    #[test]
+    fn missing_referenced_sound_is_rejected() {
+        let manifest = br#"{"name": "mine", "categories": {"greeting": {"sounds": [
+            {"file": "Hi.wav"}, {"file": "Bye.wav"}, {"file": "peon:PeonReady1.wav"}
+        ]}}}"#;
+        let files = archive(&[("manifest.json", manifest), ("sounds/Hi.wav", b"RIFF")]);
+        assert_eq!(
+            verify_archive(&files),
+            Err(ArchiveError::MissingSound("sounds/Bye.wav".into()))
+        );
+    }
+
+    #[test]
+    fn bad_checksum_line() {
+        assert!(matches!(
+            parse_checksums("nothex  a.wav\n"),
+            Err(ArchiveError::BadChecksumLine { line: 1, .. })
+        ));
+    }
+}
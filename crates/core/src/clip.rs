@@ -0,0 +1,168 @@
+/// Audio formats supported by packs, by file extension.
+pub const SUPPORTED_AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "ogg"];
+
+/// Lower-cased extension of a sound file name, if any.
+pub fn audio_extension(file_name: &str) -> Option<String> {
+    file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+}
+
+/// Returns `true` if the file name has a supported audio extension.
+pub fn is_supported_audio(file_name: &str) -> bool {
+    audio_extension(file_name).is_some_and(|ext| SUPPORTED_AUDIO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Estimate the duration of a clip in seconds from its contents.
+///
+/// WAV durations are exact (from the header). MP3 durations are estimated from the
+/// first frame's bitrate, which is accurate for constant-bitrate files. Other formats
+/// return `None`.
+pub fn clip_duration(file_name: &str, bytes: &[u8]) -> Option<f64> {
+    match audio_extension(file_name)?.as_str() {
+        "wav" => wav_duration(bytes),
+        "mp3" => mp3_duration(bytes),
+        _ => None,
+    }
+}
+
+fn wav_duration(bytes: &[u8]) -> Option<f64> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut byte_rate = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let body = pos + 8;
+        match id {
+            b"fmt " if body + 12 <= bytes.len() => {
+                byte_rate = Some(u32::from_le_bytes(
+                    bytes[body + 8..body + 12].try_into().ok()?,
+                ));
+            }
+            b"data" => {
+                let rate = byte_rate.filter(|r| *r > 0)?;
+                // Truncated files report more data than they hold
+                let available = bytes.len().saturating_sub(body).min(size);
+                return Some(available as f64 / f64::from(rate));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos = body + size + (size & 1);
+    }
+    None
+}
+
+const MPEG1_LAYER3_KBPS: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const MPEG2_LAYER3_KBPS: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+fn mp3_duration(bytes: &[u8]) -> Option<f64> {
+    let mut start = 0;
+    if bytes.len() >= 10 && &bytes[0..3] == b"ID3" {
+        let size = bytes[6..10]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 7) | usize::from(b & 0x7f));
+        let footer = if bytes[5] & 0x10 != 0 { 10 } else { 0 };
+        start = 10 + size + footer;
+    }
+
+    let frame = (start..bytes.len().saturating_sub(3))
+        .find(|&i| bytes[i] == 0xFF && bytes[i + 1] & 0xE0 == 0xE0)?;
+    let version = (bytes[frame + 1] >> 3) & 0b11;
+    let layer = (bytes[frame + 1] >> 1) & 0b11;
+    let bitrate_index = usize::from(bytes[frame + 2] >> 4);
+    if layer != 0b01 || bitrate_index == 0 || bitrate_index >= 15 {
+        return None;
+    }
+    let kbps = match version {
+        0b11 => MPEG1_LAYER3_KBPS[bitrate_index],
+        0b10 | 0b00 => MPEG2_LAYER3_KBPS[bitrate_index],
+        _ => return None,
+    };
+
+    let audio_bytes = (bytes.len() - frame) as f64;
+    Some(audio_bytes * 8.0 / (f64::from(kbps) * 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(byte_rate: u32, data_len: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&((36 + data_len) as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+        bytes.extend_from_slice(&(byte_rate / 2).to_le_bytes()); // sample rate
+        bytes.extend_from_slice(&byte_rate.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data_len as u32).to_le_bytes());
+        bytes.extend(std::iter::repeat_n(0, data_len));
+        bytes
+    }
+
+    #[test]
+    fn supported_extensions() {
+        assert!(is_supported_audio("a.wav"));
+        assert!(is_supported_audio("a.MP3"));
+        assert!(is_supported_audio("a.ogg"));
+        assert!(!is_supported_audio("a.flac"));
+        assert!(!is_supported_audio("noext"));
+    }
+
+    #[test]
+    fn wav_duration_from_header() {
+        let bytes = wav(44100, 44100 * 3);
+        assert_eq!(clip_duration("a.wav", &bytes), Some(3.0));
+    }
+
+    #[test]
+    fn wav_duration_skips_extra_chunks() {
+        let mut bytes = wav(1000, 500);
+        // Insert a LIST chunk (odd size, padded) before "data"
+        let data_pos = bytes.windows(4).position(|w| w == b"data").unwrap();
+        let mut list = b"LIST".to_vec();
+        list.extend_from_slice(&3u32.to_le_bytes());
+        list.extend_from_slice(b"abc\0");
+        bytes.splice(data_pos..data_pos, list);
+        assert_eq!(clip_duration("a.wav", &bytes), Some(0.5));
+    }
+
+    #[test]
+    fn wav_invalid_header() {
+        assert_eq!(clip_duration("a.wav", b"not a wav"), None);
+    }
+
+    #[test]
+    fn mp3_duration_from_bitrate() {
+        // MPEG1 Layer III, 128 kbps, after a 10-byte ID3 tag with no body
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        bytes.resize(10 + 16_000, 0);
+        let duration = clip_duration("a.mp3", &bytes).unwrap();
+        assert!((duration - 1.0).abs() < 0.01, "duration {duration}");
+    }
+
+    #[test]
+    fn mp3_without_frame() {
+        assert_eq!(clip_duration("a.mp3", &[0u8; 64]), None);
+    }
+
+    #[test]
+    fn ogg_duration_unknown() {
+        assert_eq!(clip_duration("a.ogg", b"OggS"), None);
+    }
+}
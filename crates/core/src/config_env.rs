@@ -0,0 +1,362 @@
+use serde_json::Value;
+
+use crate::types::Config;
+
+/// Prefix shared by every config environment variable.
+const ENV_PREFIX: &str = "PEON_";
+
+/// Short aliases for fields whose full variable name is awkward to type.
+const ENV_ALIASES: &[(&str, &str)] = &[("PEON_PACK", "active_pack")];
+
+/// A config field whose value was taken from an environment variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvOverride {
+    /// Dotted path of the overridden field (e.g. `categories.greeting`).
+    pub field: String,
+    /// Name of the environment variable that provided the value.
+    pub var: String,
+}
+
+/// The effective config after layering environment variables over the file config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedConfig {
+    pub config: Config,
+    pub overrides: Vec<EnvOverride>,
+    /// Variables that matched a field but could not be applied.
+    pub errors: Vec<String>,
+}
+
+impl ResolvedConfig {
+    /// Returns the variable that overrides `field`, if any.
+    pub fn override_for(&self, field: &str) -> Option<&str> {
+        self.overrides
+            .iter()
+            .find(|o| o.field == field)
+            .map(|o| o.var.as_str())
+    }
+}
+
+/// Layer `PEON_*` environment variables over a config loaded from disk.
+///
+/// Every top-level field `foo_bar` can be set with `PEON_FOO_BAR`; object fields also
+/// accept one variable per key (`PEON_CATEGORIES_GREETING=false`). Values are parsed
+/// according to the type of the field they replace: booleans accept `1/0`, `true/false`,
+/// `yes/no` and `on/off`, lists accept comma-separated items or a JSON array.
+///
+/// A variable that does not produce a valid config is skipped and reported in `errors`.
+pub fn resolve_config(file_config: &Config, vars: &[(String, String)]) -> ResolvedConfig {
+    let mut resolved = ResolvedConfig {
+        config: file_config.clone(),
+        overrides: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let Ok(Value::Object(base)) = serde_json::to_value(file_config) else {
+        return resolved;
+    };
+
+    let mut vars: Vec<&(String, String)> = vars
+        .iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    vars.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut current = Value::Object(base.clone());
+
+    for (var, raw) in vars {
+        let Some(path) = field_path_for_var(var, &base) else {
+            continue;
+        };
+
+        let mut candidate = current.clone();
+        let slot = match path.as_slice() {
+            [field] => candidate.get_mut(field.as_str()),
+            [field, key] => candidate
+                .get_mut(field.as_str())
+                .and_then(Value::as_object_mut)
+                .map(|obj| obj.entry(key.clone()).or_insert(Value::Null)),
+            _ => None,
+        };
+        let Some(slot) = slot else {
+            continue;
+        };
+
+        match parse_env_value(raw, slot) {
+            Some(value) => *slot = value,
+            None => {
+                resolved.errors.push(format!(
+                    "{var}: cannot parse \"{raw}\" for {}",
+                    path.join(".")
+                ));
+                continue;
+            }
+        }
+
+        match serde_json::from_value::<Config>(candidate.clone()) {
+            Ok(config) => {
+                resolved.config = config;
+                current = candidate;
+                resolved.overrides.push(EnvOverride {
+                    field: path.join("."),
+                    var: var.clone(),
+                });
+            }
+            Err(e) => resolved.errors.push(format!("{var}: {e}")),
+        }
+    }
+
+    resolved
+}
+
+/// Map an environment variable name onto a config field path.
+fn field_path_for_var(var: &str, base: &serde_json::Map<String, Value>) -> Option<Vec<String>> {
+    if let Some((_, field)) = ENV_ALIASES.iter().find(|(alias, _)| *alias == var) {
+        return Some(vec![field.to_string()]);
+    }
+
+    let rest = var.strip_prefix(ENV_PREFIX)?;
+    for (field, value) in base {
+        let upper = field.to_ascii_uppercase();
+        if rest == upper {
+            return Some(vec![field.clone()]);
+        }
+        if value.is_object() {
+            if let Some(key) = rest
+                .strip_prefix(upper.as_str())
+                .and_then(|k| k.strip_prefix('_'))
+            {
+                if !key.is_empty() {
+                    return Some(vec![field.clone(), key.to_ascii_lowercase()]);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Parse a raw environment value using the JSON type of the value it replaces.
+fn parse_env_value(raw: &str, existing: &Value) -> Option<Value> {
+    let trimmed = raw.trim();
+    match existing {
+        Value::Bool(_) => match trimmed.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Some(Value::Bool(true)),
+            "0" | "false" | "no" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Value::Number(_) => {
+            if let Ok(n) = trimmed.parse::<i64>() {
+                Some(Value::from(n))
+            } else {
+                trimmed
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+            }
+        }
+        Value::String(_) => Some(Value::String(raw.to_string())),
+        Value::Array(_) => {
+            if trimmed.starts_with('[') {
+                serde_json::from_str(trimmed).ok()
+            } else {
+                Some(Value::Array(
+                    trimmed
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(|s| Value::String(s.to_string()))
+                        .collect(),
+                ))
+            }
+        }
+        Value::Object(_) => serde_json::from_str(trimmed).ok(),
+        Value::Null => {
+            Some(serde_json::from_str(trimmed).unwrap_or_else(|_| Value::String(raw.to_string())))
+        }
+    }
+}
+
+/// Format the effective config for `peon config show`, marking env-sourced values.
+pub fn format_config(resolved: &ResolvedConfig) -> String {
+    let mut out = String::new();
+    let Ok(Value::Object(fields)) = serde_json::to_value(&resolved.config) else {
+        return out;
+    };
+
+    for (field, value) in &fields {
+        match value {
+            Value::Object(entries) if !entries.is_empty() => {
+                out.push_str(&format!("{field}:\n"));
+                for (key, value) in entries {
+                    let path = format!("{field}.{key}");
+                    push_config_line(&mut out, &format!("  {key}"), value, resolved, &path);
+                }
+            }
+            _ => push_config_line(&mut out, field, value, resolved, field),
+        }
+    }
+
+    if !resolved.errors.is_empty() {
+        out.push_str("\nIgnored environment variables:\n");
+        for error in &resolved.errors {
+            out.push_str(&format!("  {error}\n"));
+        }
+    }
+
+    out
+}
+
+fn push_config_line(
+    out: &mut String,
+    label: &str,
+    value: &Value,
+    resolved: &ResolvedConfig,
+    path: &str,
+) {
+    let rendered = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    match resolved.override_for(path) {
+        Some(var) => out.push_str(&format!("{label:24} {rendered}  (env: {var})\n")),
+        None => out.push_str(&format!("{label:24} {rendered}\n")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn no_vars_keeps_file_config() {
+        let config = Config::default();
+        let resolved = resolve_config(&config, &[]);
+        assert_eq!(resolved.config, config);
+        assert!(resolved.overrides.is_empty());
+        assert!(resolved.errors.is_empty());
+    }
+
+    #[test]
+    fn scalar_overrides() {
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[
+                ("PEON_VOLUME", "0.9"),
+                ("PEON_ENABLED", "off"),
+                ("PEON_ANNOYED_THRESHOLD", "7"),
+            ]),
+        );
+        assert_eq!(resolved.config.volume, 0.9);
+        assert!(!resolved.config.enabled);
+        assert_eq!(resolved.config.annoyed_threshold, 7);
+        assert_eq!(resolved.override_for("volume"), Some("PEON_VOLUME"));
+        assert_eq!(resolved.override_for("enabled"), Some("PEON_ENABLED"));
+    }
+
+    #[test]
+    fn pack_alias_and_full_name() {
+        let resolved = resolve_config(&Config::default(), &vars(&[("PEON_PACK", "sc_kerrigan")]));
+        assert_eq!(resolved.config.active_pack, "sc_kerrigan");
+        assert_eq!(resolved.override_for("active_pack"), Some("PEON_PACK"));
+
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[("PEON_ACTIVE_PACK", "peasant")]),
+        );
+        assert_eq!(resolved.config.active_pack, "peasant");
+    }
+
+    #[test]
+    fn category_overrides() {
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[
+                ("PEON_CATEGORIES_GREETING", "false"),
+                ("PEON_CATEGORIES_RESOURCE_LIMIT", "0"),
+            ]),
+        );
+        assert!(!resolved.config.categories.greeting);
+        assert!(!resolved.config.categories.resource_limit);
+        assert!(resolved.config.categories.complete);
+        assert_eq!(
+            resolved.override_for("categories.resource_limit"),
+            Some("PEON_CATEGORIES_RESOURCE_LIMIT")
+        );
+    }
+
+    #[test]
+    fn list_overrides() {
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[("PEON_PACK_ROTATION", "peon, sc_kerrigan")]),
+        );
+        assert_eq!(resolved.config.pack_rotation, vec!["peon", "sc_kerrigan"]);
+
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[("PEON_PACK_ROTATION", r#"["peasant"]"#)]),
+        );
+        assert_eq!(resolved.config.pack_rotation, vec!["peasant"]);
+    }
+
+    #[test]
+    fn invalid_value_is_reported_and_skipped() {
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[("PEON_VOLUME", "loud"), ("PEON_ENABLED", "maybe")]),
+        );
+        assert_eq!(resolved.config, Config::default());
+        assert_eq!(resolved.errors.len(), 2);
+        assert!(resolved.overrides.is_empty());
+    }
+
+    #[test]
+    fn wrong_numeric_type_is_rejected() {
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[("PEON_ANNOYED_THRESHOLD", "2.5")]),
+        );
+        assert_eq!(resolved.config.annoyed_threshold, 3);
+        assert_eq!(resolved.errors.len(), 1);
+    }
+
+    #[test]
+    fn unrelated_vars_ignored() {
+        let resolved = resolve_config(
+            &Config::default(),
+            &vars(&[
+                ("PEON_PACKS", "/tmp/packs"),
+                ("HOME", "/root"),
+                ("PEON_NOPE", "1"),
+            ]),
+        );
+        assert_eq!(resolved.config, Config::default());
+        assert!(resolved.overrides.is_empty());
+        assert!(resolved.errors.is_empty());
+    }
+
+    #[test]
+    fn format_marks_env_values() {
+        let resolved = resolve_config(&Config::default(), &vars(&[("PEON_VOLUME", "0.2")]));
+        let output = format_config(&resolved);
+        assert!(output.contains("volume"));
+        assert!(output.contains("0.2  (env: PEON_VOLUME)"));
+        assert!(output.contains("categories:\n"));
+        assert!(!output.contains("enabled                  true  (env"));
+    }
+
+    #[test]
+    fn format_lists_errors() {
+        let resolved = resolve_config(&Config::default(), &vars(&[("PEON_VOLUME", "loud")]));
+        let output = format_config(&resolved);
+        assert!(output.contains("Ignored environment variables:"));
+        assert!(output.contains("PEON_VOLUME"));
+    }
+}
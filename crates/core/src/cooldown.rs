@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::types::{Config, SuppressedPlay};
+
+/// How many suppressed plays the state keeps for `peon status`.
+pub const MAX_SUPPRESSED_PLAYS: usize = 20;
+
+/// Decide whether a sound for `category` may play at `now`, given when each category last
+/// played in any session. Returns why it's suppressed otherwise.
+///
+/// A category waits out its own cooldown. Any sound also waits out the global minimum
+/// gap after a sound of equal or higher priority, so a `permission` alert is never held
+/// back by a recent `complete`.
+pub fn check_sound_gate(
+    config: &Config,
+    last_sound_at: &HashMap<String, f64>,
+    category: &str,
+    now: f64,
+) -> Result<(), String> {
+    let cooldown = config.cooldown_for(category);
+    if let Some(last) = last_sound_at.get(category) {
+        if now - last < cooldown {
+            return Err(format!("{category} cooldown ({cooldown}s)"));
+        }
+    }
+    let priority = config.priority_of(category);
+    let blocker = last_sound_at
+        .iter()
+        .filter(|(other, at)| {
+            now - **at < config.min_sound_gap_seconds && config.priority_of(other) >= priority
+        })
+        .max_by(|a, b| a.1.total_cmp(b.1));
+    match blocker {
+        Some((other, _)) => Err(format!(
+            "within {}s of {other}",
+            config.min_sound_gap_seconds
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Forget categories whose cooldown and the global gap have both run out, since they
+/// can no longer hold anything back.
+pub fn prune_sound_times(config: &Config, last_sound_at: &mut HashMap<String, f64>, now: f64) {
+    last_sound_at.retain(|category, at| {
+        now - *at
+            < config
+                .cooldown_for(category)
+                .max(config.min_sound_gap_seconds)
+    });
+}
+
+/// Add a suppressed play to the log, keeping the newest [`MAX_SUPPRESSED_PLAYS`].
+pub fn record_suppressed(log: &mut Vec<SuppressedPlay>, play: SuppressedPlay) {
+    log.push(play);
+    if log.len() > MAX_SUPPRESSED_PLAYS {
+        log.drain(..log.len() - MAX_SUPPRESSED_PLAYS);
+    }
+}
+
+/// Describe the most recent suppressed plays, newest first, one per line.
+pub fn format_suppressed(log: &[SuppressedPlay], now: f64, limit: usize) -> String {
+    log.iter()
+        .rev()
+        .take(limit)
+        .map(|play| {
+            let ago = (now - play.at).max(0.0).round();
+            format!(
+                "  {} ({}s ago, session {}): {}\n",
+                play.category, ago, play.session_id, play.reason
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(entries: &[(&str, f64)]) -> HashMap<String, f64> {
+        entries.iter().map(|(c, at)| (c.to_string(), *at)).collect()
+    }
+
+    #[test]
+    fn category_waits_out_its_cooldown() {
+        let config = Config::default();
+        let last = played(&[("complete", 95.0)]);
+        assert_eq!(
+            check_sound_gate(&config, &last, "complete", 100.0),
+            Err("complete cooldown (10s)".to_string())
+        );
+        assert!(check_sound_gate(&config, &last, "complete", 106.0).is_ok());
+    }
+
+    #[test]
+    fn global_gap_respects_priority() {
+        let config = Config::default();
+        let last = played(&[("complete", 99.5)]);
+        assert!(check_sound_gate(&config, &last, "permission", 100.0).is_ok());
+        assert_eq!(
+            check_sound_gate(&config, &last, "acknowledge", 100.0),
+            Err("within 2s of complete".to_string())
+        );
+        let last = played(&[("permission", 99.5)]);
+        assert!(check_sound_gate(&config, &last, "complete", 100.0).is_err());
+        assert!(check_sound_gate(&config, &last, "error", 102.0).is_ok());
+    }
+
+    #[test]
+    fn configured_cooldowns_and_priorities_override_defaults() {
+        let mut config = Config::default();
+        config.category_cooldowns.insert("complete".into(), 0.0);
+        config.category_priorities.insert("acknowledge".into(), 5);
+        config.min_sound_gap_seconds = 0.0;
+        let last = played(&[("complete", 99.0)]);
+        assert!(check_sound_gate(&config, &last, "complete", 100.0).is_ok());
+        assert_eq!(config.priority_of("acknowledge"), 5);
+        assert_eq!(
+            config.priority_of("annoyed_2"),
+            config.priority_of("annoyed")
+        );
+    }
+
+    #[test]
+    fn annoyance_tiers_share_annoyed_cooldown() {
+        let mut config = Config::default();
+        config.category_cooldowns.insert("annoyed".into(), 30.0);
+        assert_eq!(config.cooldown_for("annoyed_2"), 30.0);
+        config.category_cooldowns.insert("annoyed_2".into(), 5.0);
+        assert_eq!(config.cooldown_for("annoyed_2"), 5.0);
+        assert_eq!(config.cooldown_for("annoyed_1"), 30.0);
+    }
+
+    #[test]
+    fn expired_sound_times_are_pruned() {
+        let config = Config::default();
+        let mut last = played(&[("complete", 95.0), ("error", 97.0), ("greeting", 80.0)]);
+        prune_sound_times(&config, &mut last, 100.0);
+        assert_eq!(last, played(&[("complete", 95.0)]));
+    }
+
+    #[test]
+    fn suppressed_log_is_bounded() {
+        let mut log = Vec::new();
+        for i in 0..30 {
+            record_suppressed(
+                &mut log,
+                SuppressedPlay {
+                    category: "complete".into(),
+                    session_id: format!("s{i}"),
+                    at: f64::from(i),
+                    reason: "complete cooldown (10s)".into(),
+                },
+            );
+        }
+        assert_eq!(log.len(), MAX_SUPPRESSED_PLAYS);
+        assert_eq!(log[0].session_id, "s10");
+        assert_eq!(
+            format_suppressed(&log, 32.0, 2),
+            "  complete (3s ago, session s29): complete cooldown (10s)\n  \
+             complete (4s ago, session s28): complete cooldown (10s)\n"
+        );
+    }
+}
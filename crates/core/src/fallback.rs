@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+
+use crate::types::{Category, Config, Manifest};
+
+/// Where a routed category will actually be played from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedCategory<'a> {
+    /// Pack that provides the sounds.
+    pub pack: &'a str,
+    /// Category that provides the sounds (may differ from the requested one).
+    pub category: &'a str,
+    pub sounds: &'a Category,
+}
+
+/// Expand the fallback chain for `category`, starting with the category itself.
+///
+/// Fallbacks are followed transitively (`resource_limit → error → permission`) in
+/// depth-first order; each category appears at most once, so cycles are harmless.
+pub fn fallback_chain(category: &str, config: &Config) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::new();
+    push_chain(category, config, &mut chain, &mut seen);
+    chain
+}
+
+fn push_chain(
+    category: &str,
+    config: &Config,
+    chain: &mut Vec<String>,
+    seen: &mut HashSet<String>,
+) {
+    if !seen.insert(category.to_string()) {
+        return;
+    }
+    chain.push(category.to_string());
+    for next in config.fallbacks_for(category) {
+        push_chain(&next, config, chain, seen);
+    }
+}
+
+/// Find the sounds to play for `category` across `packs`, tried in order.
+///
+/// Each pack is searched along the whole fallback chain before moving on to the next
+/// pack, so the active pack's own `error` sounds win over a backup pack's
+/// `resource_limit`. Categories with no sounds count as missing.
+pub fn resolve_category<'a>(
+    packs: &[(&'a str, &'a Manifest)],
+    category: &str,
+    config: &Config,
+) -> Option<ResolvedCategory<'a>> {
+    let chain = fallback_chain(category, config);
+    packs.iter().find_map(|(pack, manifest)| {
+        chain.iter().find_map(|name| {
+            manifest
+                .categories
+                .get_key_value(name.as_str())
+                .filter(|(_, cat)| !cat.sounds.is_empty())
+                .map(|(key, cat)| ResolvedCategory {
+                    pack,
+                    category: key.as_str(),
+                    sounds: cat,
+                })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Sound;
+    use std::collections::HashMap;
+
+    fn manifest(name: &str, categories: &[&str]) -> Manifest {
+        let categories = categories
+            .iter()
+            .map(|c| {
+                (
+                    c.to_string(),
+                    Category {
+                        sounds: vec![Sound {
+                            file: format!("{c}.wav"),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        Manifest {
+            name: name.into(),
+            categories,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chain_follows_defaults_transitively() {
+        let chain = fallback_chain("resource_limit", &Config::default());
+        assert_eq!(chain, vec!["resource_limit", "error", "permission"]);
+    }
+
+    #[test]
+    fn chain_without_fallbacks_is_just_the_category() {
+        assert_eq!(
+            fallback_chain("greeting", &Config::default()),
+            vec!["greeting"]
+        );
+    }
+
+    #[test]
+    fn chain_survives_cycles() {
+        let config = Config {
+            category_fallbacks: HashMap::from([
+                ("a".to_string(), vec!["b".to_string()]),
+                ("b".to_string(), vec!["a".to_string(), "c".to_string()]),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(fallback_chain("a", &config), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn present_category_is_used_directly() {
+        let peon = manifest("peon", &["resource_limit", "error"]);
+        let packs = [("peon", &peon)];
+        let resolved = resolve_category(&packs, "resource_limit", &Config::default()).unwrap();
+        assert_eq!(resolved.pack, "peon");
+        assert_eq!(resolved.category, "resource_limit");
+    }
+
+    #[test]
+    fn missing_category_falls_back_in_same_pack() {
+        let peon = manifest("peon", &["permission"]);
+        let packs = [("peon", &peon)];
+        let resolved = resolve_category(&packs, "resource_limit", &Config::default()).unwrap();
+        assert_eq!(resolved.category, "permission");
+    }
+
+    #[test]
+    fn empty_category_counts_as_missing() {
+        let mut peon = manifest("peon", &["acknowledge"]);
+        peon.categories
+            .insert("annoyed".into(), Category::default());
+        let packs = [("peon", &peon)];
+        let resolved = resolve_category(&packs, "annoyed", &Config::default()).unwrap();
+        assert_eq!(resolved.category, "acknowledge");
+    }
+
+    #[test]
+    fn backup_pack_used_when_chain_exhausted() {
+        let primary = manifest("primary", &["greeting"]);
+        let backup = manifest("backup", &["annoyed"]);
+        let packs = [("primary", &primary), ("backup", &backup)];
+        let resolved = resolve_category(&packs, "annoyed", &Config::default()).unwrap();
+        assert_eq!(resolved.pack, "backup");
+        assert_eq!(resolved.category, "annoyed");
+    }
+
+    #[test]
+    fn primary_fallback_beats_backup_exact_match() {
+        let primary = manifest("primary", &["acknowledge"]);
+        let backup = manifest("backup", &["annoyed"]);
+        let packs = [("primary", &primary), ("backup", &backup)];
+        let resolved = resolve_category(&packs, "annoyed", &Config::default()).unwrap();
+        assert_eq!(resolved.pack, "primary");
+        assert_eq!(resolved.category, "acknowledge");
+    }
+
+    #[test]
+    fn nothing_found_returns_none() {
+        let peon = manifest("peon", &["greeting"]);
+        let packs = [("peon", &peon)];
+        assert!(resolve_category(&packs, "complete", &Config::default()).is_none());
+    }
+}
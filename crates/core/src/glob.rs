@@ -0,0 +1,65 @@
+/// Match `text` against a shell-style pattern supporting `*` (any run of characters)
+/// and `?` (any single character). Matching is case-sensitive.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_match() {
+        assert!(glob_match("PeonYes1.wav", "PeonYes1.wav"));
+        assert!(!glob_match("PeonYes1.wav", "PeonYes2.wav"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob_match("*Ready*", "PeonReady1.wav"));
+        assert!(glob_match("Peon*", "Peon"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("/home/*/work/*", "/home/me/work/project"));
+        assert!(!glob_match("*Ready*", "PeonYes1.wav"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_char() {
+        assert!(glob_match("PeonYes?.wav", "PeonYes3.wav"));
+        assert!(!glob_match("PeonYes?.wav", "PeonYes10.wav"));
+    }
+
+    #[test]
+    fn backtracks_after_partial_match() {
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("*a*b", "xaxxa"));
+    }
+
+    #[test]
+    fn case_sensitive() {
+        assert!(!glob_match("*ready*", "PeonReady1.wav"));
+    }
+}
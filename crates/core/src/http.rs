@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+/// Longest server-requested wait (`Retry-After`) that is honoured before giving up.
+pub const MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+/// First backoff delay; doubled on every further attempt.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(16);
+
+/// Host of a URL without userinfo, port or IPv6 brackets, lowercased.
+pub fn url_host(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host_port.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host_port.split(':').next().unwrap_or_default(),
+    };
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// Proxy to use for a URL scheme, following curl's variables: `HTTPS_PROXY` for https,
+/// `HTTP_PROXY` for http, then `ALL_PROXY`. Lowercase names are accepted too.
+pub fn proxy_for(scheme: &str, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let names: &[&str] = match scheme {
+        "https" => &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+        "http" => &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+        _ => &["ALL_PROXY", "all_proxy"],
+    };
+    names
+        .iter()
+        .filter_map(|name| env(name))
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+/// Whether `host` is excluded from proxying by a `NO_PROXY` list: comma or space
+/// separated hosts or domain suffixes (`example.com` and `.example.com` both match
+/// subdomains), optional ports are ignored, and `*` matches everything.
+pub fn bypasses_proxy(no_proxy: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    no_proxy
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+            let entry = match entry.strip_prefix('[') {
+                Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+                None if entry.matches(':').count() == 1 => {
+                    entry.split(':').next().unwrap_or_default()
+                }
+                None => entry,
+            };
+            let entry = entry.to_ascii_lowercase();
+            host == entry || host.ends_with(&format!(".{entry}"))
+        })
+}
+
+/// How long to wait before retrying a response with `status`, or `None` to give up.
+///
+/// Server errors (5xx other than 501) and rate limits (429, or 403 with `Retry-After`,
+/// GitHub's secondary limit) are retried. A `Retry-After` in seconds is honoured up to
+/// [`MAX_RETRY_WAIT`]; otherwise the delay doubles from one second per `attempt`
+/// (starting at 0). An exhausted hourly quota (`X-RateLimit-Remaining: 0`) without
+/// `Retry-After` won't lift within the backoff, so it isn't retried.
+pub fn retry_delay(
+    status: u16,
+    retry_after: Option<&str>,
+    quota_exhausted: bool,
+    attempt: u32,
+) -> Option<Duration> {
+    let retry_after = retry_after.and_then(|value| value.trim().parse::<u64>().ok());
+    let retryable = match status {
+        501 => false,
+        500..=599 | 429 => true,
+        403 => retry_after.is_some(),
+        _ => false,
+    };
+    if !retryable {
+        return None;
+    }
+    match retry_after.map(Duration::from_secs) {
+        Some(wait) if wait > MAX_RETRY_WAIT => None,
+        Some(wait) => Some(wait),
+        None if quota_exhausted => None,
+        None => Some(backoff(attempt)),
+    }
+}
+
+/// Exponential backoff before retry `attempt` (starting at 0): one second, doubling up to
+/// a cap. Also used for requests that failed before a response, like a dropped connection.
+pub fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// Whether a `GITHUB_TOKEN` may be sent to `url`: only to github.com, its API, or the
+/// host of the configured GitHub API (e.g. a GitHub Enterprise server).
+pub fn is_github_url(url: &str, api_url: &str) -> bool {
+    let Some(host) = url_host(url) else {
+        return false;
+    };
+    host == "github.com" || host == "api.github.com" || url_host(api_url) == Some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_url_hosts() {
+        assert_eq!(
+            url_host("https://api.github.com/repos/a/b").as_deref(),
+            Some("api.github.com")
+        );
+        assert_eq!(
+            url_host("http://user:pw@Proxy.Corp:8080").as_deref(),
+            Some("proxy.corp")
+        );
+        assert_eq!(url_host("http://[::1]:80/x").as_deref(), Some("::1"));
+        assert_eq!(url_host("not a url"), None);
+    }
+
+    #[test]
+    fn picks_proxy_by_scheme() {
+        let env = |name: &str| match name {
+            "https_proxy" => Some("http://secure:3128".to_string()),
+            "ALL_PROXY" => Some("socks5://any:1080".to_string()),
+            "HTTP_PROXY" => Some(" ".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            proxy_for("https", env).as_deref(),
+            Some("http://secure:3128")
+        );
+        assert_eq!(proxy_for("http", env).as_deref(), Some("socks5://any:1080"));
+        assert_eq!(proxy_for("https", |_| None), None);
+    }
+
+    #[test]
+    fn no_proxy_matches_hosts_and_suffixes() {
+        let no_proxy = "localhost, .corp.example,github.internal:8443 127.0.0.1";
+        assert!(bypasses_proxy(no_proxy, "localhost"));
+        assert!(bypasses_proxy(no_proxy, "git.corp.example"));
+        assert!(bypasses_proxy(no_proxy, "corp.example"));
+        assert!(bypasses_proxy(no_proxy, "GITHUB.internal"));
+        assert!(bypasses_proxy(no_proxy, "127.0.0.1"));
+        assert!(!bypasses_proxy(no_proxy, "notcorp.example"));
+        assert!(!bypasses_proxy(no_proxy, "api.github.com"));
+        assert!(bypasses_proxy("*", "anything"));
+        assert!(!bypasses_proxy("", "anything"));
+    }
+
+    #[test]
+    fn retries_server_errors_with_backoff() {
+        assert_eq!(
+            retry_delay(503, None, false, 0),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            retry_delay(502, None, false, 2),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(retry_delay(500, None, false, 10), Some(MAX_BACKOFF));
+        assert_eq!(retry_delay(501, None, false, 0), None);
+        assert_eq!(retry_delay(404, None, false, 0), None);
+    }
+
+    #[test]
+    fn honours_retry_after_for_rate_limits() {
+        assert_eq!(
+            retry_delay(429, Some("3"), false, 0),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry_delay(429, None, false, 1),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_delay(403, Some("5"), false, 0),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(retry_delay(403, None, false, 0), None);
+        assert_eq!(retry_delay(429, Some("3600"), false, 0), None);
+        assert_eq!(retry_delay(429, None, true, 0), None);
+        assert_eq!(
+            retry_delay(429, Some("2"), true, 0),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn scopes_github_token() {
+        let api = "https://api.github.com";
+        assert!(is_github_url("https://api.github.com/repos/a/b", api));
+        assert!(is_github_url(
+            "https://github.com/a/b/releases/download/v1/x",
+            api
+        ));
+        assert!(!is_github_url("https://example.com/index.json", api));
+        assert!(is_github_url(
+            "https://ghe.corp/api/v3/repos/a/b",
+            "https://ghe.corp/api/v3"
+        ));
+    }
+}
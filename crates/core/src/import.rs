@@ -0,0 +1,312 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::clip::is_supported_audio;
+use crate::glob::glob_match;
+use crate::types::{Category, Manifest, Sound, KNOWN_CATEGORIES};
+
+/// File name keywords that suggest a category, checked in order (case-insensitive).
+const CATEGORY_HINTS: &[(&str, &[&str])] = &[
+    ("annoyed", &["angry", "annoy", "pissed", "stop"]),
+    ("resource_limit", &["warcry", "limit", "gold", "resource"]),
+    ("error", &["error", "fail", "death"]),
+    ("greeting", &["ready", "hello", "greet", "reporting"]),
+    ("complete", &["done", "complete", "finish"]),
+    ("permission", &["what", "help", "need"]),
+    ("acknowledge", &["yes", "okay", "okie", "work", "ack"]),
+];
+
+/// Guess a category from an audio file name (`PeonAngry1.wav` → `annoyed`).
+pub fn guess_category(file_name: &str) -> Option<&'static str> {
+    let stem = file_stem(file_name).to_ascii_lowercase();
+    CATEGORY_HINTS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|k| stem.contains(k)))
+        .map(|(category, _)| *category)
+}
+
+/// Build a placeholder voice line from a file name (`PeonReady1.wav` → `Peon ready`).
+pub fn line_from_file_name(file_name: &str) -> String {
+    let stem = file_stem(file_name).trim_end_matches(|c: char| c.is_ascii_digit());
+
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for c in stem.chars() {
+        if c == '_' || c == '-' || c == ' ' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else if c.is_uppercase() && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+            current.push(c);
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    let sentence = words
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut chars = sentence.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn file_stem(file_name: &str) -> &str {
+    file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// User-supplied file → category assignments for `peon pack import --mapping`.
+///
+/// The mapping file is a JSON object whose keys are file names or glob patterns and
+/// whose values are a category or a list of categories:
+///
+/// ```json
+/// { "*Ready*": "greeting", "PeonYes3.wav": ["acknowledge", "complete"] }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CategoryMapping {
+    rules: BTreeMap<String, Vec<String>>,
+}
+
+impl CategoryMapping {
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        let raw: BTreeMap<String, OneOrMany> = serde_json::from_str(json)?;
+        let rules = raw
+            .into_iter()
+            .map(|(pattern, value)| {
+                let categories = match value {
+                    OneOrMany::One(c) => vec![c],
+                    OneOrMany::Many(cs) => cs,
+                };
+                (pattern, categories)
+            })
+            .collect();
+        Ok(Self { rules })
+    }
+
+    /// Categories for `file_name`: an exact entry wins, then the longest matching pattern.
+    pub fn categories_for(&self, file_name: &str) -> Option<&[String]> {
+        if let Some(categories) = self.rules.get(file_name) {
+            return Some(categories);
+        }
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, file_name))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, categories)| categories.as_slice())
+    }
+}
+
+/// One audio file to be imported into a pack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportEntry {
+    pub file: String,
+    /// Categories the file will be listed under. Empty means it won't be imported.
+    pub categories: Vec<String>,
+    pub line: String,
+}
+
+/// Decide categories and voice lines for a folder of audio files.
+///
+/// Categories come from `mapping` when it covers the file, otherwise from file name
+/// heuristics. Lines come from `transcripts` (keyed by file name without extension,
+/// read from sidecar `.txt` files), otherwise from the file name. Files with
+/// unsupported formats are left out.
+pub fn plan_import(
+    files: &[String],
+    transcripts: &HashMap<String, String>,
+    mapping: Option<&CategoryMapping>,
+) -> Vec<ImportEntry> {
+    let mut files: Vec<&String> = files.iter().filter(|f| is_supported_audio(f)).collect();
+    files.sort();
+
+    files
+        .into_iter()
+        .map(|file| {
+            let categories = match mapping.and_then(|m| m.categories_for(file)) {
+                Some(categories) => categories.to_vec(),
+                None => guess_category(file)
+                    .map(|c| vec![c.to_string()])
+                    .unwrap_or_default(),
+            };
+            let line = transcripts
+                .get(file_stem(file))
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .unwrap_or_else(|| line_from_file_name(file));
+            ImportEntry {
+                file: file.clone(),
+                categories,
+                line,
+            }
+        })
+        .collect()
+}
+
+/// Build a manifest from planned import entries.
+pub fn build_manifest(name: &str, display_name: &str, entries: &[ImportEntry]) -> Manifest {
+    let mut categories: HashMap<String, Category> = HashMap::new();
+    for entry in entries {
+        for category in &entry.categories {
+            categories
+                .entry(category.clone())
+                .or_default()
+                .sounds
+                .push(Sound {
+                    file: entry.file.clone(),
+                    line: entry.line.clone(),
+                    ..Default::default()
+                });
+        }
+    }
+    Manifest {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        categories,
+        ..Default::default()
+    }
+}
+
+/// A starter manifest with every known category present and empty.
+pub fn scaffold_manifest(name: &str, display_name: &str) -> Manifest {
+    Manifest {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        version: Some("0.1.0".to_string()),
+        categories: KNOWN_CATEGORIES
+            .iter()
+            .map(|c| (c.to_string(), Category::default()))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(files: &[&str]) -> Vec<String> {
+        files.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn guesses_from_warcraft_style_names() {
+        assert_eq!(guess_category("PeonReady1.wav"), Some("greeting"));
+        assert_eq!(guess_category("PeonYes3.wav"), Some("acknowledge"));
+        assert_eq!(guess_category("PeonYesAttack1.wav"), Some("acknowledge"));
+        assert_eq!(guess_category("PeonAngry2.wav"), Some("annoyed"));
+        assert_eq!(guess_category("PeonWhat4.wav"), Some("permission"));
+        assert_eq!(guess_category("PeonWarcry1.wav"), Some("resource_limit"));
+        assert_eq!(guess_category("PeonDeath.wav"), Some("error"));
+        assert_eq!(guess_category("JobsDone.mp3"), Some("complete"));
+        assert_eq!(guess_category("Random.wav"), None);
+    }
+
+    #[test]
+    fn line_from_camel_case_name() {
+        assert_eq!(line_from_file_name("PeonReady1.wav"), "Peon ready");
+        assert_eq!(line_from_file_name("im_ready.mp3"), "Im ready");
+        assert_eq!(line_from_file_name("123.wav"), "");
+    }
+
+    #[test]
+    fn mapping_exact_beats_glob() {
+        let mapping = CategoryMapping::parse(
+            r#"{"*Yes*": "acknowledge", "PeonYes3.wav": ["acknowledge", "complete"], "*": "error"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mapping.categories_for("PeonYes3.wav"),
+            Some(&["acknowledge".to_string(), "complete".to_string()][..])
+        );
+        assert_eq!(
+            mapping.categories_for("PeonYes1.wav"),
+            Some(&["acknowledge".to_string()][..])
+        );
+        assert_eq!(
+            mapping.categories_for("Other.wav"),
+            Some(&["error".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn mapping_rejects_invalid_json() {
+        assert!(CategoryMapping::parse(r#"{"a.wav": 3}"#).is_err());
+    }
+
+    #[test]
+    fn plan_uses_mapping_then_heuristics() {
+        let mapping = CategoryMapping::parse(r#"{"Random.wav": "greeting"}"#).unwrap();
+        let plan = plan_import(
+            &names(&["PeonAngry1.wav", "Random.wav", "Unknown.wav"]),
+            &HashMap::new(),
+            Some(&mapping),
+        );
+        assert_eq!(plan[0].categories, vec!["annoyed"]);
+        assert_eq!(plan[1].categories, vec!["greeting"]);
+        assert!(plan[2].categories.is_empty());
+    }
+
+    #[test]
+    fn plan_uses_transcripts_for_lines() {
+        let transcripts = HashMap::from([("PeonYes3".to_string(), "Work, work.\n".to_string())]);
+        let plan = plan_import(&names(&["PeonYes3.wav"]), &transcripts, None);
+        assert_eq!(plan[0].line, "Work, work.");
+    }
+
+    #[test]
+    fn plan_skips_unsupported_files() {
+        let plan = plan_import(
+            &names(&["PeonYes3.wav", "notes.txt", "cover.png"]),
+            &HashMap::new(),
+            None,
+        );
+        assert_eq!(plan.len(), 1);
+    }
+
+    #[test]
+    fn build_manifest_groups_by_category() {
+        let plan = plan_import(
+            &names(&[
+                "PeonReady1.wav",
+                "PeonYes1.wav",
+                "PeonYes2.wav",
+                "Random.wav",
+            ]),
+            &HashMap::new(),
+            None,
+        );
+        let manifest = build_manifest("orc", "Orc", &plan);
+        assert_eq!(manifest.name, "orc");
+        assert_eq!(manifest.categories["greeting"].sounds.len(), 1);
+        assert_eq!(manifest.categories["acknowledge"].sounds.len(), 2);
+        assert_eq!(manifest.categories.len(), 2);
+    }
+
+    #[test]
+    fn scaffold_has_all_known_categories() {
+        let manifest = scaffold_manifest("mine", "My Pack");
+        for category in KNOWN_CATEGORIES {
+            assert!(manifest.categories[*category].sounds.is_empty());
+        }
+        assert_eq!(manifest.version.as_deref(), Some("0.1.0"));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::archive::is_valid_pack_name;
+use crate::types::{Category, Manifest};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum InheritError {
+    #[error("pack \"{0}\" not found")]
+    NotFound(String),
+    #[error("pack \"{pack}\" extends \"{base}\", which is not installed")]
+    MissingBase { pack: String, base: String },
+    #[error("pack \"{pack}\" extends \"{base}\", which is not a valid pack name")]
+    InvalidBase { pack: String, base: String },
+    #[error("pack inheritance cycle: {0}")]
+    Cycle(String),
+}
+
+/// Resolve a pack's manifest, flattening its `extends` chain.
+///
+/// `load` returns the raw manifest for an installed pack directory. Categories of the
+/// extending pack replace the base's categories of the same name, or are added to them
+/// when marked `append`. Sounds and the icon inherited from a base are rewritten to
+/// `"base:File.wav"` so they keep pointing at the base pack's files.
+///
+/// Attribution (`display_name`, `author`, `license`, `source`, `icon`) is inherited
+/// where the extending pack leaves it unset. `version` never is: it versions the
+/// extending pack itself.
+pub fn resolve_manifest(
+    name: &str,
+    load: &impl Fn(&str) -> Option<Manifest>,
+) -> Result<Manifest, InheritError> {
+    let manifest = load(name).ok_or_else(|| InheritError::NotFound(name.to_string()))?;
+    resolve_chain(name, manifest, load, &mut vec![name.to_string()])
+}
+
+fn resolve_chain(
+    name: &str,
+    manifest: Manifest,
+    load: &impl Fn(&str) -> Option<Manifest>,
+    stack: &mut Vec<String>,
+) -> Result<Manifest, InheritError> {
+    let Some(base_name) = manifest.extends.clone() else {
+        return Ok(manifest);
+    };
+    // The base is loaded from the packs directory by name
+    if !is_valid_pack_name(&base_name) {
+        return Err(InheritError::InvalidBase {
+            pack: name.to_string(),
+            base: base_name,
+        });
+    }
+
+    if stack.contains(&base_name) {
+        stack.push(base_name);
+        return Err(InheritError::Cycle(stack.join(" -> ")));
+    }
+
+    let raw_base = load(&base_name).ok_or_else(|| InheritError::MissingBase {
+        pack: name.to_string(),
+        base: base_name.clone(),
+    })?;
+    stack.push(base_name.clone());
+    let base = resolve_chain(&base_name, raw_base, load, stack)?;
+    stack.pop();
+
+    Ok(merge(manifest, base, &base_name))
+}
+
+fn merge(child: Manifest, base: Manifest, base_name: &str) -> Manifest {
+    let mut categories = base.categories;
+    for category in categories.values_mut() {
+        for sound in &mut category.sounds {
+            let (pack, file) = sound.location(base_name);
+            sound.file = format!("{pack}:{file}");
+        }
+    }
+
+    for (name, category) in child.categories {
+        if category.append {
+            categories
+                .entry(name)
+                .or_default()
+                .sounds
+                .extend(category.sounds);
+        } else {
+            categories.insert(
+                name,
+                Category {
+                    sounds: category.sounds,
+                    append: false,
+                },
+            );
+        }
+    }
+
+    Manifest {
+        display_name: if child.display_name.is_empty() {
+            base.display_name
+        } else {
+            child.display_name
+        },
+        author: child.author.or(base.author),
+        license: child.license.or(base.license),
+        source: child.source.or(base.source),
+        icon: child.icon.or_else(|| {
+            base.icon.map(|icon| match icon.split_once(':') {
+                Some((pack, _)) if !pack.is_empty() => icon,
+                _ => format!("{base_name}:{icon}"),
+            })
+        }),
+        categories,
+        ..child
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn packs(entries: &[(&str, &str)]) -> HashMap<String, Manifest> {
+        entries
+            .iter()
+            .map(|(name, json)| {
+                (
+                    name.to_string(),
+                    serde_json::from_str(json).expect("valid manifest"),
+                )
+            })
+            .collect()
+    }
+
+    fn resolve(all: &HashMap<String, Manifest>, name: &str) -> Result<Manifest, InheritError> {
+        resolve_manifest(name, &|n: &str| all.get(n).cloned())
+    }
+
+    fn files(manifest: &Manifest, category: &str) -> Vec<String> {
+        manifest.categories[category]
+            .sounds
+            .iter()
+            .map(|s| s.file.clone())
+            .collect()
+    }
+
+    const PEON: &str = r#"{
+        "name": "peon", "display_name": "Orc Peon", "source": "Warcraft III",
+        "version": "2.0.0", "author": "Blizzard", "icon": "icon.png",
+        "categories": {
+            "greeting": {"sounds": [{"file": "PeonReady1.wav"}]},
+            "permission": {"sounds": [{"file": "PeonWhat4.wav"}]}
+        }
+    }"#;
+
+    #[test]
+    fn pack_without_extends_is_unchanged() {
+        let all = packs(&[("peon", PEON)]);
+        assert_eq!(resolve(&all, "peon").unwrap(), all["peon"]);
+    }
+
+    #[test]
+    fn override_replaces_base_category() {
+        let all = packs(&[
+            ("peon", PEON),
+            (
+                "mine",
+                r#"{"name": "mine", "extends": "peon", "categories": {
+                    "permission": {"sounds": [{"file": "sc_kerrigan:KerriganYes1.wav"}]}
+                }}"#,
+            ),
+        ]);
+        let resolved = resolve(&all, "mine").unwrap();
+        assert_eq!(resolved.name, "mine");
+        assert_eq!(resolved.display_name, "Orc Peon");
+        assert_eq!(resolved.source.as_deref(), Some("Warcraft III"));
+        assert_eq!(resolved.author.as_deref(), Some("Blizzard"));
+        assert_eq!(resolved.icon.as_deref(), Some("peon:icon.png"));
+        assert_eq!(resolved.version, None);
+        assert_eq!(files(&resolved, "greeting"), vec!["peon:PeonReady1.wav"]);
+        assert_eq!(
+            files(&resolved, "permission"),
+            vec!["sc_kerrigan:KerriganYes1.wav"]
+        );
+    }
+
+    #[test]
+    fn append_adds_to_base_category() {
+        let all = packs(&[
+            ("peon", PEON),
+            (
+                "mine",
+                r#"{"name": "mine", "extends": "peon", "categories": {
+                    "permission": {"append": true, "sounds": [{"file": "Local.wav"}]},
+                    "annoyed": {"append": true, "sounds": [{"file": "Angry.wav"}]}
+                }}"#,
+            ),
+        ]);
+        let resolved = resolve(&all, "mine").unwrap();
+        assert_eq!(
+            files(&resolved, "permission"),
+            vec!["peon:PeonWhat4.wav", "Local.wav"]
+        );
+        assert_eq!(files(&resolved, "annoyed"), vec!["Angry.wav"]);
+        assert!(!resolved.categories["permission"].append);
+    }
+
+    #[test]
+    fn multi_level_chain_keeps_original_pack() {
+        let all = packs(&[
+            ("peon", PEON),
+            ("middle", r#"{"name": "middle", "extends": "peon"}"#),
+            ("top", r#"{"name": "top", "extends": "middle"}"#),
+        ]);
+        let resolved = resolve(&all, "top").unwrap();
+        assert_eq!(files(&resolved, "greeting"), vec!["peon:PeonReady1.wav"]);
+    }
+
+    #[test]
+    fn missing_base_is_reported() {
+        let all = packs(&[("mine", r#"{"name": "mine", "extends": "ghost"}"#)]);
+        assert_eq!(
+            resolve(&all, "mine"),
+            Err(InheritError::MissingBase {
+                pack: "mine".into(),
+                base: "ghost".into()
+            })
+        );
+    }
+
+    #[test]
+    fn base_outside_packs_dir_is_rejected() {
+        let all = packs(&[("mine", r#"{"name": "mine", "extends": "../../x"}"#)]);
+        assert_eq!(
+            resolve(&all, "mine"),
+            Err(InheritError::InvalidBase {
+                pack: "mine".into(),
+                base: "../../x".into()
+            })
+        );
+    }
+
+    #[test]
+    fn missing_pack_is_reported() {
+        let all = packs(&[]);
+        assert_eq!(
+            resolve(&all, "ghost"),
+            Err(InheritError::NotFound("ghost".into()))
+        );
+    }
+
+    #[test]
+    fn cycles_are_detected() {
+        let all = packs(&[
+            ("a", r#"{"name": "a", "extends": "b"}"#),
+            ("b", r#"{"name": "b", "extends": "a"}"#),
+        ]);
+        assert_eq!(
+            resolve(&all, "a"),
+            Err(InheritError::Cycle("a -> b -> a".into()))
+        );
+    }
+
+    #[test]
+    fn self_extension_is_a_cycle() {
+        let all = packs(&[("a", r#"{"name": "a", "extends": "a"}"#)]);
+        assert!(matches!(resolve(&all, "a"), Err(InheritError::Cycle(_))));
+    }
+}
@@ -3,18 +3,76 @@
 pub mod types;
 pub mod upgrade;
 
+mod acknowledge;
 mod agent;
 mod annoyed;
+mod archive;
+mod clip;
+mod config_env;
+mod cooldown;
+mod fallback;
+mod glob;
+mod http;
+mod import;
+mod inherit;
+mod lock;
 mod pack;
+mod registry;
 mod routing;
+mod semver;
 mod sound;
 mod sounds;
+mod source;
 pub mod tab_title;
+mod tool_failure;
+mod update_check;
+mod validate;
 
-pub use agent::is_agent_session;
-pub use annoyed::check_annoyed;
-pub use pack::resolve_pack;
-pub use routing::route_event;
+pub use acknowledge::should_acknowledge;
+pub use agent::{apply_agent_behavior, classify_agent};
+pub use annoyed::{annoyance_tier, annoyed_category, annoyed_tier, decayed_score, register_prompt};
+pub use archive::{
+    check_checksums, check_entry_path, check_pack_contents, format_checksums, is_valid_pack_name,
+    parse_checksums, sha256_hex, verify_archive, ArchiveError, ARCHIVE_EXTENSION, CHECKSUMS_FILE,
+};
+pub use clip::{clip_duration, is_supported_audio, SUPPORTED_AUDIO_EXTENSIONS};
+pub use config_env::{format_config, resolve_config, EnvOverride, ResolvedConfig};
+pub use cooldown::{
+    check_sound_gate, format_suppressed, prune_sound_times, record_suppressed, MAX_SUPPRESSED_PLAYS,
+};
+pub use fallback::{fallback_chain, resolve_category, ResolvedCategory};
+pub use glob::glob_match;
+pub use http::{
+    backoff, bypasses_proxy, is_github_url, proxy_for, retry_delay, url_host, MAX_RETRY_WAIT,
+};
+pub use import::{
+    build_manifest, guess_category, line_from_file_name, plan_import, scaffold_manifest,
+    CategoryMapping, ImportEntry,
+};
+pub use inherit::{resolve_manifest, InheritError};
+pub use lock::{
+    diff_sounds, file_digests, format_sound_diff, registry_digests, LockEntry, PackLock,
+    PackSource, SoundDiff, LOCK_FILE,
+};
+pub use pack::{prune_state, removal_conflicts, replacement_pack, resolve_pack, RemovalConflict};
+pub use registry::{
+    format_remote_packs, format_size, index_entry, pack_status, verify_download, verify_pack_files,
+    PackStatus, RegistryFile, RegistryIndex, RegistryPack, DEFAULT_REGISTRY_URL, REGISTRY_FORMAT,
+};
+pub use routing::{is_limit_notification, route_event};
+pub use semver::{Identifier, Version};
 pub use sound::pick_sound;
-pub use sounds::format_pack_sounds;
+pub use sounds::{format_pack_credits, format_pack_sounds};
+pub use source::{
+    is_pack_tree_dir, is_pack_tree_file, packs_in_tree, parse_pack_request, PackRequest,
+    SourceError, SourceSpec, TreePack, DEFAULT_GITHUB_API_URL,
+};
 pub use tab_title::build_tab_title;
+pub use tool_failure::{detect_tool_failure, error_cooldown_elapsed, ToolFailure};
+pub use update_check::{
+    available_update, update_check_due, update_notice, UpdateCache, UPDATE_CACHE_FILE,
+};
+pub use validate::{
+    format_validation, validate_pack, Issue, PackFiles, Severity, SoundFile, ValidationReport,
+    DEFAULT_MAX_CLIP_SECONDS,
+};
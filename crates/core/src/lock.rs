@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::archive::{sha256_hex, CHECKSUMS_FILE};
+use crate::registry::RegistryPack;
+
+/// Lock file kept in the packs directory, recording where each pack came from.
+pub const LOCK_FILE: &str = ".peon-lock.json";
+
+/// Installed packs by directory name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PackLock {
+    #[serde(default)]
+    pub packs: BTreeMap<String, LockEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackSource {
+    /// Downloaded through the registry index.
+    Registry,
+    /// Downloaded from a GitHub repository.
+    Github,
+    /// Cloned from a git remote.
+    Git,
+    /// Copied from a local directory by `peon pull`.
+    Local,
+    /// Installed from a `.peonpack` archive or a local directory.
+    Archive,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockEntry {
+    pub source: PackSource,
+    /// Registry index URL or source (see `SourceSpec`) the pack was fetched from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Git tree SHA of the pack directory, for packs downloaded from GitHub.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// SHA-256 of each installed file, keyed by path relative to the pack.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+/// SHA-256 digests of pack files keyed by relative path, leaving out the checksum list.
+pub fn file_digests(files: &BTreeMap<String, Vec<u8>>) -> BTreeMap<String, String> {
+    files
+        .iter()
+        .filter(|(path, _)| path.as_str() != CHECKSUMS_FILE)
+        .map(|(path, bytes)| (path.clone(), sha256_hex(bytes)))
+        .collect()
+}
+
+/// The digests a registry entry lists, keyed like [`file_digests`].
+pub fn registry_digests(pack: &RegistryPack) -> BTreeMap<String, String> {
+    pack.files
+        .iter()
+        .map(|f| (f.path.clone(), f.sha256.to_ascii_lowercase()))
+        .collect()
+}
+
+/// Sound files that differ between two versions of a pack.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SoundDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Whether `manifest.json` itself changed.
+    pub manifest_changed: bool,
+}
+
+impl SoundDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && !self.manifest_changed
+    }
+
+    /// `2 added, 1 removed, 3 changed`, or `manifest only` when no sound changed.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [
+            (self.added.len(), "added"),
+            (self.removed.len(), "removed"),
+            (self.changed.len(), "changed"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{count} {label}"))
+        .collect();
+        if parts.is_empty() {
+            if self.manifest_changed {
+                "manifest only".to_string()
+            } else {
+                "no changes".to_string()
+            }
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Compare two digest maps (see [`file_digests`]).
+pub fn diff_sounds(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> SoundDiff {
+    let sound = |path: &String| path.strip_prefix("sounds/").map(str::to_string);
+    let mut diff = SoundDiff {
+        manifest_changed: old.get("manifest.json") != new.get("manifest.json"),
+        ..Default::default()
+    };
+    for (path, digest) in new {
+        let Some(name) = sound(path) else { continue };
+        match old.get(path) {
+            None => diff.added.push(name),
+            Some(previous) if !previous.eq_ignore_ascii_case(digest) => diff.changed.push(name),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .filter_map(sound)
+        .collect();
+    diff
+}
+
+/// Detailed listing of a diff, one `+`/`-`/`~` line per sound, indented by two spaces.
+pub fn format_sound_diff(diff: &SoundDiff) -> String {
+    let mut out = String::new();
+    for (marker, files) in [
+        ("+", &diff.added),
+        ("-", &diff.removed),
+        ("~", &diff.changed),
+    ] {
+        for file in files {
+            out.push_str(&format!("  {marker} {file}\n"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digests(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(path, digest)| (path.to_string(), digest.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn diff_reports_added_removed_changed() {
+        let old = digests(&[
+            ("manifest.json", "m1"),
+            ("sounds/A.wav", "a"),
+            ("sounds/B.wav", "b"),
+        ]);
+        let new = digests(&[
+            ("manifest.json", "m2"),
+            ("sounds/A.wav", "a2"),
+            ("sounds/C.wav", "c"),
+        ]);
+        let diff = diff_sounds(&old, &new);
+        assert_eq!(diff.added, vec!["C.wav"]);
+        assert_eq!(diff.removed, vec!["B.wav"]);
+        assert_eq!(diff.changed, vec!["A.wav"]);
+        assert!(diff.manifest_changed);
+        assert_eq!(diff.summary(), "1 added, 1 removed, 1 changed");
+        assert_eq!(
+            format_sound_diff(&diff),
+            "  + C.wav\n  - B.wav\n  ~ A.wav\n"
+        );
+    }
+
+    #[test]
+    fn identical_digests_are_empty() {
+        let old = digests(&[("manifest.json", "m"), ("sounds/A.wav", "ab")]);
+        let new = digests(&[("manifest.json", "m"), ("sounds/A.wav", "AB")]);
+        let diff = diff_sounds(&old, &new);
+        assert!(diff.is_empty());
+        assert_eq!(diff.summary(), "no changes");
+    }
+
+    #[test]
+    fn manifest_only_change() {
+        let old = digests(&[("manifest.json", "m1")]);
+        let new = digests(&[("manifest.json", "m2")]);
+        let diff = diff_sounds(&old, &new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.summary(), "manifest only");
+    }
+
+    #[test]
+    fn file_digests_skip_checksum_list() {
+        let files = BTreeMap::from([
+            ("manifest.json".to_string(), b"{}".to_vec()),
+            (CHECKSUMS_FILE.to_string(), b"x".to_vec()),
+        ]);
+        let digests = file_digests(&files);
+        assert_eq!(digests.len(), 1);
+        assert_eq!(digests["manifest.json"], sha256_hex(b"{}"));
+    }
+
+    #[test]
+    fn lock_round_trip() {
+        let lock = PackLock {
+            packs: BTreeMap::from([(
+                "peon".to_string(),
+                LockEntry {
+                    source: PackSource::Github,
+                    url: None,
+                    version: Some("1.0.0".into()),
+                    revision: Some("3f1c2a".into()),
+                    files: digests(&[("manifest.json", "m")]),
+                },
+            )]),
+        };
+        let json = serde_json::to_string(&lock).unwrap();
+        assert!(json.contains(r#""source":"github""#));
+        assert_eq!(serde_json::from_str::<PackLock>(&json).unwrap(), lock);
+    }
+}
@@ -1,7 +1,7 @@
 use rand::Rng;
 use std::collections::HashMap;
 
-use crate::types::Config;
+use crate::types::{Config, State};
 
 /// Resolve which pack to use for this session.
 ///
@@ -40,6 +40,67 @@ pub fn resolve_pack<'a>(
     valid_rotation[idx].clone()
 }
 
+/// Why a pack can't simply be deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemovalConflict {
+    /// The pack is `active_pack`.
+    Active(String),
+    /// The pack is listed in `pack_rotation`.
+    Rotation(String),
+}
+
+impl std::fmt::Display for RemovalConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Active(name) => write!(f, "\"{name}\" is the active pack"),
+            Self::Rotation(name) => write!(f, "\"{name}\" is in pack_rotation"),
+        }
+    }
+}
+
+/// Config references that removing `removed` would leave dangling.
+pub fn removal_conflicts(config: &Config, removed: &[String]) -> Vec<RemovalConflict> {
+    let mut conflicts = Vec::new();
+    for name in removed {
+        if config.active_pack == *name {
+            conflicts.push(RemovalConflict::Active(name.clone()));
+        }
+        if config.pack_rotation.contains(name) {
+            conflicts.push(RemovalConflict::Rotation(name.clone()));
+        }
+    }
+    conflicts
+}
+
+/// Pack to switch to when the active pack is removed: the first remaining rotation
+/// pack, then `peon`, then the first remaining pack alphabetically.
+pub fn replacement_pack(
+    config: &Config,
+    removed: &[String],
+    installed: &[String],
+) -> Option<String> {
+    let remaining: Vec<&String> = installed.iter().filter(|p| !removed.contains(p)).collect();
+    config
+        .pack_rotation
+        .iter()
+        .find(|p| remaining.contains(p))
+        .or_else(|| remaining.iter().copied().find(|p| *p == "peon"))
+        .or_else(|| remaining.iter().copied().min())
+        .cloned()
+}
+
+/// Drop session pins to removed packs. Returns the number of entries dropped.
+///
+/// `last_played` is keyed by file name, which other packs may share, and a stale entry
+/// there only means the next pick might repeat a sound, so it's left alone.
+pub fn prune_state(state: &mut State, removed: &[String]) -> usize {
+    let before = state.session_packs.len();
+    state
+        .session_packs
+        .retain(|_, pack| !removed.contains(pack));
+    before - state.session_packs.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +166,69 @@ mod tests {
             "peon"
         );
     }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn removal_conflicts_with_config() {
+        let config = Config {
+            active_pack: "peon".into(),
+            pack_rotation: names(&["peon", "sc_kerrigan"]),
+            ..Config::default()
+        };
+        assert_eq!(
+            removal_conflicts(&config, &names(&["peon", "glados"])),
+            vec![
+                RemovalConflict::Active("peon".into()),
+                RemovalConflict::Rotation("peon".into()),
+            ]
+        );
+        assert!(removal_conflicts(&config, &names(&["glados"])).is_empty());
+    }
+
+    #[test]
+    fn replacement_prefers_rotation_then_peon() {
+        let installed = names(&["glados", "peon", "sc_kerrigan"]);
+        let config = Config {
+            pack_rotation: names(&["sc_kerrigan"]),
+            ..Config::default()
+        };
+        assert_eq!(
+            replacement_pack(&config, &names(&["peon"]), &installed).as_deref(),
+            Some("sc_kerrigan")
+        );
+        assert_eq!(
+            replacement_pack(&default_config(), &names(&["sc_kerrigan"]), &installed).as_deref(),
+            Some("peon")
+        );
+        assert_eq!(
+            replacement_pack(&default_config(), &names(&["peon"]), &installed).as_deref(),
+            Some("glados")
+        );
+        assert_eq!(
+            replacement_pack(&default_config(), &installed, &installed),
+            None
+        );
+    }
+
+    #[test]
+    fn prune_state_drops_removed_pack_entries() {
+        let mut state = State::default();
+        state.session_packs.insert("s1".into(), "glados".into());
+        state.session_packs.insert("s2".into(), "peon".into());
+        state
+            .last_played
+            .insert("greeting".into(), "Hello.wav".into());
+        state
+            .last_played
+            .insert("complete".into(), "PeonYes1.wav".into());
+
+        assert_eq!(prune_state(&mut state, &names(&["glados"])), 1);
+        assert_eq!(state.session_packs.len(), 1);
+        // Other packs may have sounds with the same names
+        assert_eq!(state.last_played["greeting"], "Hello.wav");
+        assert_eq!(state.last_played["complete"], "PeonYes1.wav");
+    }
 }
@@ -0,0 +1,418 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::archive::{sha256_hex, CHECKSUMS_FILE};
+use crate::types::Manifest;
+
+/// Index published alongside the bundled packs in the main repository.
+pub const DEFAULT_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/cloudbridgeuy/peon-ping/main/packs/index.json";
+
+/// Newest index format this build understands.
+pub const REGISTRY_FORMAT: u32 = 1;
+
+/// A list of downloadable packs with per-file checksums.
+///
+/// Pack files are fetched from `{base_url}/{pack}/{path}`. Without `base_url`, the
+/// directory containing the index is used, so a mirror only needs to copy the tree.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistryIndex {
+    #[serde(default = "default_format")]
+    pub format: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub packs: Vec<RegistryPack>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RegistryPack {
+    pub name: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Total size of all files in bytes.
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub files: Vec<RegistryFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RegistryFile {
+    /// Path relative to the pack directory, e.g. `sounds/PeonReady1.wav`.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+fn default_format() -> u32 {
+    REGISTRY_FORMAT
+}
+
+impl Default for RegistryIndex {
+    fn default() -> Self {
+        Self {
+            format: REGISTRY_FORMAT,
+            base_url: None,
+            packs: Vec::new(),
+        }
+    }
+}
+
+impl RegistryIndex {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let index: Self =
+            serde_json::from_str(json).map_err(|e| format!("invalid registry index: {e}"))?;
+        if index.format > REGISTRY_FORMAT {
+            return Err(format!(
+                "registry index format {} is newer than supported ({REGISTRY_FORMAT}); upgrade peon",
+                index.format
+            ));
+        }
+        Ok(index)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&RegistryPack> {
+        self.packs.iter().find(|p| p.name == name)
+    }
+
+    /// URL of one pack file, given the URL the index was fetched from.
+    pub fn file_url(&self, index_url: &str, pack: &str, path: &str) -> String {
+        let base = match &self.base_url {
+            Some(base) => base.as_str(),
+            None => index_url
+                .rsplit_once('/')
+                .map(|(dir, _)| dir)
+                .unwrap_or(index_url),
+        };
+        format!("{}/{pack}/{path}", base.trim_end_matches('/'))
+    }
+}
+
+/// Describe a pack for the index from its manifest and files (keyed by relative path).
+///
+/// Languages and tags are collected from the manifest's sounds.
+pub fn index_entry(manifest: &Manifest, files: &BTreeMap<String, Vec<u8>>) -> RegistryPack {
+    let files: Vec<RegistryFile> = files
+        .iter()
+        .filter(|(path, _)| path.as_str() != CHECKSUMS_FILE)
+        .map(|(path, bytes)| RegistryFile {
+            path: path.clone(),
+            size: bytes.len() as u64,
+            sha256: sha256_hex(bytes),
+        })
+        .collect();
+
+    let sounds = manifest.categories.values().flat_map(|c| &c.sounds);
+    let languages: BTreeSet<String> = sounds.clone().filter_map(|s| s.language.clone()).collect();
+    let tags: BTreeSet<String> = sounds.flat_map(|s| s.tags.iter().cloned()).collect();
+
+    RegistryPack {
+        name: manifest.name.clone(),
+        display_name: manifest.display_name.clone(),
+        version: manifest.version.clone(),
+        size: files.iter().map(|f| f.size).sum(),
+        files,
+        languages: languages.into_iter().collect(),
+        tags: tags.into_iter().collect(),
+    }
+}
+
+/// Check a downloaded file against its index entry.
+pub fn verify_download(file: &RegistryFile, bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() as u64 != file.size {
+        return Err(format!(
+            "{}: expected {} bytes, got {}",
+            file.path,
+            file.size,
+            bytes.len()
+        ));
+    }
+    if !sha256_hex(bytes).eq_ignore_ascii_case(&file.sha256) {
+        return Err(format!("{}: checksum mismatch", file.path));
+    }
+    Ok(())
+}
+
+/// Check a pack's files against its index entry: every listed file must be present with
+/// the listed size and digest, and no other file may be present.
+pub fn verify_pack_files(
+    pack: &RegistryPack,
+    files: &BTreeMap<String, Vec<u8>>,
+) -> Result<(), String> {
+    for file in &pack.files {
+        let bytes = files
+            .get(&file.path)
+            .ok_or_else(|| format!("{}: missing", file.path))?;
+        verify_download(file, bytes)?;
+    }
+    let unlisted = files
+        .keys()
+        .find(|path| *path != CHECKSUMS_FILE && !pack.files.iter().any(|f| &f.path == *path));
+    match unlisted {
+        Some(path) => Err(format!("{path}: not listed in the index")),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackStatus {
+    Available,
+    Installed,
+    /// Installed, but some files differ from the index.
+    Outdated,
+}
+
+/// Compare an index entry with the SHA-256 digests of an installed copy (path → digest).
+pub fn pack_status(pack: &RegistryPack, local: Option<&BTreeMap<String, String>>) -> PackStatus {
+    let Some(local) = local else {
+        return PackStatus::Available;
+    };
+    let current = pack.files.iter().all(|file| {
+        local
+            .get(&file.path)
+            .is_some_and(|digest| digest.eq_ignore_ascii_case(&file.sha256))
+    });
+    if current {
+        PackStatus::Installed
+    } else {
+        PackStatus::Outdated
+    }
+}
+
+/// `1536` → `1.5 KB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Format the index for `peon packs --remote`, one pack per line.
+pub fn format_remote_packs(
+    index: &RegistryIndex,
+    status: &impl Fn(&RegistryPack) -> PackStatus,
+) -> String {
+    if index.packs.is_empty() {
+        return "No packs in the registry.\n".to_string();
+    }
+
+    let mut packs: Vec<&RegistryPack> = index.packs.iter().collect();
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let name_width = packs.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    let display_width = packs
+        .iter()
+        .map(|p| p.display_name.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for pack in packs {
+        let version = pack
+            .version
+            .as_ref()
+            .map(|v| format!("v{v}"))
+            .unwrap_or_default();
+        let marker = match status(pack) {
+            PackStatus::Available => "",
+            PackStatus::Installed => "  [installed]",
+            PackStatus::Outdated => "  [outdated]",
+        };
+        let line = format!(
+            "  {:name_width$}  {:display_width$}  {version:8}  {:>9}{marker}",
+            pack.name,
+            pack.display_name,
+            format_size(pack.size),
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &[u8])]) -> BTreeMap<String, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(path, bytes)| (path.to_string(), bytes.to_vec()))
+            .collect()
+    }
+
+    fn manifest() -> Manifest {
+        serde_json::from_str(
+            r#"{"name": "peon", "display_name": "Orc Peon", "version": "1.0.0", "categories": {
+                "greeting": {"sounds": [{"file": "A.wav", "language": "en", "tags": ["classic"]}]},
+                "complete": {"sounds": [{"file": "B.wav", "language": "en", "tags": ["work"]}]}
+            }}"#,
+        )
+        .unwrap()
+    }
+
+    fn entry() -> RegistryPack {
+        index_entry(
+            &manifest(),
+            &files(&[
+                ("manifest.json", b"{}"),
+                ("sounds/A.wav", b"aaaa"),
+                ("checksums.txt", b"ignored"),
+            ]),
+        )
+    }
+
+    #[test]
+    fn index_entry_describes_files() {
+        let pack = entry();
+        assert_eq!(pack.name, "peon");
+        assert_eq!(pack.version.as_deref(), Some("1.0.0"));
+        assert_eq!(pack.size, 6);
+        assert_eq!(pack.files.len(), 2);
+        assert_eq!(pack.files[1].path, "sounds/A.wav");
+        assert_eq!(pack.files[1].sha256, sha256_hex(b"aaaa"));
+        assert_eq!(pack.languages, vec!["en"]);
+        assert_eq!(pack.tags, vec!["classic", "work"]);
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let index = RegistryIndex {
+            packs: vec![entry()],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&index).unwrap();
+        assert_eq!(RegistryIndex::parse(&json).unwrap(), index);
+    }
+
+    #[test]
+    fn parse_rejects_newer_format() {
+        let err = RegistryIndex::parse(r#"{"format": 99, "packs": []}"#).unwrap_err();
+        assert!(err.contains("upgrade peon"), "{err}");
+    }
+
+    #[test]
+    fn file_url_relative_to_index() {
+        let index = RegistryIndex::default();
+        assert_eq!(
+            index.file_url("file:///mirror/index.json", "peon", "sounds/A.wav"),
+            "file:///mirror/peon/sounds/A.wav"
+        );
+        let index = RegistryIndex {
+            base_url: Some("https://cdn.example.com/packs/".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            index.file_url("https://example.com/index.json", "peon", "manifest.json"),
+            "https://cdn.example.com/packs/peon/manifest.json"
+        );
+    }
+
+    #[test]
+    fn verify_download_checks_size_and_digest() {
+        let file = &entry().files[1];
+        assert!(verify_download(file, b"aaaa").is_ok());
+        assert!(verify_download(file, b"aaa")
+            .unwrap_err()
+            .contains("expected 4 bytes"));
+        assert!(verify_download(file, b"aaab")
+            .unwrap_err()
+            .contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn pack_files_must_match_the_index() {
+        let pack = entry();
+        let good = files(&[("manifest.json", b"{}"), ("sounds/A.wav", b"aaaa")]);
+        assert!(verify_pack_files(&pack, &good).is_ok());
+
+        let tampered = files(&[("manifest.json", b"{}"), ("sounds/A.wav", b"aaab")]);
+        assert!(verify_pack_files(&pack, &tampered)
+            .unwrap_err()
+            .contains("checksum mismatch"));
+        let missing = files(&[("manifest.json", b"{}")]);
+        assert_eq!(
+            verify_pack_files(&pack, &missing).unwrap_err(),
+            "sounds/A.wav: missing"
+        );
+        let extra = files(&[
+            ("manifest.json", b"{}"),
+            ("sounds/A.wav", b"aaaa"),
+            ("sounds/B.wav", b"bbbb"),
+        ]);
+        assert!(verify_pack_files(&pack, &extra)
+            .unwrap_err()
+            .contains("not listed"));
+    }
+
+    #[test]
+    fn status_compares_digests() {
+        let pack = entry();
+        assert_eq!(pack_status(&pack, None), PackStatus::Available);
+
+        let mut local: BTreeMap<String, String> = pack
+            .files
+            .iter()
+            .map(|f| (f.path.clone(), f.sha256.clone()))
+            .collect();
+        local.insert("sounds/Extra.wav".into(), "00".into());
+        assert_eq!(pack_status(&pack, Some(&local)), PackStatus::Installed);
+
+        local.insert("sounds/A.wav".into(), sha256_hex(b"old"));
+        assert_eq!(pack_status(&pack, Some(&local)), PackStatus::Outdated);
+
+        local.remove("sounds/A.wav");
+        assert_eq!(pack_status(&pack, Some(&local)), PackStatus::Outdated);
+    }
+
+    #[test]
+    fn sizes_are_human_readable() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+
+    #[test]
+    fn remote_list_shows_markers() {
+        let mut other = entry();
+        other.name = "zerg".into();
+        other.version = None;
+        let index = RegistryIndex {
+            packs: vec![other, entry()],
+            ..Default::default()
+        };
+        let out = format_remote_packs(&index, &|p| {
+            if p.name == "peon" {
+                PackStatus::Outdated
+            } else {
+                PackStatus::Available
+            }
+        });
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].starts_with("  peon"));
+        assert!(lines[0].contains("v1.0.0"));
+        assert!(lines[0].ends_with("[outdated]"));
+        assert!(lines[1].starts_with("  zerg"));
+        assert!(!lines[1].contains('['));
+    }
+
+    #[test]
+    fn empty_registry() {
+        let out = format_remote_packs(&RegistryIndex::default(), &|_| PackStatus::Available);
+        assert_eq!(out, "No packs in the registry.\n");
+    }
+}
@@ -10,6 +10,71 @@ pub fn extract_project_name(cwd: &str) -> String {
         .collect::<String>()
 }
 
+/// Longest event-provided text shown in a notification.
+const MAX_DETAIL_CHARS: usize = 120;
+
+/// Whether a `Notification` reports a context-window, usage or rate limit, judged by its
+/// type or, for generic notifications, its message.
+pub fn is_limit_notification(notification_type: &str, message: &str) -> bool {
+    let kind = notification_type.to_ascii_lowercase();
+    if ["limit", "quota", "context"]
+        .iter()
+        .any(|k| kind.contains(k))
+    {
+        return true;
+    }
+    let message = message.to_ascii_lowercase();
+    [
+        "usage limit",
+        "rate limit",
+        "rate-limit",
+        "limit reached",
+        "quota",
+        "too many requests",
+        "context window",
+        "context low",
+        "context left",
+    ]
+    .iter()
+    .any(|phrase| message.contains(phrase))
+}
+
+/// Event-provided text made safe for a notification: one line without control
+/// characters, quotes or backslashes, and at most [`MAX_DETAIL_CHARS`] long.
+fn notification_detail(text: &str) -> String {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !matches!(c, '"' | '\\'))
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    let line = words.join(" ");
+    if line.chars().count() <= MAX_DETAIL_CHARS {
+        return line;
+    }
+    let cut: String = line.chars().take(MAX_DETAIL_CHARS - 1).collect();
+    format!("{}\u{2026}", cut.trim_end())
+}
+
+/// Title, `resource_limit` sound and notification for a session that hit a limit.
+fn resource_limit_actions(project: &str, config: &Config, detail: &str) -> Vec<Action> {
+    let title = crate::tab_title::build_tab_title(project, "limit reached", "\u{25cf} ");
+    let mut actions = vec![Action::SetTabTitle {
+        title: title.clone(),
+    }];
+    if config.categories.is_enabled("resource_limit") {
+        actions.push(Action::PlaySound {
+            category: "resource_limit".into(),
+        });
+    }
+    actions.push(Action::Notify {
+        message: format!("{project}  \u{2014}  {detail}"),
+        title,
+        color: NotifyColor::Yellow,
+    });
+    actions
+}
+
 /// Route a hook event to a list of actions.
 ///
 /// This is the main pure routing function. It does NOT handle:
@@ -39,8 +104,8 @@ pub fn route_event(event: &HookEvent, config: &Config, _state: &State) -> Vec<Ac
             actions
         }
         HookEvent::UserPromptSubmit { .. } => {
-            // Tab title only — sound is only played if annoyed threshold met
-            // The caller handles annoyed detection and may add a PlaySound action
+            // Tab title only — the caller decides between annoyed and acknowledge
+            // (which need the clock, state and randomness) and may add a PlaySound action
             vec![Action::SetTabTitle {
                 title: crate::tab_title::build_tab_title(&project, "working", ""),
             }]
@@ -62,8 +127,25 @@ pub fn route_event(event: &HookEvent, config: &Config, _state: &State) -> Vec<Ac
             });
             actions
         }
+        HookEvent::SubagentStop { .. } => {
+            // The parent session is still working, so no title or notification
+            let mut actions = vec![];
+            if config.categories.is_enabled("complete") {
+                actions.push(Action::PlaySound {
+                    category: "complete".into(),
+                });
+            }
+            actions
+        }
+        HookEvent::PreCompact { trigger, .. } => match trigger.as_str() {
+            // Compaction the user asked for (`/compact`) is no surprise
+            "manual" => vec![Action::Skip],
+            _ => resource_limit_actions(&project, config, "Context window full, compacting"),
+        },
         HookEvent::Notification {
-            notification_type, ..
+            notification_type,
+            message,
+            ..
         } => match notification_type.as_str() {
             "permission_prompt" => {
                 let mut actions = vec![];
@@ -102,8 +184,47 @@ pub fn route_event(event: &HookEvent, config: &Config, _state: &State) -> Vec<Ac
                 });
                 actions
             }
+            _ if is_limit_notification(notification_type, message) => {
+                let detail = match notification_detail(message) {
+                    detail if detail.is_empty() => "Limit reached".to_string(),
+                    detail => detail,
+                };
+                resource_limit_actions(&project, config, &detail)
+            }
             _ => vec![Action::Skip],
         },
+        HookEvent::PostToolUse {
+            tool_name,
+            tool_input,
+            tool_response,
+            ..
+        } => {
+            // The session keeps working, so no title; the caller applies the cooldown
+            let Some(failure) = crate::detect_tool_failure(
+                tool_name,
+                tool_input,
+                tool_response,
+                &config.error_patterns,
+            ) else {
+                return vec![Action::Skip];
+            };
+            let mut actions = vec![];
+            if config.categories.is_enabled("error") {
+                actions.push(Action::PlaySound {
+                    category: "error".into(),
+                });
+            }
+            if config.notify_on_error {
+                let subject = notification_detail(&failure.subject);
+                let reason = notification_detail(&failure.reason);
+                actions.push(Action::Notify {
+                    message: format!("{project}  \u{2014}  {subject} failed ({reason})"),
+                    title: crate::tab_title::build_tab_title(&project, "error", "\u{25cf} "),
+                    color: NotifyColor::Red,
+                });
+            }
+            actions
+        }
         HookEvent::PermissionRequest { .. } => {
             let mut actions = vec![];
             actions.push(Action::SetTabTitle {
@@ -205,6 +326,20 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn subagent_stop_plays_complete_only() {
+        let event = default_event("SubagentStop");
+        let config = Config::default();
+        let state = State::default();
+        let actions = route_event(&event, &config, &state);
+        assert_eq!(
+            actions,
+            vec![Action::PlaySound {
+                category: "complete".into()
+            }]
+        );
+    }
+
     #[test]
     fn notification_permission_prompt() {
         let json = r#"{"hook_event_name":"Notification","cwd":"/tmp/proj","session_id":"s1","permission_mode":"default","notification_type":"permission_prompt"}"#;
@@ -245,6 +380,107 @@ mod tests {
         )));
     }
 
+    #[test]
+    fn auto_compaction_plays_resource_limit() {
+        let json = r#"{"hook_event_name":"PreCompact","cwd":"/tmp/proj","session_id":"s1","trigger":"auto"}"#;
+        let event: HookEvent = serde_json::from_str(json).expect("valid");
+        let actions = route_event(&event, &Config::default(), &State::default());
+
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, Action::PlaySound { category } if category == "resource_limit")));
+        assert!(actions.iter().any(|a| matches!(
+            a,
+            Action::Notify { message, .. } if message == "proj  \u{2014}  Context window full, compacting"
+        )));
+    }
+
+    #[test]
+    fn manual_compaction_is_silent() {
+        let json = r#"{"hook_event_name":"PreCompact","cwd":"/tmp/proj","session_id":"s1","trigger":"manual"}"#;
+        let event: HookEvent = serde_json::from_str(json).expect("valid");
+        let actions = route_event(&event, &Config::default(), &State::default());
+        assert_eq!(actions, vec![Action::Skip]);
+    }
+
+    #[test]
+    fn limit_notifications_play_resource_limit() {
+        let json = r#"{"hook_event_name":"Notification","cwd":"/tmp/proj","session_id":"s1","notification_type":"","message":"Claude usage limit reached. Your limit will reset at 5pm \"now\"."}"#;
+        let event: HookEvent = serde_json::from_str(json).expect("valid");
+        let mut config = Config::default();
+        let actions = route_event(&event, &config, &State::default());
+
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, Action::PlaySound { category } if category == "resource_limit")));
+        assert!(actions.iter().any(|a| matches!(
+            a,
+            Action::Notify { message, .. }
+                if message == "proj  \u{2014}  Claude usage limit reached. Your limit will reset at 5pm now."
+        )));
+
+        config.categories.resource_limit = false;
+        let actions = route_event(&event, &config, &State::default());
+        assert!(!actions
+            .iter()
+            .any(|a| matches!(a, Action::PlaySound { .. })));
+    }
+
+    #[test]
+    fn detects_limit_notifications() {
+        assert!(is_limit_notification("rate_limit", ""));
+        assert!(is_limit_notification("context_low", ""));
+        assert!(is_limit_notification("", "API Error: Rate limit exceeded"));
+        assert!(is_limit_notification(
+            "",
+            "Context left until auto-compact: 5%"
+        ));
+        assert!(!is_limit_notification(
+            "",
+            "Claude needs your permission to use Bash"
+        ));
+        assert!(!is_limit_notification("auth_success", "Signed in"));
+    }
+
+    #[test]
+    fn notification_detail_is_one_short_line() {
+        assert_eq!(notification_detail("  a\n\tb \\ \"c\"  "), "a b c");
+        let long = "x".repeat(300);
+        let detail = notification_detail(&long);
+        assert_eq!(detail.chars().count(), MAX_DETAIL_CHARS);
+        assert!(detail.ends_with('\u{2026}'));
+    }
+
+    #[test]
+    fn failing_tool_plays_error() {
+        let json = r#"{"hook_event_name":"PostToolUse","cwd":"/tmp/proj","session_id":"s1","tool_name":"Bash","tool_input":{"command":"cargo build"},"tool_response":{"stderr":"error: could not compile `proj`"}}"#;
+        let event: HookEvent = serde_json::from_str(json).expect("valid");
+        let mut config = Config::default();
+        let actions = route_event(&event, &config, &State::default());
+        assert_eq!(
+            actions,
+            vec![Action::PlaySound {
+                category: "error".into()
+            }]
+        );
+
+        config.notify_on_error = true;
+        let actions = route_event(&event, &config, &State::default());
+        assert!(actions.iter().any(|a| matches!(
+            a,
+            Action::Notify { message, color: NotifyColor::Red, .. }
+                if message == "proj  \u{2014}  cargo build failed (error: could not compile `proj`)"
+        )));
+    }
+
+    #[test]
+    fn successful_tool_skips() {
+        let json = r#"{"hook_event_name":"PostToolUse","cwd":"/tmp/proj","session_id":"s1","tool_name":"Bash","tool_input":{"command":"ls"},"tool_response":{"stdout":"a","stderr":"","interrupted":false}}"#;
+        let event: HookEvent = serde_json::from_str(json).expect("valid");
+        let actions = route_event(&event, &Config::default(), &State::default());
+        assert_eq!(actions, vec![Action::Skip]);
+    }
+
     #[test]
     fn unknown_notification_type_skips() {
         let json = r#"{"hook_event_name":"Notification","cwd":"/tmp","session_id":"s1","permission_mode":"default","notification_type":"something_else"}"#;
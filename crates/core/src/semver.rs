@@ -0,0 +1,215 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A semantic version (`1.2.3`, `3.1.0-rc.1`, `2.0.0+build.5`), ordered by semver
+/// precedence: pre-releases sort before their release, and build metadata is ignored.
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+    pub build: Vec<String>,
+}
+
+/// One dot-separated pre-release identifier. Numeric identifiers sort numerically and
+/// before alphanumeric ones, which sort in ASCII order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Identifier {
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Version {
+    /// Parse a version, with or without a leading `v`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let version = text.strip_prefix('v').unwrap_or(text);
+        let (version, build) = match version.split_once('+') {
+            Some((version, build)) => (version, Some(build)),
+            None => (version, None),
+        };
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+
+        let parts: Vec<&str> = core.split('.').collect();
+        let [major, minor, patch] = parts.as_slice() else {
+            return Err(format!("invalid semver: {text}"));
+        };
+        let number = |part: &str, name: &str| {
+            parse_number(part).ok_or_else(|| format!("invalid {name} version: {part}"))
+        };
+
+        let pre = pre
+            .map(|pre| {
+                identifiers(pre)
+                    .ok_or_else(|| format!("invalid pre-release: {pre}"))?
+                    .into_iter()
+                    .map(|id| match parse_number(id) {
+                        Some(n) => Ok(Identifier::Numeric(n)),
+                        None if id.bytes().all(|b| b.is_ascii_digit()) => {
+                            Err(format!("invalid pre-release: {pre}"))
+                        }
+                        None => Ok(Identifier::Alpha(id.to_string())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let build = build
+            .map(|build| {
+                identifiers(build)
+                    .map(|ids| ids.into_iter().map(str::to_string).collect())
+                    .ok_or_else(|| format!("invalid build metadata: {build}"))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            major: number(major, "major")?,
+            minor: number(minor, "minor")?,
+            patch: number(patch, "patch")?,
+            pre,
+            build,
+        })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+/// A decimal number without leading zeros.
+fn parse_number(part: &str) -> Option<u64> {
+    let digits = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !digits || (part.len() > 1 && part.starts_with('0')) {
+        return None;
+    }
+    part.parse().ok()
+}
+
+/// Split dot-separated identifiers of `[0-9A-Za-z-]`, none empty.
+fn identifiers(text: &str) -> Option<Vec<&str>> {
+    let ids: Vec<&str> = text.split('.').collect();
+    ids.iter()
+        .all(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-'))
+        .then_some(ids)
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Numeric(n) => write!(f, "{n}"),
+            Self::Alpha(s) => f.write_str(s),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self.pre.iter().map(ToString::to_string).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parses_pre_release_and_build() {
+        let version = v("v3.1.0-rc.1+sha.5114f85");
+        assert_eq!((version.major, version.minor, version.patch), (3, 1, 0));
+        assert_eq!(
+            version.pre,
+            vec![Identifier::Alpha("rc".into()), Identifier::Numeric(1)]
+        );
+        assert_eq!(version.build, vec!["sha", "5114f85"]);
+        assert!(version.is_prerelease());
+        assert_eq!(version.to_string(), "3.1.0-rc.1+sha.5114f85");
+        assert!(!v("3.1.0").is_prerelease());
+    }
+
+    #[test]
+    fn rejects_invalid_versions() {
+        for text in [
+            "1.0",
+            "1.0.0.0",
+            "01.0.0",
+            "1.0.x",
+            "1.0.0-",
+            "1.0.0-rc..1",
+            "1.0.0-01",
+            "1.0.0+",
+            "1.0.0-rc!",
+            "",
+        ] {
+            assert!(Version::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn orders_by_semver_precedence() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.10.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn build_metadata_does_not_affect_precedence() {
+        assert_eq!(v("1.0.0+a"), v("1.0.0+b"));
+        assert_eq!(v("1.0.0-rc.1+x").cmp(&v("1.0.0-rc.1")), Ordering::Equal);
+    }
+}
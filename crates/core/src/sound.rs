@@ -3,33 +3,57 @@ use rand::Rng;
 
 /// Pick a random sound from the list, avoiding the last-played file when possible.
 ///
+/// Sounds are chosen proportionally to their `weight` (default 1.0) and a zero weight
+/// disables a sound. Only if every sound has a zero weight does the choice fall back
+/// to uniform.
+///
 /// Returns `None` if the sound list is empty.
 pub fn pick_sound<'a>(
     sounds: &'a [Sound],
     last_played: Option<&str>,
     rng: &mut impl Rng,
 ) -> Option<&'a Sound> {
-    if sounds.is_empty() {
-        return None;
-    }
-    if sounds.len() == 1 {
-        return Some(&sounds[0]);
+    let playable: Vec<&Sound> = sounds.iter().filter(|s| s.weight() > 0.0).collect();
+    let playable = if playable.is_empty() {
+        sounds.iter().collect()
+    } else {
+        playable
+    };
+    if playable.len() <= 1 {
+        return playable.first().copied();
     }
 
     let candidates: Vec<&Sound> = match last_played {
         Some(last) => {
-            let filtered: Vec<_> = sounds.iter().filter(|s| s.file != last).collect();
+            let filtered: Vec<_> = playable
+                .iter()
+                .filter(|s| s.file != last)
+                .copied()
+                .collect();
             if filtered.is_empty() {
-                sounds.iter().collect()
+                playable
             } else {
                 filtered
             }
         }
-        None => sounds.iter().collect(),
+        None => playable,
     };
 
-    let idx = rng.gen_range(0..candidates.len());
-    Some(candidates[idx])
+    let total: f64 = candidates.iter().map(|s| s.weight()).sum();
+    if total <= 0.0 {
+        let idx = rng.gen_range(0..candidates.len());
+        return Some(candidates[idx]);
+    }
+
+    let mut roll = rng.gen_range(0.0..total);
+    for sound in &candidates {
+        let weight = sound.weight();
+        if roll < weight {
+            return Some(sound);
+        }
+        roll -= weight;
+    }
+    candidates.iter().rev().find(|s| s.weight() > 0.0).copied()
 }
 
 #[cfg(test)]
@@ -42,11 +66,19 @@ mod tests {
             .iter()
             .map(|f| Sound {
                 file: f.to_string(),
-                line: String::new(),
+                ..Default::default()
             })
             .collect()
     }
 
+    fn weighted(file: &str, weight: f64) -> Sound {
+        Sound {
+            file: file.to_string(),
+            weight: Some(weight),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn empty_list_returns_none() {
         let mut rng = StepRng::new(0, 1);
@@ -77,4 +109,52 @@ mod tests {
         let picked = pick_sound(&sounds, None, &mut rng);
         assert!(picked.is_some());
     }
+
+    #[test]
+    fn zero_weight_never_picked() {
+        let sounds = vec![weighted("never.wav", 0.0), weighted("always.wav", 1.0)];
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let picked = pick_sound(&sounds, None, &mut rng);
+            assert_eq!(picked.map(|s| s.file.as_str()), Some("always.wav"));
+        }
+    }
+
+    #[test]
+    fn zero_weight_not_picked_to_avoid_repeat() {
+        let sounds = vec![weighted("a.wav", 1.0), weighted("b.wav", 0.0)];
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let picked = pick_sound(&sounds, Some("a.wav"), &mut rng);
+            assert_eq!(picked.map(|s| s.file.as_str()), Some("a.wav"));
+        }
+    }
+
+    #[test]
+    fn zero_weight_not_picked_beside_single_playable_sound() {
+        let sounds = vec![weighted("off.wav", 0.0), weighted("on.wav", 2.0)];
+        let mut rng = StepRng::new(0, 1);
+        let picked = pick_sound(&sounds, Some("on.wav"), &mut rng);
+        assert_eq!(picked.map(|s| s.file.as_str()), Some("on.wav"));
+    }
+
+    #[test]
+    fn all_zero_weights_fall_back_to_uniform() {
+        let sounds = vec![weighted("a.wav", 0.0), weighted("b.wav", 0.0)];
+        let mut rng = StepRng::new(0, 1);
+        assert!(pick_sound(&sounds, None, &mut rng).is_some());
+    }
+
+    #[test]
+    fn weights_bias_selection() {
+        use rand::SeedableRng;
+        let sounds = vec![weighted("rare.wav", 1.0), weighted("common.wav", 9.0)];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let common = (0..1000)
+            .filter(|_| {
+                pick_sound(&sounds, None, &mut rng).map(|s| s.file.as_str()) == Some("common.wav")
+            })
+            .count();
+        assert!(common > 800, "common picked {common} times");
+    }
 }
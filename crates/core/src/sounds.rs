@@ -8,7 +8,14 @@ pub fn format_pack_sounds(manifest: &Manifest) -> String {
         &manifest.display_name
     };
 
-    let mut out = format!("{display} ({})\n", manifest.name);
+    let mut out = match &manifest.version {
+        Some(version) => format!("{display} ({}) v{version}\n", manifest.name),
+        None => format!("{display} ({})\n", manifest.name),
+    };
+
+    if let Some(base) = &manifest.extends {
+        out.push_str(&format!("  extends {base}\n"));
+    }
 
     if manifest.categories.is_empty() {
         out.push_str("\n  No categories.\n");
@@ -23,13 +30,64 @@ pub fn format_pack_sounds(manifest: &Manifest) -> String {
         let label = if count == 1 { "sound" } else { "sounds" };
         out.push_str(&format!("\n  {name} ({count} {label})\n"));
         for sound in &category.sounds {
-            out.push_str(&format!("    \"{}\"\n", sound.line));
+            let (pack, _) = sound.location(&manifest.name);
+            if pack == manifest.name {
+                out.push_str(&format!("    \"{}\"\n", sound.line));
+            } else {
+                out.push_str(&format!("    \"{}\"  ({pack})\n", sound.line));
+            }
         }
     }
 
     out
 }
 
+/// Format a manifest's attribution metadata for `peon credits`.
+pub fn format_pack_credits(manifest: &Manifest) -> String {
+    let display = if manifest.display_name.is_empty() {
+        &manifest.name
+    } else {
+        &manifest.display_name
+    };
+
+    let mut out = match &manifest.version {
+        Some(version) => format!("{display} ({}) v{version}\n", manifest.name),
+        None => format!("{display} ({})\n", manifest.name),
+    };
+
+    let fields = [
+        ("author", &manifest.author),
+        ("license", &manifest.license),
+        ("source", &manifest.source),
+    ];
+    let mut any = false;
+    for (label, value) in fields {
+        if let Some(value) = value {
+            out.push_str(&format!("  {label:8} {value}\n"));
+            any = true;
+        }
+    }
+
+    let mut languages: Vec<&str> = manifest
+        .categories
+        .values()
+        .flat_map(|c| c.sounds.iter())
+        .filter_map(|s| s.language.as_deref())
+        .collect();
+    languages.sort_unstable();
+    languages.dedup();
+    if !languages.is_empty() {
+        out.push_str(&format!("  {:8} {}\n", "language", languages.join(", ")));
+        any = true;
+    }
+
+    if !any {
+        out.push_str("  No attribution information.\n");
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,12 +103,15 @@ mod tests {
                     Sound {
                         file: "PeonReady1.wav".into(),
                         line: "Ready to work?".into(),
+                        ..Default::default()
                     },
                     Sound {
                         file: "PeonWhat1.wav".into(),
                         line: "Yes?".into(),
+                        ..Default::default()
                     },
                 ],
+                ..Default::default()
             },
         );
         categories.insert(
@@ -59,13 +120,16 @@ mod tests {
                 sounds: vec![Sound {
                     file: "PeonAngry1.wav".into(),
                     line: "Whaaat?".into(),
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
         );
         Manifest {
             name: "peon".into(),
             display_name: "Orc Peon".into(),
             categories,
+            ..Default::default()
         }
     }
 
@@ -104,6 +168,7 @@ mod tests {
             name: "empty".into(),
             display_name: "Empty Pack".into(),
             categories: HashMap::new(),
+            ..Default::default()
         };
         let output = format_pack_sounds(&manifest);
         assert_eq!(output, "Empty Pack (empty)\n\n  No categories.\n");
@@ -115,8 +180,61 @@ mod tests {
             name: "minimal".into(),
             display_name: String::new(),
             categories: HashMap::new(),
+            ..Default::default()
         };
         let output = format_pack_sounds(&manifest);
         assert!(output.starts_with("minimal (minimal)\n"));
     }
+
+    #[test]
+    fn format_pack_sounds_shows_version() {
+        let manifest = Manifest {
+            version: Some("1.2.0".into()),
+            ..test_manifest()
+        };
+        let output = format_pack_sounds(&manifest);
+        assert!(output.starts_with("Orc Peon (peon) v1.2.0\n"));
+    }
+
+    #[test]
+    fn format_pack_sounds_marks_borrowed_sounds() {
+        let mut manifest = Manifest {
+            name: "mine".into(),
+            extends: Some("peon".into()),
+            ..test_manifest()
+        };
+        if let Some(cat) = manifest.categories.get_mut("annoyed") {
+            cat.sounds[0].file = "peon:PeonAngry1.wav".into();
+        }
+        let output = format_pack_sounds(&manifest);
+        assert!(output.contains("  extends peon\n"));
+        assert!(output.contains("\"Whaaat?\"  (peon)\n"));
+        assert!(output.contains("\"Yes?\"\n"));
+    }
+
+    #[test]
+    fn format_pack_credits_lists_attribution() {
+        let mut manifest = Manifest {
+            version: Some("2.0.0".into()),
+            author: Some("tonyyont".into()),
+            license: Some("Blizzard Entertainment".into()),
+            source: Some("Warcraft III".into()),
+            ..test_manifest()
+        };
+        if let Some(cat) = manifest.categories.get_mut("greeting") {
+            cat.sounds[0].language = Some("en".into());
+        }
+        let output = format_pack_credits(&manifest);
+        assert!(output.starts_with("Orc Peon (peon) v2.0.0\n"));
+        assert!(output.contains("  author   tonyyont\n"));
+        assert!(output.contains("  license  Blizzard Entertainment\n"));
+        assert!(output.contains("  source   Warcraft III\n"));
+        assert!(output.contains("  language en\n"));
+    }
+
+    #[test]
+    fn format_pack_credits_without_metadata() {
+        let output = format_pack_credits(&test_manifest());
+        assert_eq!(output, "Orc Peon (peon)\n  No attribution information.\n");
+    }
 }
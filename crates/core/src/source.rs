@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+
+use crate::archive::is_valid_pack_name;
+use crate::types::Manifest;
+
+/// GitHub API used when `github_api_url` isn't configured.
+pub const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Repository the bundled packs are published from.
+const DEFAULT_PACK_REPO: (&str, &str) = ("cloudbridgeuy", "peon-ping");
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("invalid pack source \"{spec}\": {reason}")]
+pub struct SourceError {
+    pub spec: String,
+    pub reason: &'static str,
+}
+
+/// Somewhere packs can be pulled from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSpec {
+    /// `owner/repo[@ref]`, fetched through the GitHub API.
+    Github {
+        owner: String,
+        repo: String,
+        git_ref: Option<String>,
+    },
+    /// Any URL `git clone` understands, with an optional branch or tag.
+    Git {
+        url: String,
+        git_ref: Option<String>,
+    },
+    /// A directory holding one pack, a folder of packs, or a checkout with `packs/`.
+    Local { path: String },
+}
+
+impl SourceSpec {
+    /// The repository `peon pull` falls back to when the registry is unavailable.
+    pub fn default_repo() -> Self {
+        Self::Github {
+            owner: DEFAULT_PACK_REPO.0.to_string(),
+            repo: DEFAULT_PACK_REPO.1.to_string(),
+            git_ref: None,
+        }
+    }
+
+    /// Parse a source without a pack name, as listed in `pack_sources`.
+    pub fn parse(spec: &str) -> Result<Self, SourceError> {
+        match parse_pack_request(spec)? {
+            PackRequest {
+                source: Some(source),
+                pack: None,
+            } => Ok(source),
+            PackRequest { source: None, .. } => {
+                Err(error(spec, "expected owner/repo, a git URL or a path"))
+            }
+            PackRequest { pack: Some(_), .. } => Err(error(spec, "a source can't name a pack")),
+        }
+    }
+
+    pub fn git_ref(&self) -> Option<&str> {
+        match self {
+            Self::Github { git_ref, .. } | Self::Git { git_ref, .. } => git_ref.as_deref(),
+            Self::Local { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Github { owner, repo, .. } => write!(f, "{owner}/{repo}")?,
+            Self::Git { url, .. } => write!(f, "{url}")?,
+            Self::Local { path } => return write!(f, "{path}"),
+        }
+        match (self, self.git_ref()) {
+            (Self::Github { .. }, Some(git_ref)) => write!(f, "@{git_ref}"),
+            (_, Some(git_ref)) => write!(f, "#{git_ref}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What `peon pull` was asked for: a pack name, a source, or a pack from a source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackRequest {
+    pub source: Option<SourceSpec>,
+    pub pack: Option<String>,
+}
+
+fn error(spec: &str, reason: &'static str) -> SourceError {
+    SourceError {
+        spec: spec.to_string(),
+        reason,
+    }
+}
+
+fn is_local(spec: &str) -> bool {
+    spec == "."
+        || spec == "~"
+        || ["/", "./", "../", "~/", "file://"]
+            .iter()
+            .any(|prefix| spec.starts_with(prefix))
+}
+
+fn is_git_url(spec: &str) -> bool {
+    let url = spec.split_once('#').map_or(spec, |(url, _)| url);
+    (spec.contains("://") && !spec.starts_with("file://"))
+        || spec.starts_with("git@")
+        || (is_local(spec) && url.trim_end_matches('/').ends_with(".git"))
+}
+
+fn is_repo_part(part: &str) -> bool {
+    !part.is_empty()
+        && part
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn checked_pack(spec: &str, pack: &str) -> Result<Option<String>, SourceError> {
+    match pack {
+        "" => Ok(None),
+        pack if is_valid_pack_name(pack) => Ok(Some(pack.to_string())),
+        _ => Err(error(spec, "invalid pack name")),
+    }
+}
+
+fn checked_ref(spec: &str, git_ref: &str) -> Result<Option<String>, SourceError> {
+    match git_ref {
+        "" => Ok(None),
+        r if r.chars().any(|c| c.is_whitespace() || c == ':') => Err(error(spec, "invalid ref")),
+        r => Ok(Some(r.to_string())),
+    }
+}
+
+/// Parse the argument of `peon pull`.
+///
+/// - `pack`: a pack name, searched in `pack_sources` and then the registry
+/// - `owner/repo[@ref][:pack]`: a GitHub repository
+/// - `<git-url>[#ref][:pack]`: any git remote (`https://…`, `ssh://…`, `git@host:…`, or a
+///   path ending in `.git`)
+/// - `/path`, `./path`, `~/path` or `file://…`: a local directory
+pub fn parse_pack_request(spec: &str) -> Result<PackRequest, SourceError> {
+    if spec.is_empty() {
+        return Err(error(spec, "empty"));
+    }
+    if spec.starts_with('-') {
+        // git would read the spec as an option
+        return Err(error(spec, "starts with '-'"));
+    }
+
+    if is_git_url(spec) {
+        let (url, fragment) = spec.split_once('#').unwrap_or((spec, ""));
+        let (git_ref, pack) = fragment.split_once(':').unwrap_or((fragment, ""));
+        return Ok(PackRequest {
+            source: Some(SourceSpec::Git {
+                url: url.to_string(),
+                git_ref: checked_ref(spec, git_ref)?,
+            }),
+            pack: checked_pack(spec, pack)?,
+        });
+    }
+
+    if is_local(spec) {
+        let path = spec.strip_prefix("file://").unwrap_or(spec);
+        return Ok(PackRequest {
+            source: Some(SourceSpec::Local {
+                path: path.to_string(),
+            }),
+            pack: None,
+        });
+    }
+
+    if !spec.contains('/') {
+        return Ok(PackRequest {
+            source: None,
+            pack: checked_pack(spec, spec)?,
+        });
+    }
+
+    let (repo_ref, pack) = spec.rsplit_once(':').unwrap_or((spec, ""));
+    let (repo_path, git_ref) = repo_ref.split_once('@').unwrap_or((repo_ref, ""));
+    let Some((owner, repo)) = repo_path.split_once('/') else {
+        return Err(error(spec, "expected owner/repo"));
+    };
+    if !is_repo_part(owner) || !is_repo_part(repo) {
+        return Err(error(spec, "expected owner/repo"));
+    }
+    Ok(PackRequest {
+        source: Some(SourceSpec::Github {
+            owner: owner.to_string(),
+            repo: repo.trim_end_matches(".git").to_string(),
+            git_ref: checked_ref(spec, git_ref)?,
+        }),
+        pack: checked_pack(spec, pack)?,
+    })
+}
+
+/// Files of one pack found in a source tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreePack {
+    /// Directory of the pack relative to the tree root (empty for a root pack).
+    pub prefix: String,
+    /// Files keyed by path relative to the pack directory.
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+/// Whether a directory (relative to a source root) can hold pack files. Used to prune
+/// walks of checkouts and tarballs.
+pub fn is_pack_tree_dir(path: &str) -> bool {
+    let parts: Vec<&str> = path.split('/').collect();
+    if parts.iter().any(|p| p.is_empty() || p.starts_with('.')) {
+        return false;
+    }
+    matches!(
+        parts.as_slice(),
+        [_] | [_, "sounds"] | ["packs", _] | ["packs", _, "sounds"]
+    )
+}
+
+/// Whether a file (relative to a source root) can belong to a pack.
+pub fn is_pack_tree_file(path: &str) -> bool {
+    match path.rsplit_once('/') {
+        None => !path.starts_with('.'),
+        Some((dir, name)) => !name.starts_with('.') && is_pack_tree_dir(dir),
+    }
+}
+
+/// Find the packs in a source tree (paths relative to its root).
+///
+/// Supports three layouts, tried in order:
+/// - a single pack at the root (`manifest.json`, `sounds/…`), named by its manifest
+/// - a repository with packs under `packs/<name>/`
+/// - a folder of packs, `<name>/manifest.json`
+pub fn packs_in_tree(files: &BTreeMap<String, Vec<u8>>) -> BTreeMap<String, TreePack> {
+    if let Some(manifest) = files.get("manifest.json") {
+        let name = serde_json::from_slice::<Manifest>(manifest)
+            .ok()
+            .map(|m| m.name)
+            .filter(|name| is_valid_pack_name(name));
+        return name
+            .map(|name| (name, pack_at(files, "")))
+            .into_iter()
+            .collect();
+    }
+
+    let in_packs_dir = files.keys().any(|path| {
+        path.strip_prefix("packs/")
+            .is_some_and(|rest| rest.split('/').nth(1) == Some("manifest.json"))
+    });
+    let base = if in_packs_dir { "packs/" } else { "" };
+    files
+        .keys()
+        .filter_map(|path| path.strip_prefix(base)?.strip_suffix("/manifest.json"))
+        .filter(|name| !name.contains('/') && is_valid_pack_name(name))
+        .map(|name| {
+            let prefix = format!("{base}{name}");
+            (name.to_string(), pack_at(files, &prefix))
+        })
+        .collect()
+}
+
+fn pack_at(files: &BTreeMap<String, Vec<u8>>, prefix: &str) -> TreePack {
+    let strip = |path: &str| -> Option<String> {
+        let rest = if prefix.is_empty() {
+            path
+        } else {
+            path.strip_prefix(prefix)?.strip_prefix('/')?
+        };
+        let in_pack = match rest.split_once('/') {
+            None => true,
+            Some(("sounds", file)) => !file.contains('/'),
+            Some(_) => false,
+        };
+        in_pack.then(|| rest.to_string())
+    };
+    TreePack {
+        prefix: prefix.to_string(),
+        files: files
+            .iter()
+            .filter_map(|(path, bytes)| Some((strip(path)?, bytes.clone())))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github(owner: &str, repo: &str, git_ref: Option<&str>) -> SourceSpec {
+        SourceSpec::Github {
+            owner: owner.into(),
+            repo: repo.into(),
+            git_ref: git_ref.map(Into::into),
+        }
+    }
+
+    fn request(source: Option<SourceSpec>, pack: Option<&str>) -> PackRequest {
+        PackRequest {
+            source,
+            pack: pack.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn parses_pack_names() {
+        assert_eq!(
+            parse_pack_request("sc_kerrigan").unwrap(),
+            request(None, Some("sc_kerrigan"))
+        );
+        assert!(parse_pack_request("bad name").is_err());
+    }
+
+    #[test]
+    fn rejects_option_like_specs() {
+        for spec in ["--upload-pack=touch /tmp/x://x", "-oProxyCommand=x", "-u"] {
+            assert!(parse_pack_request(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn parses_github_shorthand() {
+        assert_eq!(
+            parse_pack_request("acme/peon-packs@v2:glados").unwrap(),
+            request(
+                Some(github("acme", "peon-packs", Some("v2"))),
+                Some("glados")
+            )
+        );
+        assert_eq!(
+            parse_pack_request("acme/peon-packs:glados").unwrap(),
+            request(Some(github("acme", "peon-packs", None)), Some("glados"))
+        );
+        assert_eq!(
+            parse_pack_request("acme/peon-packs@feature/new").unwrap(),
+            request(
+                Some(github("acme", "peon-packs", Some("feature/new"))),
+                None
+            )
+        );
+        assert!(parse_pack_request("acme/").is_err());
+        assert!(parse_pack_request("a/b/c").is_err());
+    }
+
+    #[test]
+    fn parses_git_urls() {
+        assert_eq!(
+            parse_pack_request("https://git.example.com/team/packs.git#main:glados").unwrap(),
+            request(
+                Some(SourceSpec::Git {
+                    url: "https://git.example.com/team/packs.git".into(),
+                    git_ref: Some("main".into()),
+                }),
+                Some("glados")
+            )
+        );
+        assert_eq!(
+            parse_pack_request("git@github.com:team/packs.git#:glados").unwrap(),
+            request(
+                Some(SourceSpec::Git {
+                    url: "git@github.com:team/packs.git".into(),
+                    git_ref: None,
+                }),
+                Some("glados")
+            )
+        );
+        assert_eq!(
+            parse_pack_request("/srv/git/packs.git#v1").unwrap(),
+            request(
+                Some(SourceSpec::Git {
+                    url: "/srv/git/packs.git".into(),
+                    git_ref: Some("v1".into()),
+                }),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn parses_local_paths() {
+        for (spec, path) in [
+            ("./packs", "./packs"),
+            ("/srv/peon", "/srv/peon"),
+            ("~/packs", "~/packs"),
+            ("file:///srv/peon", "/srv/peon"),
+        ] {
+            assert_eq!(
+                parse_pack_request(spec).unwrap(),
+                request(Some(SourceSpec::Local { path: path.into() }), None)
+            );
+        }
+    }
+
+    #[test]
+    fn source_round_trips_through_display() {
+        for spec in [
+            "acme/peon-packs",
+            "acme/peon-packs@v2",
+            "https://git.example.com/packs.git#main",
+            "/srv/peon",
+        ] {
+            let source = SourceSpec::parse(spec).unwrap();
+            assert_eq!(source.to_string(), spec);
+        }
+        assert!(SourceSpec::parse("glados").is_err());
+        assert!(SourceSpec::parse("acme/packs:glados").is_err());
+    }
+
+    fn tree(paths: &[(&str, &str)]) -> BTreeMap<String, Vec<u8>> {
+        paths
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_root_pack() {
+        let packs = packs_in_tree(&tree(&[
+            ("manifest.json", r#"{"name": "glados", "categories": {}}"#),
+            ("sounds/Hi.wav", "RIFF"),
+            ("README.md", "readme"),
+            ("docs/notes.md", "notes"),
+        ]));
+        let pack = &packs["glados"];
+        assert_eq!(pack.prefix, "");
+        assert_eq!(
+            pack.files.keys().collect::<Vec<_>>(),
+            vec!["README.md", "manifest.json", "sounds/Hi.wav"]
+        );
+    }
+
+    #[test]
+    fn finds_packs_directory() {
+        let packs = packs_in_tree(&tree(&[
+            ("packs/peon/manifest.json", "{}"),
+            ("packs/peon/sounds/A.wav", "a"),
+            ("packs/index.json", "{}"),
+            ("src/manifest.json", "{}"),
+        ]));
+        assert_eq!(packs.keys().collect::<Vec<_>>(), vec!["peon"]);
+        assert_eq!(packs["peon"].prefix, "packs/peon");
+        assert_eq!(packs["peon"].files.len(), 2);
+    }
+
+    #[test]
+    fn finds_folder_of_packs() {
+        let packs = packs_in_tree(&tree(&[
+            ("glados/manifest.json", "{}"),
+            ("glados/sounds/A.wav", "a"),
+            ("peon/manifest.json", "{}"),
+            ("notes.txt", "x"),
+        ]));
+        assert_eq!(packs.keys().collect::<Vec<_>>(), vec!["glados", "peon"]);
+        assert!(packs["glados"].files.contains_key("sounds/A.wav"));
+    }
+
+    #[test]
+    fn tree_paths_are_pruned() {
+        assert!(is_pack_tree_file("manifest.json"));
+        assert!(is_pack_tree_file("packs/peon/sounds/A.wav"));
+        assert!(is_pack_tree_file("peon/manifest.json"));
+        assert!(!is_pack_tree_file("packs/peon/extra/A.wav"));
+        assert!(!is_pack_tree_file(".git/config"));
+        assert!(!is_pack_tree_file("peon/.DS_Store"));
+        assert!(is_pack_tree_dir("packs/peon/sounds"));
+        assert!(!is_pack_tree_dir("src/bin"));
+    }
+}
@@ -0,0 +1,163 @@
+use serde_json::Value;
+
+use crate::glob_match;
+
+/// Response fields that carry a Bash exit status, depending on the Claude Code version.
+const EXIT_CODE_FIELDS: &[&str] = &["exit_code", "exitCode", "returnCode", "return_code"];
+
+/// A tool call that failed, as reported by a `PostToolUse` event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolFailure {
+    /// The command that ran (for Bash), otherwise the tool's name.
+    pub subject: String,
+    /// What gave the failure away, e.g. `exit code 2` or `interrupted`.
+    pub reason: String,
+}
+
+/// Inspect a `PostToolUse` payload for a failure: an `is_error` response, an interrupted
+/// tool, a non-zero exit code, or a line of error output (`stderr`, or the whole response
+/// when it's plain text) matching one of `patterns` (globs).
+pub fn detect_tool_failure(
+    tool_name: &str,
+    tool_input: &Value,
+    tool_response: &Value,
+    patterns: &[String],
+) -> Option<ToolFailure> {
+    let flag = |names: &[&str]| names.iter().any(|name| tool_response[*name] == true);
+    let exit_code = EXIT_CODE_FIELDS
+        .iter()
+        .find_map(|field| tool_response[*field].as_i64())
+        .filter(|code| *code != 0);
+    let output = match tool_response {
+        Value::String(text) => Some(text.as_str()),
+        _ => tool_response["stderr"].as_str(),
+    };
+    let matched = output.and_then(|output| {
+        output
+            .lines()
+            .map(str::trim)
+            .find(|line| patterns.iter().any(|pattern| glob_match(pattern, line)))
+    });
+
+    let reason = if flag(&["interrupted"]) {
+        "interrupted".to_string()
+    } else if let Some(code) = exit_code {
+        format!("exit code {code}")
+    } else if flag(&["is_error", "isError"]) {
+        "error".to_string()
+    } else {
+        format!("\"{}\"", matched?)
+    };
+    let subject = tool_input["command"]
+        .as_str()
+        .filter(|command| tool_name == "Bash" && !command.trim().is_empty())
+        .unwrap_or(tool_name)
+        .to_string();
+    Some(ToolFailure { subject, reason })
+}
+
+/// Whether an `error` sound may play at `now`, given the session's `last` one.
+pub fn error_cooldown_elapsed(last: Option<f64>, cooldown_secs: f64, now: f64) -> bool {
+    last.is_none_or(|last| now - last >= cooldown_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patterns() -> Vec<String> {
+        crate::types::Config::default().error_patterns
+    }
+
+    fn detect(tool: &str, input: Value, response: Value) -> Option<ToolFailure> {
+        detect_tool_failure(tool, &input, &response, &patterns())
+    }
+
+    #[test]
+    fn successful_calls_are_not_failures() {
+        let bash = json!({"command": "cargo build"});
+        assert_eq!(
+            detect(
+                "Bash",
+                bash.clone(),
+                json!({"stdout": "ok", "stderr": "", "interrupted": false})
+            ),
+            None
+        );
+        assert_eq!(detect("Bash", bash, json!({"exit_code": 0})), None);
+        assert_eq!(detect("Read", json!({}), json!({"file": "x"})), None);
+        assert_eq!(detect("Read", json!({}), Value::Null), None);
+    }
+
+    #[test]
+    fn non_zero_exit_codes_fail() {
+        let failure = detect(
+            "Bash",
+            json!({"command": "cargo test"}),
+            json!({"stdout": "", "exitCode": 101}),
+        )
+        .unwrap();
+        assert_eq!(failure.subject, "cargo test");
+        assert_eq!(failure.reason, "exit code 101");
+    }
+
+    #[test]
+    fn error_flags_and_interruptions_fail() {
+        let failure = detect(
+            "Edit",
+            json!({"file_path": "/x"}),
+            json!({"is_error": true}),
+        )
+        .unwrap();
+        assert_eq!(failure.subject, "Edit");
+        assert_eq!(failure.reason, "error");
+        let failure = detect(
+            "Bash",
+            json!({"command": "sleep 100"}),
+            json!({"interrupted": true}),
+        )
+        .unwrap();
+        assert_eq!(failure.reason, "interrupted");
+    }
+
+    #[test]
+    fn error_output_matching_patterns_fails() {
+        let failure = detect(
+            "Bash",
+            json!({"command": "cargo build"}),
+            json!({"stdout": "", "stderr": "   Compiling x\nerror: could not compile `x` (bin)\n"}),
+        )
+        .unwrap();
+        assert_eq!(failure.reason, "\"error: could not compile `x` (bin)\"");
+
+        // Only stderr is searched: stdout may legitimately mention errors
+        assert_eq!(
+            detect(
+                "Bash",
+                json!({"command": "grep -r 'npm ERR!' ."}),
+                json!({"stdout": "npm ERR! code 1", "stderr": ""}),
+            ),
+            None
+        );
+        assert!(detect("Task", json!({}), json!("npm ERR! missing script")).is_some());
+        assert_eq!(
+            detect_tool_failure(
+                "Bash",
+                &json!({"command": "make"}),
+                &json!({"stderr": "Oops"}),
+                &["Oops".to_string()]
+            )
+            .unwrap()
+            .subject,
+            "make"
+        );
+    }
+
+    #[test]
+    fn cooldown_spaces_out_errors() {
+        assert!(error_cooldown_elapsed(None, 30.0, 100.0));
+        assert!(!error_cooldown_elapsed(Some(80.0), 30.0, 100.0));
+        assert!(error_cooldown_elapsed(Some(70.0), 30.0, 100.0));
+    }
+}
@@ -11,12 +11,79 @@ pub struct Config {
     pub enabled: bool,
     #[serde(default)]
     pub categories: CategoryToggles,
+    /// Annoyance score at which each tier starts: tier `n` at `n × annoyed_threshold`.
     #[serde(default = "default_annoyed_threshold")]
     pub annoyed_threshold: u32,
+    /// Seconds for one prompt's worth of annoyance to wear off.
     #[serde(default = "default_annoyed_window")]
     pub annoyed_window_seconds: f64,
+    /// Number of annoyance tiers (`annoyed_1` … `annoyed_<n>`).
+    #[serde(default = "default_annoyed_levels")]
+    pub annoyed_levels: u32,
+    /// Probability (0–1) of playing `acknowledge` when a prompt is submitted.
+    #[serde(default = "default_acknowledge_chance")]
+    pub acknowledge_chance: f64,
+    /// Minimum time between two `acknowledge` sounds in the same session.
+    #[serde(default = "default_acknowledge_cooldown")]
+    pub acknowledge_cooldown_seconds: f64,
     #[serde(default)]
     pub pack_rotation: Vec<String>,
+    /// Per-category fallback lists, merged over the built-in defaults.
+    /// An empty list disables fallback for that category.
+    #[serde(default)]
+    pub category_fallbacks: HashMap<String, Vec<String>>,
+    /// Pack to borrow sounds from when the active pack cannot serve a category.
+    #[serde(default)]
+    pub fallback_pack: Option<String>,
+    /// URL of the pack registry index (`https://` or `file://`).
+    #[serde(default = "default_registry_url")]
+    pub registry_url: String,
+    /// Sources searched in order by `peon pull <pack>` before the registry.
+    #[serde(default)]
+    pub pack_sources: Vec<String>,
+    /// GitHub API base, e.g. `https://github.example.com/api/v3` for GitHub Enterprise.
+    #[serde(default = "default_github_api_url")]
+    pub github_api_url: String,
+    /// Connect and read timeout for network requests, in seconds.
+    #[serde(default = "default_http_timeout")]
+    pub http_timeout_seconds: f64,
+    /// How many times a request is retried after a network error, server error or rate limit.
+    #[serde(default = "default_http_retries")]
+    pub http_retries: u32,
+    /// PEM file of extra CA certificates to trust, e.g. a corporate TLS proxy's.
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
+    /// Check for new peon releases in the background and mention them on session start.
+    #[serde(default)]
+    pub update_check: bool,
+    /// Minimum time between background update checks.
+    #[serde(default = "default_update_check_interval")]
+    pub update_check_interval_hours: u64,
+    /// Release channel followed by `peon upgrade` and the update check.
+    #[serde(default)]
+    pub update_channel: crate::upgrade::Channel,
+    /// Globs matched against each line of a failing tool's error output.
+    #[serde(default = "default_error_patterns")]
+    pub error_patterns: Vec<String>,
+    /// Minimum time between two `error` sounds in the same session.
+    #[serde(default = "default_error_cooldown")]
+    pub error_cooldown_seconds: f64,
+    /// Send a desktop notification naming the failing command.
+    #[serde(default)]
+    pub notify_on_error: bool,
+    /// Minimum time between two sounds of a category, across sessions. Merged over the
+    /// built-in defaults.
+    #[serde(default)]
+    pub category_cooldowns: HashMap<String, f64>,
+    /// Minimum time after any sound before one of equal or lower priority plays.
+    #[serde(default = "default_min_sound_gap")]
+    pub min_sound_gap_seconds: f64,
+    /// Category priorities for the global gap, merged over the built-in defaults.
+    #[serde(default)]
+    pub category_priorities: HashMap<String, i32>,
+    /// Rules recognising agent sessions and sub-agent events, tried in order.
+    #[serde(default = "default_agent_rules")]
+    pub agent_rules: Vec<AgentRule>,
 }
 
 impl Default for Config {
@@ -28,11 +95,168 @@ impl Default for Config {
             categories: CategoryToggles::default(),
             annoyed_threshold: default_annoyed_threshold(),
             annoyed_window_seconds: default_annoyed_window(),
+            annoyed_levels: default_annoyed_levels(),
+            acknowledge_chance: default_acknowledge_chance(),
+            acknowledge_cooldown_seconds: default_acknowledge_cooldown(),
             pack_rotation: Vec::new(),
+            category_fallbacks: HashMap::new(),
+            fallback_pack: None,
+            registry_url: default_registry_url(),
+            pack_sources: Vec::new(),
+            github_api_url: default_github_api_url(),
+            http_timeout_seconds: default_http_timeout(),
+            http_retries: default_http_retries(),
+            ca_bundle: None,
+            update_check: false,
+            update_check_interval_hours: default_update_check_interval(),
+            update_channel: crate::upgrade::Channel::default(),
+            error_patterns: default_error_patterns(),
+            error_cooldown_seconds: default_error_cooldown(),
+            notify_on_error: false,
+            category_cooldowns: HashMap::new(),
+            min_sound_gap_seconds: default_min_sound_gap(),
+            category_priorities: HashMap::new(),
+            agent_rules: default_agent_rules(),
+        }
+    }
+}
+
+/// Recognises agent activity. Every condition that is set must hold, and a rule without
+/// conditions never matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AgentRule {
+    /// Label recorded as the reason a session was classified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Exact `permission_mode`, e.g. `delegate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<String>,
+    /// Match `SubagentStop` events. Applies to the event only, not its whole session.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub subagent: bool,
+    /// Glob matched against the session's working directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Environment marker: `NAME` (set and non-empty) or `NAME=glob`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Prefix of the session ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_prefix: Option<String>,
+    #[serde(default)]
+    pub behavior: AgentBehavior,
+}
+
+/// What peon does for activity matched by an [`AgentRule`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentBehavior {
+    /// No sounds, notifications or tab titles.
+    #[default]
+    Silent,
+    /// Tab titles and desktop notifications, but no sounds.
+    NotifyOnly,
+    /// Play this category instead of the event's usual sound.
+    Category(String),
+}
+
+/// Delegate-mode sessions and sub-agents are silent unless the config says otherwise.
+fn default_agent_rules() -> Vec<AgentRule> {
+    vec![
+        AgentRule {
+            permission_mode: Some("delegate".to_string()),
+            ..Default::default()
+        },
+        AgentRule {
+            subagent: true,
+            ..Default::default()
+        },
+    ]
+}
+
+/// Fallbacks used when a pack is missing a category and the config doesn't say otherwise.
+const DEFAULT_CATEGORY_FALLBACKS: &[(&str, &[&str])] = &[
+    ("resource_limit", &["error"]),
+    ("error", &["permission"]),
+    ("annoyed", &["acknowledge"]),
+    ("acknowledge", &["complete"]),
+];
+
+/// Cooldowns used unless the config says otherwise; other categories have none.
+const DEFAULT_CATEGORY_COOLDOWNS: &[(&str, f64)] = &[("complete", 10.0), ("greeting", 5.0)];
+
+/// Priorities used unless the config says otherwise; other categories get 1.
+const DEFAULT_CATEGORY_PRIORITIES: &[(&str, i32)] = &[
+    ("permission", 3),
+    ("error", 2),
+    ("resource_limit", 2),
+    ("acknowledge", 0),
+    ("annoyed", 0),
+];
+
+impl Config {
+    /// Cooldown for `category` in seconds: the configured one, or the built-in default.
+    /// Annoyance tiers share `annoyed`'s unless configured.
+    pub fn cooldown_for(&self, category: &str) -> f64 {
+        if let Some(secs) = self.category_cooldowns.get(category) {
+            return *secs;
+        }
+        if crate::annoyed_tier(category).is_some() {
+            return self.cooldown_for("annoyed");
+        }
+        DEFAULT_CATEGORY_COOLDOWNS
+            .iter()
+            .find(|(name, _)| *name == category)
+            .map_or(0.0, |(_, secs)| *secs)
+    }
+
+    /// Priority of `category`; annoyance tiers share `annoyed`'s unless configured.
+    pub fn priority_of(&self, category: &str) -> i32 {
+        if let Some(priority) = self.category_priorities.get(category) {
+            return *priority;
+        }
+        if crate::annoyed_tier(category).is_some() {
+            return self.priority_of("annoyed");
+        }
+        DEFAULT_CATEGORY_PRIORITIES
+            .iter()
+            .find(|(name, _)| *name == category)
+            .map_or(1, |(_, priority)| *priority)
+    }
+
+    /// Direct fallbacks for `category`: the configured list, or the built-in default.
+    pub fn fallbacks_for(&self, category: &str) -> Vec<String> {
+        if let Some(list) = self.category_fallbacks.get(category) {
+            return list.clone();
         }
+        if crate::annoyed_tier(category).is_some() {
+            return vec!["annoyed".to_string()];
+        }
+        DEFAULT_CATEGORY_FALLBACKS
+            .iter()
+            .find(|(name, _)| *name == category)
+            .map(|(_, list)| list.iter().map(|s| s.to_string()).collect())
+            .unwrap_or_default()
     }
 }
 
+/// Sound categories that events are routed to.
+pub const KNOWN_CATEGORIES: &[&str] = &[
+    "greeting",
+    "acknowledge",
+    "complete",
+    "error",
+    "permission",
+    "resource_limit",
+    "annoyed",
+];
+
+/// Whether events can be routed to `category`: one of [`KNOWN_CATEGORIES`] or an
+/// annoyance tier (`annoyed_1`, `annoyed_2`, …).
+pub fn is_known_category(category: &str) -> bool {
+    KNOWN_CATEGORIES.contains(&category) || crate::annoyed_tier(category).is_some()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CategoryToggles {
     #[serde(default = "default_true")]
@@ -75,6 +299,7 @@ impl CategoryToggles {
             "permission" => self.permission,
             "resource_limit" => self.resource_limit,
             "annoyed" => self.annoyed,
+            tier if crate::annoyed_tier(tier).is_some() => self.annoyed,
             _ => true,
         }
     }
@@ -84,6 +309,47 @@ fn default_active_pack() -> String {
     "peon".to_string()
 }
 
+fn default_registry_url() -> String {
+    crate::DEFAULT_REGISTRY_URL.to_string()
+}
+
+fn default_github_api_url() -> String {
+    crate::DEFAULT_GITHUB_API_URL.to_string()
+}
+
+fn default_http_timeout() -> f64 {
+    30.0
+}
+
+fn default_http_retries() -> u32 {
+    3
+}
+
+fn default_update_check_interval() -> u64 {
+    24
+}
+
+fn default_error_patterns() -> Vec<String> {
+    [
+        "error: could not compile*",
+        "error[E*",
+        "npm ERR!*",
+        "FAILED*",
+        "Traceback (most recent call last):*",
+    ]
+    .iter()
+    .map(|pattern| pattern.to_string())
+    .collect()
+}
+
+fn default_error_cooldown() -> f64 {
+    30.0
+}
+
+fn default_min_sound_gap() -> f64 {
+    2.0
+}
+
 fn default_volume() -> f64 {
     0.5
 }
@@ -100,6 +366,18 @@ fn default_annoyed_window() -> f64 {
     10.0
 }
 
+fn default_annoyed_levels() -> u32 {
+    3
+}
+
+fn default_acknowledge_chance() -> f64 {
+    0.5
+}
+
+fn default_acknowledge_cooldown() -> f64 {
+    60.0
+}
+
 /// Represents config with unknown/extra fields preserved for round-tripping.
 /// Used by shell when updating config to avoid losing unknown keys.
 pub type ConfigMap = HashMap<String, serde_json::Value>;
@@ -149,7 +427,27 @@ mod tests {
         assert!(config.categories.annoyed);
         assert_eq!(config.annoyed_threshold, 3);
         assert_eq!(config.annoyed_window_seconds, 10.0);
+        assert_eq!(config.annoyed_levels, 3);
         assert!(config.pack_rotation.is_empty());
+        assert!(config.category_fallbacks.is_empty());
+        assert!(config.fallback_pack.is_none());
+    }
+
+    #[test]
+    fn fallbacks_use_defaults_unless_configured() {
+        let mut config = Config::default();
+        assert_eq!(config.fallbacks_for("resource_limit"), vec!["error"]);
+        assert_eq!(config.fallbacks_for("annoyed"), vec!["acknowledge"]);
+        assert!(config.fallbacks_for("greeting").is_empty());
+        assert_eq!(config.fallbacks_for("annoyed_2"), vec!["annoyed"]);
+
+        config
+            .category_fallbacks
+            .insert("annoyed".into(), vec!["complete".into()]);
+        config.category_fallbacks.insert("error".into(), vec![]);
+        assert_eq!(config.fallbacks_for("annoyed"), vec!["complete"]);
+        assert!(config.fallbacks_for("error").is_empty());
+        assert_eq!(config.fallbacks_for("resource_limit"), vec!["error"]);
     }
 
     #[test]
@@ -30,6 +30,15 @@ pub enum HookEvent {
         #[serde(default)]
         permission_mode: String,
     },
+    /// A sub-agent (Task tool) finished; `session_id` is the parent session's.
+    SubagentStop {
+        #[serde(default)]
+        cwd: String,
+        #[serde(default)]
+        session_id: String,
+        #[serde(default)]
+        permission_mode: String,
+    },
     Notification {
         #[serde(default)]
         cwd: String,
@@ -39,6 +48,20 @@ pub enum HookEvent {
         permission_mode: String,
         #[serde(default)]
         notification_type: String,
+        #[serde(default)]
+        message: String,
+    },
+    /// About to compact the conversation; `trigger` is `auto` (context window full) or
+    /// `manual` (`/compact`).
+    PreCompact {
+        #[serde(default)]
+        cwd: String,
+        #[serde(default)]
+        session_id: String,
+        #[serde(default)]
+        permission_mode: String,
+        #[serde(default)]
+        trigger: String,
     },
     PermissionRequest {
         #[serde(default)]
@@ -52,6 +75,21 @@ pub enum HookEvent {
         #[serde(default)]
         tool_input: serde_json::Value,
     },
+    /// A tool call finished, successfully or not.
+    PostToolUse {
+        #[serde(default)]
+        cwd: String,
+        #[serde(default)]
+        session_id: String,
+        #[serde(default)]
+        permission_mode: String,
+        #[serde(default)]
+        tool_name: String,
+        #[serde(default)]
+        tool_input: serde_json::Value,
+        #[serde(default)]
+        tool_response: serde_json::Value,
+    },
 }
 
 impl HookEvent {
@@ -60,8 +98,11 @@ impl HookEvent {
             Self::SessionStart { session_id, .. }
             | Self::UserPromptSubmit { session_id, .. }
             | Self::Stop { session_id, .. }
+            | Self::SubagentStop { session_id, .. }
             | Self::Notification { session_id, .. }
-            | Self::PermissionRequest { session_id, .. } => session_id,
+            | Self::PreCompact { session_id, .. }
+            | Self::PermissionRequest { session_id, .. }
+            | Self::PostToolUse { session_id, .. } => session_id,
         }
     }
 
@@ -76,11 +117,20 @@ impl HookEvent {
             | Self::Stop {
                 permission_mode, ..
             }
+            | Self::SubagentStop {
+                permission_mode, ..
+            }
             | Self::Notification {
                 permission_mode, ..
             }
+            | Self::PreCompact {
+                permission_mode, ..
+            }
             | Self::PermissionRequest {
                 permission_mode, ..
+            }
+            | Self::PostToolUse {
+                permission_mode, ..
             } => permission_mode,
         }
     }
@@ -90,8 +140,11 @@ impl HookEvent {
             Self::SessionStart { cwd, .. }
             | Self::UserPromptSubmit { cwd, .. }
             | Self::Stop { cwd, .. }
+            | Self::SubagentStop { cwd, .. }
             | Self::Notification { cwd, .. }
-            | Self::PermissionRequest { cwd, .. } => cwd,
+            | Self::PreCompact { cwd, .. }
+            | Self::PermissionRequest { cwd, .. }
+            | Self::PostToolUse { cwd, .. } => cwd,
         }
     }
 }
@@ -123,6 +176,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deserialize_pre_compact() {
+        let json = r#"{"hook_event_name":"PreCompact","cwd":"/tmp","session_id":"s1","trigger":"auto","custom_instructions":""}"#;
+        let event: HookEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            event,
+            HookEvent::PreCompact {
+                cwd: "/tmp".into(),
+                session_id: "s1".into(),
+                permission_mode: String::new(),
+                trigger: "auto".into(),
+            }
+        );
+    }
+
     #[test]
     fn deserialize_permission_request() {
         let json = r#"{"hook_event_name":"PermissionRequest","cwd":"/tmp","session_id":"s1","permission_mode":"default","tool_name":"Bash","tool_input":{"command":"ls"}}"#;
@@ -140,6 +208,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deserialize_subagent_stop() {
+        let json = r#"{"hook_event_name":"SubagentStop","cwd":"/tmp","session_id":"s1","permission_mode":"default","stop_hook_active":false}"#;
+        let event: HookEvent = serde_json::from_str(json).unwrap();
+        assert!(matches!(event, HookEvent::SubagentStop { .. }));
+        assert_eq!(event.session_id(), "s1");
+    }
+
     #[test]
     fn deserialize_delegate_mode() {
         let json = r#"{"hook_event_name":"SessionStart","cwd":"/tmp","session_id":"agent-1","permission_mode":"delegate"}"#;
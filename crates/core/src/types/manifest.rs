@@ -1,26 +1,106 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Manifest {
     pub name: String,
     #[serde(default)]
     pub display_name: String,
-    #[serde(default)]
+    /// Pack version, used to detect outdated installs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Who recorded or assembled the pack.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// License or usage terms of the sound files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Where the sounds come from (game, URL, publisher).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Icon file inside the pack directory, or `"pack:icon.png"` once inherited from a base.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Installed pack whose categories this pack starts from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub categories: HashMap<String, Category>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Write categories in name order so generated manifests are stable.
+fn serialize_sorted<S: serde::Serializer>(
+    categories: &HashMap<String, Category>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted: std::collections::BTreeMap<_, _> = categories.iter().collect();
+    sorted.serialize(serializer)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Category {
     #[serde(default)]
     pub sounds: Vec<Sound>,
+    /// When extending another pack, add these sounds to the base category instead of
+    /// replacing it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub append: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Sound {
     pub file: String,
     #[serde(default)]
     pub line: String,
+    /// Relative likelihood of being picked (default 1.0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    /// Volume multiplier applied on top of the configured volume (default 1.0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Language of the voice line (e.g. `en`, `fr`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl Sound {
+    /// Pack directory and file name for this sound.
+    ///
+    /// Files may reference another installed pack as `"pack:File.wav"`; plain names
+    /// live in `pack`'s own `sounds/` directory.
+    pub fn location<'a>(&'a self, pack: &'a str) -> (&'a str, &'a str) {
+        match self.file.split_once(':') {
+            Some((other, file)) if !other.is_empty() => (other, file),
+            _ => (pack, self.file.as_str()),
+        }
+    }
+
+    /// Whether the file resolves to a pack's `sounds/` directory: any referenced pack
+    /// must have a valid pack name and the file must be a plain file name, since sounds
+    /// aren't looked up (or exported) in subdirectories.
+    pub fn has_safe_location(&self) -> bool {
+        let (pack, file) = self.location("");
+        (pack.is_empty() || crate::archive::is_valid_pack_name(pack))
+            && !file.contains('/')
+            && crate::archive::check_entry_path(file).is_ok()
+    }
+
+    /// Selection weight, defaulting to 1.0. Negative or non-finite weights count as 0.
+    pub fn weight(&self) -> f64 {
+        match self.weight {
+            Some(w) if w.is_finite() && w > 0.0 => w,
+            Some(_) => 0.0,
+            None => 1.0,
+        }
+    }
+
+    /// Playback volume for this sound given the configured volume.
+    pub fn effective_volume(&self, volume: f64) -> f64 {
+        let gain = self.gain.filter(|g| g.is_finite()).unwrap_or(1.0);
+        (volume * gain).max(0.0)
+    }
 }
 
 #[cfg(test)]
@@ -58,6 +138,127 @@ mod tests {
         assert_eq!(manifest.categories["annoyed"].sounds[0].line, "Whaaat?");
     }
 
+    #[test]
+    fn deserialize_manifest_v2_fields() {
+        let json = r#"{
+            "name": "peon",
+            "display_name": "Orc Peon",
+            "version": "1.2.0",
+            "author": "tonyyont",
+            "license": "Blizzard Entertainment",
+            "source": "Warcraft III",
+            "icon": "icon.png",
+            "categories": {
+                "greeting": {
+                    "sounds": [
+                        {"file": "PeonReady1.wav", "line": "Ready to work?", "weight": 3, "gain": 0.5, "tags": ["classic"], "language": "en"}
+                    ]
+                }
+            }
+        }"#;
+
+        let manifest: Manifest = serde_json::from_str(json).unwrap();
+        assert_eq!(manifest.version.as_deref(), Some("1.2.0"));
+        assert_eq!(manifest.author.as_deref(), Some("tonyyont"));
+        assert_eq!(manifest.license.as_deref(), Some("Blizzard Entertainment"));
+        assert_eq!(manifest.source.as_deref(), Some("Warcraft III"));
+        assert_eq!(manifest.icon.as_deref(), Some("icon.png"));
+        let sound = &manifest.categories["greeting"].sounds[0];
+        assert_eq!(sound.weight(), 3.0);
+        assert_eq!(sound.gain, Some(0.5));
+        assert_eq!(sound.tags, vec!["classic"]);
+        assert_eq!(sound.language.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn v1_manifest_round_trips_unchanged() {
+        let json = r#"{"name":"peon","display_name":"Orc Peon","categories":{"greeting":{"sounds":[{"file":"a.wav","line":"Hi"}]}}}"#;
+        let manifest: Manifest = serde_json::from_str(json).unwrap();
+        let value = serde_json::to_value(&manifest).unwrap();
+        let original: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn sound_weight_defaults_and_clamps() {
+        let mut sound = Sound {
+            file: "a.wav".into(),
+            ..Default::default()
+        };
+        assert_eq!(sound.weight(), 1.0);
+        sound.weight = Some(-2.0);
+        assert_eq!(sound.weight(), 0.0);
+        sound.weight = Some(f64::NAN);
+        assert_eq!(sound.weight(), 0.0);
+    }
+
+    #[test]
+    fn sound_location_handles_pack_references() {
+        let local = Sound {
+            file: "PeonYes1.wav".into(),
+            ..Default::default()
+        };
+        assert_eq!(local.location("mine"), ("mine", "PeonYes1.wav"));
+
+        let borrowed = Sound {
+            file: "sc_kerrigan:KerriganYes1.wav".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            borrowed.location("mine"),
+            ("sc_kerrigan", "KerriganYes1.wav")
+        );
+    }
+
+    #[test]
+    fn sound_locations_outside_sounds_dirs_are_unsafe() {
+        for file in ["PeonYes1.wav", "sc_kerrigan:KerriganYes1.wav"] {
+            let sound = Sound {
+                file: file.into(),
+                ..Default::default()
+            };
+            assert!(sound.has_safe_location(), "{file}");
+        }
+        for file in [
+            "../../x:evil.wav",
+            "/abs:f.wav",
+            "peon:../x.wav",
+            "../x.wav",
+            ".hidden:a.wav",
+            "sfx/Ding.wav",
+        ] {
+            let sound = Sound {
+                file: file.into(),
+                ..Default::default()
+            };
+            assert!(!sound.has_safe_location(), "{file}");
+        }
+    }
+
+    #[test]
+    fn deserialize_extends_and_append() {
+        let json = r#"{
+            "name": "peon_plus",
+            "extends": "peon",
+            "categories": {
+                "permission": {"append": true, "sounds": [{"file": "sc_kerrigan:KerriganYes1.wav"}]}
+            }
+        }"#;
+        let manifest: Manifest = serde_json::from_str(json).unwrap();
+        assert_eq!(manifest.extends.as_deref(), Some("peon"));
+        assert!(manifest.categories["permission"].append);
+    }
+
+    #[test]
+    fn sound_effective_volume_applies_gain() {
+        let mut sound = Sound::default();
+        assert_eq!(sound.effective_volume(0.5), 0.5);
+        sound.gain = Some(0.5);
+        assert_eq!(sound.effective_volume(0.5), 0.25);
+        sound.gain = Some(-1.0);
+        assert_eq!(sound.effective_volume(0.5), 0.0);
+    }
+
     #[test]
     fn deserialize_manifest_missing_categories() {
         let json = r#"{"name": "minimal", "display_name": "Minimal Pack"}"#;
@@ -5,7 +5,10 @@ mod manifest;
 mod state;
 
 pub use action::{Action, NotifyColor};
-pub use config::{CategoryToggles, Config, ConfigMap};
+pub use config::{
+    is_known_category, AgentBehavior, AgentRule, CategoryToggles, Config, ConfigMap,
+    KNOWN_CATEGORIES,
+};
 pub use event::HookEvent;
 pub use manifest::{Category, Manifest, Sound};
-pub use state::State;
+pub use state::{AgentSession, Annoyance, State, SuppressedPlay};
@@ -1,47 +1,86 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+
+use super::AgentBehavior;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct State {
     #[serde(default)]
     pub last_played: HashMap<String, String>,
+    #[serde(default, deserialize_with = "deserialize_agent_sessions")]
+    pub agent_sessions: HashMap<String, AgentSession>,
+    /// Annoyance per session, built up by rapid prompts.
+    #[serde(default)]
+    pub annoyance: HashMap<String, Annoyance>,
+    /// When each session last played `acknowledge` (Unix seconds).
+    #[serde(default)]
+    pub last_acknowledged: HashMap<String, f64>,
+    /// When each session last played `error` (Unix seconds).
+    #[serde(default)]
+    pub last_error: HashMap<String, f64>,
+    /// When each category last played, in any session (Unix seconds).
     #[serde(default)]
-    pub agent_sessions: HashSet<String>,
-    #[serde(default, deserialize_with = "deserialize_prompt_timestamps")]
-    pub prompt_timestamps: HashMap<String, Vec<f64>>,
+    pub last_sound_at: HashMap<String, f64>,
+    /// Recent sounds held back by cooldowns, oldest first.
+    #[serde(default)]
+    pub suppressed: Vec<SuppressedPlay>,
     #[serde(default)]
     pub session_packs: HashMap<String, String>,
 }
 
-/// The bash/python version could write prompt_timestamps as either a list (legacy) or
-/// a map. We handle both by defaulting to empty map if deserialization fails for the
-/// legacy list format. In practice the Rust version always writes the map format.
-fn deserialize_prompt_timestamps<'de, D>(
+/// How annoyed the peon is with a session, as of `updated_at` (Unix seconds).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct Annoyance {
+    pub score: f64,
+    pub updated_at: f64,
+}
+
+/// A sound that a cooldown or the global gap kept from playing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SuppressedPlay {
+    pub category: String,
+    pub session_id: String,
+    /// Unix seconds.
+    pub at: f64,
+    pub reason: String,
+}
+
+/// Why a session was classified as an agent, remembered for the rest of the session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentSession {
+    /// The matching rule's name, or a description of its conditions.
+    pub reason: String,
+    #[serde(default)]
+    pub behavior: AgentBehavior,
+}
+
+/// Older versions stored agent sessions as a list of IDs, all detected by delegate mode.
+fn deserialize_agent_sessions<'de, D>(
     deserializer: D,
-) -> Result<HashMap<String, Vec<f64>>, D::Error>
+) -> Result<HashMap<String, AgentSession>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    use serde::de::Error;
-    let value = serde_json::Value::deserialize(deserializer)?;
-    match value {
-        serde_json::Value::Object(map) => {
-            let mut result = HashMap::new();
-            for (k, v) in map {
-                let timestamps: Vec<f64> = serde_json::from_value(v).map_err(D::Error::custom)?;
-                result.insert(k, timestamps);
-            }
-            Ok(result)
-        }
-        serde_json::Value::Array(_) => {
-            // Legacy format: was a flat list, discard and start fresh
-            Ok(HashMap::new())
-        }
-        serde_json::Value::Null => Ok(HashMap::new()),
-        _ => Err(D::Error::custom(
-            "expected object or array for prompt_timestamps",
-        )),
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Sessions {
+        Map(HashMap<String, AgentSession>),
+        Legacy(Vec<String>),
     }
+    Ok(match Option::<Sessions>::deserialize(deserializer)? {
+        Some(Sessions::Map(map)) => map,
+        Some(Sessions::Legacy(ids)) => ids
+            .into_iter()
+            .map(|id| {
+                let session = AgentSession {
+                    reason: "permission_mode delegate".to_string(),
+                    behavior: AgentBehavior::Silent,
+                };
+                (id, session)
+            })
+            .collect(),
+        None => HashMap::new(),
+    })
 }
 
 #[cfg(test)]
@@ -53,7 +92,7 @@ mod tests {
         let state: State = serde_json::from_str("{}").unwrap();
         assert!(state.last_played.is_empty());
         assert!(state.agent_sessions.is_empty());
-        assert!(state.prompt_timestamps.is_empty());
+        assert!(state.annoyance.is_empty());
         assert!(state.session_packs.is_empty());
     }
 
@@ -62,9 +101,8 @@ mod tests {
         let json = r#"{
             "last_played": {"greeting": "PeonReady1.wav", "complete": "PeonYes1.wav"},
             "agent_sessions": ["agent-123", "agent-456"],
-            "prompt_timestamps": {
-                "session-1": [1708000000.123, 1708000001.456],
-                "session-2": [1708000010.0]
+            "annoyance": {
+                "session-1": {"score": 2.5, "updated_at": 1708000001.456}
             },
             "session_packs": {
                 "session-1": "sc_kerrigan"
@@ -73,18 +111,21 @@ mod tests {
 
         let state: State = serde_json::from_str(json).unwrap();
         assert_eq!(state.last_played.get("greeting").unwrap(), "PeonReady1.wav");
-        assert!(state.agent_sessions.contains("agent-123"));
-        assert_eq!(state.prompt_timestamps["session-1"].len(), 2);
+        assert_eq!(
+            state.agent_sessions["agent-123"].reason,
+            "permission_mode delegate"
+        );
+        assert_eq!(state.annoyance["session-1"].score, 2.5);
         assert_eq!(state.session_packs["session-1"], "sc_kerrigan");
     }
 
     #[test]
-    fn deserialize_legacy_list_timestamps() {
+    fn legacy_prompt_timestamps_are_ignored() {
         let json = r#"{
-            "prompt_timestamps": [1.0, 2.0, 3.0]
+            "prompt_timestamps": {"session-1": [1.0, 2.0, 3.0]}
         }"#;
         let state: State = serde_json::from_str(json).unwrap();
-        assert!(state.prompt_timestamps.is_empty());
+        assert!(state.annoyance.is_empty());
     }
 
     #[test]
@@ -93,10 +134,17 @@ mod tests {
         state
             .last_played
             .insert("greeting".into(), "test.wav".into());
-        state.agent_sessions.insert("sess-1".into());
+        state.agent_sessions.insert(
+            "sess-1".into(),
+            AgentSession {
+                reason: "ci".into(),
+                behavior: AgentBehavior::NotifyOnly,
+            },
+        );
 
         let json = serde_json::to_string(&state).unwrap();
         let restored: State = serde_json::from_str(&json).unwrap();
         assert_eq!(state.last_played, restored.last_played);
+        assert_eq!(state.agent_sessions, restored.agent_sessions);
     }
 }
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Version;
+
+/// File in the peon data directory caching the result of the last update check.
+pub const UPDATE_CACHE_FILE: &str = ".update-check.json";
+
+/// Result of the last update check, so hooks can report updates without the network.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateCache {
+    /// Unix time of the last check attempt, successful or not.
+    pub checked_at: u64,
+    /// Newest release found on the configured channel, if the check succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+}
+
+/// Whether a background check should run: none yet, or the last attempt is older than
+/// `interval_hours`. A clock that went backwards also counts as due.
+pub fn update_check_due(cache: Option<&UpdateCache>, now: u64, interval_hours: u64) -> bool {
+    match cache {
+        None => true,
+        Some(cache) => {
+            now < cache.checked_at || now - cache.checked_at >= interval_hours.saturating_mul(3600)
+        }
+    }
+}
+
+/// The cached latest version, if it is newer than `current`.
+pub fn available_update<'a>(cache: &'a UpdateCache, current: &str) -> Option<&'a str> {
+    let latest = cache.latest.as_deref()?;
+    let newer = Version::parse(latest).ok()? > Version::parse(current).ok()?;
+    newer.then_some(latest)
+}
+
+/// One-line notice for a cached newer version, e.g. for the `SessionStart` hook.
+pub fn update_notice(cache: &UpdateCache, current: &str) -> Option<String> {
+    available_update(cache, current).map(|latest| {
+        format!(
+            "peon-ping: version {latest} is available (you have {current}) — run 'peon upgrade'"
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(checked_at: u64, latest: Option<&str>) -> UpdateCache {
+        UpdateCache {
+            checked_at,
+            latest: latest.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn check_is_due_after_interval() {
+        let now = 1_000_000;
+        assert!(update_check_due(None, now, 24));
+        assert!(!update_check_due(Some(&cache(now - 3600, None)), now, 24));
+        assert!(update_check_due(
+            Some(&cache(now - 24 * 3600, None)),
+            now,
+            24
+        ));
+        assert!(update_check_due(Some(&cache(now + 60, None)), now, 24));
+        assert!(update_check_due(Some(&cache(now, None)), now, 0));
+    }
+
+    #[test]
+    fn notices_only_newer_versions() {
+        assert_eq!(
+            update_notice(&cache(0, Some("3.1.0")), "3.0.1").unwrap(),
+            "peon-ping: version 3.1.0 is available (you have 3.0.1) — run 'peon upgrade'"
+        );
+        assert_eq!(update_notice(&cache(0, Some("3.0.1")), "3.0.1"), None);
+        assert_eq!(update_notice(&cache(0, Some("3.1.0-rc.1")), "3.1.0"), None);
+        assert_eq!(update_notice(&cache(0, None), "3.0.1"), None);
+        assert_eq!(update_notice(&cache(0, Some("garbage")), "3.0.1"), None);
+    }
+
+    #[test]
+    fn cache_round_trips() {
+        let original = cache(42, Some("3.1.0"));
+        let json = serde_json::to_string(&original).unwrap();
+        assert_eq!(json, r#"{"checked_at":42,"latest":"3.1.0"}"#);
+        assert_eq!(
+            serde_json::from_str::<UpdateCache>(&json).unwrap(),
+            original
+        );
+    }
+}
@@ -1,9 +1,45 @@
-use serde::Deserialize;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+use crate::Version;
+
+/// Release asset listing the SHA-256 of every binary, in `sha256sum` format.
+pub const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+/// Minisign signature of [`CHECKSUMS_ASSET`].
+pub const SIGNATURE_ASSET: &str = "SHA256SUMS.minisig";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum VerifyError {
+    #[error("invalid {CHECKSUMS_ASSET} line {line}: {text}")]
+    BadChecksumLine { line: usize, text: String },
+    #[error("{asset} is not listed in {CHECKSUMS_ASSET}")]
+    Unlisted { asset: String },
+    #[error("checksum mismatch for {asset}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("invalid minisign public key: {0}")]
+    BadPublicKey(&'static str),
+    #[error("invalid {SIGNATURE_ASSET}: {0}")]
+    BadSignature(&'static str),
+    #[error("{SIGNATURE_ASSET} was made with key {found}, expected {expected}")]
+    WrongKey { expected: String, found: String },
+    #[error("{SIGNATURE_ASSET} does not match {CHECKSUMS_ASSET}")]
+    InvalidSignature,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct GitHubRelease {
     pub tag_name: String,
     pub assets: Vec<GitHubAsset>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -20,42 +56,231 @@ pub fn parse_version_tag(tag: &str) -> &str {
 
 /// Compare two semver strings. Returns Ok(true) if `current` >= `latest`.
 pub fn is_version_up_to_date(current: &str, latest: &str) -> Result<bool, String> {
-    let current_parts = parse_semver(current)?;
-    let latest_parts = parse_semver(latest)?;
-    Ok(current_parts >= latest_parts)
-}
-
-fn parse_semver(version: &str) -> Result<(u64, u64, u64), String> {
-    let parts: Vec<&str> = version.split('.').collect();
-    if parts.len() != 3 {
-        return Err(format!("invalid semver: {version}"));
-    }
-    let major = parts[0]
-        .parse::<u64>()
-        .map_err(|_| format!("invalid major version: {}", parts[0]))?;
-    let minor = parts[1]
-        .parse::<u64>()
-        .map_err(|_| format!("invalid minor version: {}", parts[1]))?;
-    let patch = parts[2]
-        .parse::<u64>()
-        .map_err(|_| format!("invalid patch version: {}", parts[2]))?;
-    Ok((major, minor, patch))
-}
-
-/// Get the expected asset name for a given OS and architecture.
-pub fn get_asset_name(os: &str, arch: &str) -> Result<String, String> {
+    Ok(Version::parse(current)? >= Version::parse(latest)?)
+}
+
+/// Which releases `peon upgrade` follows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Full releases only.
+    #[default]
+    Stable,
+    /// Pre-releases too (`3.1.0-rc.1`, or releases marked as pre-releases on GitHub).
+    Prerelease,
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Self::Stable),
+            "prerelease" => Ok(Self::Prerelease),
+            _ => Err(format!(
+                "unknown channel {s} (expected stable or prerelease)"
+            )),
+        }
+    }
+}
+
+/// The release's version, if its tag is valid semver.
+pub fn release_version(release: &GitHubRelease) -> Option<Version> {
+    Version::parse(&release.tag_name).ok()
+}
+
+fn is_prerelease(release: &GitHubRelease, version: &Version) -> bool {
+    release.prerelease || version.is_prerelease()
+}
+
+/// Published releases on `channel` with a semver tag, newest first.
+pub fn channel_releases(
+    releases: &[GitHubRelease],
+    channel: Channel,
+) -> Vec<(&GitHubRelease, Version)> {
+    let mut found: Vec<(&GitHubRelease, Version)> = releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| release_version(release).map(|version| (release, version)))
+        .filter(|(release, version)| {
+            channel == Channel::Prerelease || !is_prerelease(release, version)
+        })
+        .collect();
+    found.sort_by(|a, b| b.1.cmp(&a.1));
+    found
+}
+
+/// The newest release on `channel`.
+pub fn latest_release(releases: &[GitHubRelease], channel: Channel) -> Option<&GitHubRelease> {
+    channel_releases(releases, channel)
+        .first()
+        .map(|(release, _)| *release)
+}
+
+/// The published release whose tag is `version` (`3.1.0` and `v3.1.0` both match).
+pub fn find_release<'a>(
+    releases: &'a [GitHubRelease],
+    version: &str,
+) -> Result<Option<&'a GitHubRelease>, String> {
+    let wanted = Version::parse(version)?;
+    Ok(releases
+        .iter()
+        .filter(|release| !release.draft)
+        .find(|release| release_version(release).as_ref() == Some(&wanted)))
+}
+
+/// List releases on `channel` for `peon upgrade --list`, newest first, marking the
+/// installed version (`*`), the newest release and pre-releases.
+pub fn format_release_list(releases: &[GitHubRelease], current: &str, channel: Channel) -> String {
+    let listed = channel_releases(releases, channel);
+    if listed.is_empty() {
+        return "No releases found.\n".to_string();
+    }
+    let current = Version::parse(current).ok();
+    let width = listed
+        .iter()
+        .map(|(_, version)| version.to_string().len())
+        .max()
+        .unwrap_or(0);
+    listed
+        .iter()
+        .enumerate()
+        .map(|(index, (release, version))| {
+            let installed = current.as_ref() == Some(version);
+            let mut notes = Vec::new();
+            if installed {
+                notes.push("installed");
+            }
+            if index == 0 {
+                notes.push("latest");
+            }
+            if is_prerelease(release, version) {
+                notes.push("pre-release");
+            }
+            let line = format!(
+                "{} {:width$}  {}",
+                if installed { "*" } else { " " },
+                version.to_string(),
+                notes.join(", ")
+            );
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+/// Get the expected asset name for a given OS, architecture and (on Linux) C library,
+/// as in `cfg!(target_env)`: `gnu` or `musl`.
+pub fn get_asset_name(os: &str, arch: &str, target_env: &str) -> Result<String, String> {
     let target_arch = match arch {
         "aarch64" => "aarch64",
         "x86_64" => "x86_64",
         _ => return Err(format!("unsupported architecture: {arch}")),
     };
 
-    match os {
-        "macos" => Ok(format!("peon-{target_arch}-apple-darwin")),
+    match (os, target_env) {
+        ("macos", _) => Ok(format!("peon-{target_arch}-apple-darwin")),
+        ("linux", "gnu" | "musl") => Ok(format!("peon-{target_arch}-unknown-linux-{target_env}")),
+        ("linux", _) => Err(format!("unsupported C library: {target_env}")),
         _ => Err(format!("unsupported OS: {os}")),
     }
 }
 
+/// How a release asset packages the binary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssetFormat {
+    /// The executable itself.
+    Binary,
+    /// A gzipped tarball containing a `peon` executable.
+    TarGz,
+}
+
+/// Find the asset for `asset_name`: the bare binary, or else `<asset_name>.tar.gz`.
+pub fn find_release_asset<'a>(
+    release: &'a GitHubRelease,
+    asset_name: &str,
+) -> Option<(&'a GitHubAsset, AssetFormat)> {
+    find_matching_asset(release, asset_name)
+        .map(|asset| (asset, AssetFormat::Binary))
+        .or_else(|| {
+            find_matching_asset(release, &format!("{asset_name}.tar.gz"))
+                .map(|asset| (asset, AssetFormat::TarGz))
+        })
+}
+
+/// Who manages the installed binary. Only standalone installs are replaced in place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstallMethod {
+    Standalone,
+    Cargo,
+    Homebrew,
+    Nix,
+    /// A system package manager (apt, dnf, pacman, …) owns the file.
+    PackageManager,
+}
+
+impl InstallMethod {
+    /// How to upgrade a managed install, or `None` for standalone ones.
+    pub fn upgrade_hint(self) -> Option<&'static str> {
+        match self {
+            Self::Standalone => None,
+            Self::Cargo => Some(
+                "installed with cargo; upgrade with \
+                 `cargo install --git https://github.com/cloudbridgeuy/peon-ping peon`",
+            ),
+            Self::Homebrew => Some("installed with Homebrew; upgrade with `brew upgrade peon`"),
+            Self::Nix => Some("installed with Nix; upgrade it through your Nix configuration"),
+            Self::PackageManager => {
+                Some("installed by a system package manager; upgrade it with that package manager")
+            }
+        }
+    }
+}
+
+/// Work out how the binary at `exe` (with symlinks resolved) was installed from its
+/// location. `cargo_home` is `$CARGO_HOME`, if set.
+pub fn detect_install_method(exe: &Path, cargo_home: Option<&Path>) -> InstallMethod {
+    let under = |dir: &str| exe.components().any(|c| c.as_os_str() == dir);
+    if cargo_home.is_some_and(|home| exe.starts_with(home.join("bin"))) || under(".cargo") {
+        InstallMethod::Cargo
+    } else if under("Cellar") || under("homebrew") || under("linuxbrew") {
+        InstallMethod::Homebrew
+    } else if exe.starts_with("/nix/store") {
+        InstallMethod::Nix
+    } else if ["/usr/bin", "/usr/sbin", "/bin", "/sbin", "/snap"]
+        .iter()
+        .any(|dir| exe.starts_with(dir))
+    {
+        InstallMethod::PackageManager
+    } else {
+        InstallMethod::Standalone
+    }
+}
+
+/// The binary replaced by the last upgrade or rollback, kept for `peon upgrade --rollback`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviousBinary {
+    /// Version of the kept binary.
+    pub version: String,
+    /// Unix time it was replaced.
+    pub replaced_at: u64,
+}
+
+/// Where the previous binary and its [`PreviousBinary`] record live, next to the installed
+/// `exe` so restoring it is a rename on the same filesystem.
+pub fn previous_binary_paths(exe: &Path) -> (PathBuf, PathBuf) {
+    (
+        exe.with_extension("previous"),
+        exe.with_extension("previous.json"),
+    )
+}
+
+/// The version in `peon --version` output (`peon 3.0.1`), used to smoke-test a freshly
+/// installed binary.
+pub fn parse_version_output(stdout: &str) -> Option<Version> {
+    let version = stdout.trim().strip_prefix("peon ")?;
+    Version::parse(version).ok()
+}
+
 /// Find a matching asset in a release by name.
 pub fn find_matching_asset<'a>(
     release: &'a GitHubRelease,
@@ -64,6 +289,140 @@ pub fn find_matching_asset<'a>(
     release.assets.iter().find(|a| a.name == asset_name)
 }
 
+/// Check `bytes` of the release asset `asset` against its entry in a [`CHECKSUMS_ASSET`]
+/// manifest. An asset missing from the manifest fails like a mismatch.
+pub fn verify_checksum(manifest: &str, asset: &str, bytes: &[u8]) -> Result<(), VerifyError> {
+    let checksums = crate::parse_checksums(manifest).map_err(|e| match e {
+        crate::ArchiveError::BadChecksumLine { line, text } => {
+            VerifyError::BadChecksumLine { line, text }
+        }
+        other => VerifyError::BadChecksumLine {
+            line: 0,
+            text: other.to_string(),
+        },
+    })?;
+    let expected = checksums.get(asset).ok_or_else(|| VerifyError::Unlisted {
+        asset: asset.to_string(),
+    })?;
+    let actual = crate::sha256_hex(bytes);
+    if *expected != actual {
+        return Err(VerifyError::ChecksumMismatch {
+            asset: asset.to_string(),
+            expected: expected.clone(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// An Ed25519 minisign public key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: [u8; 32],
+}
+
+impl PublicKey {
+    /// Parse a minisign public key: the contents of a `.pub` file, or just its base64 line.
+    pub fn parse(text: &str) -> Result<Self, VerifyError> {
+        let line = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .ok_or(VerifyError::BadPublicKey("empty"))?;
+        let bytes = decode_base64(line).ok_or(VerifyError::BadPublicKey("not base64"))?;
+        let (algorithm, rest) = bytes
+            .split_first_chunk::<2>()
+            .ok_or(VerifyError::BadPublicKey("too short"))?;
+        if algorithm != b"Ed" {
+            return Err(VerifyError::BadPublicKey("not an Ed25519 key"));
+        }
+        let (key_id, key) = rest
+            .split_first_chunk::<8>()
+            .ok_or(VerifyError::BadPublicKey("too short"))?;
+        let key: [u8; 32] = key
+            .try_into()
+            .map_err(|_| VerifyError::BadPublicKey("wrong length"))?;
+        Ok(Self {
+            key_id: *key_id,
+            key,
+        })
+    }
+
+    /// Key ID as minisign prints it.
+    pub fn key_id(&self) -> String {
+        format_key_id(&self.key_id)
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, self.key)
+            .verify(message, signature)
+            .is_ok()
+    }
+}
+
+fn format_key_id(key_id: &[u8; 8]) -> String {
+    key_id.iter().rev().map(|b| format!("{b:02X}")).collect()
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD.decode(text).ok()
+}
+
+/// Verify a minisign signature file over `message`, including its trusted comment.
+///
+/// Only legacy signatures (`minisign -S -l`, algorithm `Ed`) are supported; prehashed
+/// ones (`ED`) are rejected.
+pub fn verify_signature(
+    key: &PublicKey,
+    signature_file: &str,
+    message: &[u8],
+) -> Result<(), VerifyError> {
+    let mut lines = signature_file
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("untrusted comment:"));
+    let signature_line = lines.next().ok_or(VerifyError::BadSignature("empty"))?;
+    let trusted_comment = lines
+        .next()
+        .and_then(|line| line.strip_prefix("trusted comment: "))
+        .ok_or(VerifyError::BadSignature("missing trusted comment"))?;
+    let global_signature = lines
+        .next()
+        .and_then(decode_base64)
+        .ok_or(VerifyError::BadSignature("missing global signature"))?;
+
+    let bytes = decode_base64(signature_line).ok_or(VerifyError::BadSignature("not base64"))?;
+    let (algorithm, rest) = bytes
+        .split_first_chunk::<2>()
+        .ok_or(VerifyError::BadSignature("too short"))?;
+    match algorithm {
+        b"Ed" => {}
+        b"ED" => {
+            return Err(VerifyError::BadSignature(
+                "prehashed signatures are not supported; sign with `minisign -S -l`",
+            ))
+        }
+        _ => return Err(VerifyError::BadSignature("unknown algorithm")),
+    }
+    let (key_id, signature) = rest
+        .split_first_chunk::<8>()
+        .ok_or(VerifyError::BadSignature("too short"))?;
+    if *key_id != key.key_id {
+        return Err(VerifyError::WrongKey {
+            expected: key.key_id(),
+            found: format_key_id(key_id),
+        });
+    }
+
+    let mut signed_comment = signature.to_vec();
+    signed_comment.extend_from_slice(trusted_comment.as_bytes());
+    if !key.verify(message, signature) || !key.verify(&signed_comment, &global_signature) {
+        return Err(VerifyError::InvalidSignature);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,18 +449,130 @@ mod tests {
         assert!(is_version_up_to_date("1.0", "1.0.0").is_err());
     }
 
+    #[test]
+    fn pre_releases_compare_below_their_release() {
+        assert!(!is_version_up_to_date("3.0.1", "3.1.0-rc.1").unwrap());
+        assert!(!is_version_up_to_date("3.1.0-rc.1", "3.1.0").unwrap());
+        assert!(is_version_up_to_date("3.1.0-rc.2", "3.1.0-rc.1").unwrap());
+        assert!(is_version_up_to_date("3.1.0+build.7", "3.1.0").unwrap());
+    }
+
+    fn release(tag: &str) -> GitHubRelease {
+        GitHubRelease {
+            tag_name: tag.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn releases() -> Vec<GitHubRelease> {
+        vec![
+            release("v3.0.1"),
+            release("v3.1.0-rc.1"),
+            GitHubRelease {
+                prerelease: true,
+                ..release("v3.0.2")
+            },
+            release("v2.9.0"),
+            GitHubRelease {
+                draft: true,
+                ..release("v4.0.0")
+            },
+            release("nightly"),
+        ]
+    }
+
+    fn tags(listed: Vec<(&GitHubRelease, Version)>) -> Vec<&str> {
+        listed
+            .into_iter()
+            .map(|(release, _)| release.tag_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn channels_pick_latest_release() {
+        let releases = releases();
+        assert_eq!(
+            tags(channel_releases(&releases, Channel::Stable)),
+            vec!["v3.0.1", "v2.9.0"]
+        );
+        assert_eq!(
+            tags(channel_releases(&releases, Channel::Prerelease)),
+            vec!["v3.1.0-rc.1", "v3.0.2", "v3.0.1", "v2.9.0"]
+        );
+        assert_eq!(
+            latest_release(&releases, Channel::Stable).unwrap().tag_name,
+            "v3.0.1"
+        );
+        assert_eq!(
+            latest_release(&releases, Channel::Prerelease)
+                .unwrap()
+                .tag_name,
+            "v3.1.0-rc.1"
+        );
+        assert!(latest_release(&[], Channel::Stable).is_none());
+        assert_eq!("prerelease".parse(), Ok(Channel::Prerelease));
+        assert!("beta".parse::<Channel>().is_err());
+    }
+
+    #[test]
+    fn finds_pinned_release() {
+        let releases = releases();
+        assert_eq!(
+            find_release(&releases, "2.9.0").unwrap().unwrap().tag_name,
+            "v2.9.0"
+        );
+        assert_eq!(
+            find_release(&releases, "v3.1.0-rc.1")
+                .unwrap()
+                .unwrap()
+                .tag_name,
+            "v3.1.0-rc.1"
+        );
+        assert!(find_release(&releases, "4.0.0").unwrap().is_none());
+        assert!(find_release(&releases, "latest").is_err());
+    }
+
+    #[test]
+    fn lists_releases_on_channel() {
+        let releases = releases();
+        assert_eq!(
+            format_release_list(&releases, "3.0.1", Channel::Prerelease),
+            "  3.1.0-rc.1  latest, pre-release\n\
+             \x20 3.0.2       pre-release\n\
+             * 3.0.1       installed\n\
+             \x20 2.9.0\n"
+        );
+        assert_eq!(
+            format_release_list(&releases, "3.0.1", Channel::Stable),
+            "* 3.0.1  installed, latest\n  2.9.0\n"
+        );
+        assert_eq!(
+            format_release_list(&[], "3.0.1", Channel::Stable),
+            "No releases found.\n"
+        );
+    }
+
     #[test]
     fn test_get_asset_name() {
         assert_eq!(
-            get_asset_name("macos", "aarch64").unwrap(),
+            get_asset_name("macos", "aarch64", "").unwrap(),
             "peon-aarch64-apple-darwin"
         );
         assert_eq!(
-            get_asset_name("macos", "x86_64").unwrap(),
+            get_asset_name("macos", "x86_64", "").unwrap(),
             "peon-x86_64-apple-darwin"
         );
-        assert!(get_asset_name("linux", "aarch64").is_err());
-        assert!(get_asset_name("macos", "arm").is_err());
+        assert_eq!(
+            get_asset_name("linux", "x86_64", "gnu").unwrap(),
+            "peon-x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            get_asset_name("linux", "aarch64", "musl").unwrap(),
+            "peon-aarch64-unknown-linux-musl"
+        );
+        assert!(get_asset_name("linux", "x86_64", "").is_err());
+        assert!(get_asset_name("windows", "x86_64", "msvc").is_err());
+        assert!(get_asset_name("macos", "arm", "").is_err());
     }
 
     #[test]
@@ -118,6 +589,7 @@ mod tests {
                     browser_download_url: "https://example.com/x86_64".to_string(),
                 },
             ],
+            ..Default::default()
         };
 
         let asset = find_matching_asset(&release, "peon-aarch64-apple-darwin");
@@ -130,12 +602,188 @@ mod tests {
         assert!(find_matching_asset(&release, "peon-linux-amd64").is_none());
     }
 
+    #[test]
+    fn finds_binary_or_tarball_asset() {
+        let asset = |name: &str| GitHubAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{name}"),
+        };
+        let release = GitHubRelease {
+            tag_name: "v3.1.0".to_string(),
+            assets: vec![
+                asset("peon-x86_64-unknown-linux-gnu.tar.gz"),
+                asset("peon-aarch64-apple-darwin"),
+                asset("peon-aarch64-apple-darwin.tar.gz"),
+            ],
+            ..Default::default()
+        };
+
+        let (found, format) =
+            find_release_asset(&release, "peon-x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(found.name, "peon-x86_64-unknown-linux-gnu.tar.gz");
+        assert_eq!(format, AssetFormat::TarGz);
+        let (found, format) = find_release_asset(&release, "peon-aarch64-apple-darwin").unwrap();
+        assert_eq!(found.name, "peon-aarch64-apple-darwin");
+        assert_eq!(format, AssetFormat::Binary);
+        assert!(find_release_asset(&release, "peon-aarch64-unknown-linux-musl").is_none());
+    }
+
+    #[test]
+    fn detects_managed_installs() {
+        let detect = |exe: &str| detect_install_method(Path::new(exe), None);
+        assert_eq!(detect("/usr/local/bin/peon"), InstallMethod::Standalone);
+        assert_eq!(
+            detect("/home/me/.local/bin/peon"),
+            InstallMethod::Standalone
+        );
+        assert_eq!(detect("/home/me/.cargo/bin/peon"), InstallMethod::Cargo);
+        assert_eq!(
+            detect_install_method(
+                Path::new("/opt/rust/bin/peon"),
+                Some(Path::new("/opt/rust"))
+            ),
+            InstallMethod::Cargo
+        );
+        assert_eq!(
+            detect("/opt/homebrew/Cellar/peon/3.0.1/bin/peon"),
+            InstallMethod::Homebrew
+        );
+        assert_eq!(
+            detect("/home/linuxbrew/.linuxbrew/bin/peon"),
+            InstallMethod::Homebrew
+        );
+        assert_eq!(
+            detect("/nix/store/abc-peon-3.0.1/bin/peon"),
+            InstallMethod::Nix
+        );
+        assert_eq!(detect("/usr/bin/peon"), InstallMethod::PackageManager);
+        assert!(InstallMethod::Standalone.upgrade_hint().is_none());
+        assert!(InstallMethod::Cargo
+            .upgrade_hint()
+            .unwrap()
+            .contains("cargo install"));
+    }
+
+    #[test]
+    fn previous_binary_sits_next_to_exe() {
+        let (binary, record) = previous_binary_paths(Path::new("/opt/peon/bin/peon"));
+        assert_eq!(binary, Path::new("/opt/peon/bin/peon.previous"));
+        assert_eq!(record, Path::new("/opt/peon/bin/peon.previous.json"));
+    }
+
+    #[test]
+    fn parses_version_output() {
+        assert_eq!(
+            parse_version_output("peon 3.1.0-rc.1\n").map(|v| v.to_string()),
+            Some("3.1.0-rc.1".to_string())
+        );
+        assert!(parse_version_output("").is_none());
+        assert!(parse_version_output("Segmentation fault").is_none());
+        assert!(parse_version_output("peon garbage").is_none());
+    }
+
     #[test]
     fn test_find_matching_asset_empty() {
         let release = GitHubRelease {
             tag_name: "v1.0.0".to_string(),
             assets: vec![],
+            ..Default::default()
         };
         assert!(find_matching_asset(&release, "peon-aarch64-apple-darwin").is_none());
     }
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/upgrade");
+    const ASSET: &str = "peon-aarch64-apple-darwin";
+
+    fn fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{FIXTURES}/{name}")).unwrap()
+    }
+
+    fn fixture_text(name: &str) -> String {
+        String::from_utf8(fixture(name)).unwrap()
+    }
+
+    fn release_key() -> PublicKey {
+        PublicKey::parse(&fixture_text("minisign.pub")).unwrap()
+    }
+
+    #[test]
+    fn checksum_matches_release_asset() {
+        let manifest = fixture_text(CHECKSUMS_ASSET);
+        assert_eq!(verify_checksum(&manifest, ASSET, &fixture(ASSET)), Ok(()));
+    }
+
+    #[test]
+    fn checksum_rejects_tampered_or_unlisted_asset() {
+        let manifest = fixture_text(CHECKSUMS_ASSET);
+        let mut tampered = fixture(ASSET);
+        tampered.push(0);
+        assert!(matches!(
+            verify_checksum(&manifest, ASSET, &tampered),
+            Err(VerifyError::ChecksumMismatch { .. })
+        ));
+        assert_eq!(
+            verify_checksum(&manifest, "peon-riscv64", b""),
+            Err(VerifyError::Unlisted {
+                asset: "peon-riscv64".into()
+            })
+        );
+        assert!(matches!(
+            verify_checksum("not a checksum\n", ASSET, b""),
+            Err(VerifyError::BadChecksumLine { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn parses_minisign_public_key() {
+        let key = release_key();
+        assert_eq!(key.key_id(), "607A3B9D1E2F4A8C");
+        let bare = fixture_text("minisign.pub")
+            .lines()
+            .nth(1)
+            .unwrap()
+            .to_string();
+        assert_eq!(PublicKey::parse(&bare).unwrap(), key);
+        assert!(PublicKey::parse("untrusted comment: nothing\n").is_err());
+        assert!(PublicKey::parse("RWQ=").is_err());
+    }
+
+    #[test]
+    fn signature_verifies_checksum_manifest() {
+        let signature = fixture_text(SIGNATURE_ASSET);
+        assert_eq!(
+            verify_signature(&release_key(), &signature, &fixture(CHECKSUMS_ASSET)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn signature_rejects_tampered_manifest_or_comment() {
+        let signature = fixture_text(SIGNATURE_ASSET);
+        let manifest = fixture_text(CHECKSUMS_ASSET).replace("54ced", "00000");
+        assert_eq!(
+            verify_signature(&release_key(), &signature, manifest.as_bytes()),
+            Err(VerifyError::InvalidSignature)
+        );
+        let forged_comment = signature.replace("file:SHA256SUMS", "file:other");
+        assert_eq!(
+            verify_signature(&release_key(), &forged_comment, &fixture(CHECKSUMS_ASSET)),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn signature_from_another_key_is_rejected() {
+        let signature = fixture_text(SIGNATURE_ASSET);
+        let mut other = release_key();
+        other.key_id = [0; 8];
+        assert!(matches!(
+            verify_signature(&other, &signature, &fixture(CHECKSUMS_ASSET)),
+            Err(VerifyError::WrongKey { .. })
+        ));
+        assert!(matches!(
+            verify_signature(&release_key(), "untrusted comment: x\n", b""),
+            Err(VerifyError::BadSignature(_))
+        ));
+    }
 }
@@ -0,0 +1,470 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::archive::is_valid_pack_name;
+use crate::clip::{audio_extension, is_supported_audio};
+use crate::types::{is_known_category, Manifest, KNOWN_CATEGORIES};
+
+/// Clips longer than this are reported as a warning.
+pub const DEFAULT_MAX_CLIP_SECONDS: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Stable identifier for the kind of problem (e.g. `missing_file`).
+    pub code: &'static str,
+    pub message: String,
+    /// Where the problem is: `manifest.json:LINE:COL`, a category, or a file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationReport {
+    pub pack: String,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn error_count(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.issues.len() - self.error_count()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error_count() == 0
+    }
+}
+
+/// A file found in a pack's `sounds/` directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundFile {
+    pub name: String,
+    /// Clip length in seconds, when it could be determined.
+    pub duration_secs: Option<f64>,
+}
+
+/// Everything validation needs to know about a pack on disk.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackFiles {
+    /// Name of the pack directory.
+    pub dir_name: String,
+    pub manifest_json: String,
+    pub sound_files: Vec<SoundFile>,
+    /// Other installed packs and the files in their `sounds/` directories, used to check
+    /// `extends` and `"pack:File.wav"` references.
+    pub installed: HashMap<String, HashSet<String>>,
+}
+
+/// Lint a sound pack.
+///
+/// Errors are problems that break playback (unparseable manifest, name not matching the
+/// directory, missing or unsupported files, broken references). Warnings flag things
+/// worth fixing: empty or unknown categories, duplicates, unused files and long clips.
+pub fn validate_pack(pack: &PackFiles, max_clip_secs: f64) -> ValidationReport {
+    let mut issues = Vec::new();
+
+    let manifest: Manifest = match serde_json::from_str(&pack.manifest_json) {
+        Ok(m) => m,
+        Err(e) => {
+            issues.push(Issue {
+                severity: Severity::Error,
+                code: "parse_error",
+                message: format!("manifest.json is not a valid manifest: {e}"),
+                location: Some(format!("manifest.json:{}:{}", e.line(), e.column())),
+            });
+            return ValidationReport {
+                pack: pack.dir_name.clone(),
+                issues,
+            };
+        }
+    };
+
+    if manifest.name != pack.dir_name {
+        issues.push(error(
+            "name_mismatch",
+            format!(
+                "manifest name \"{}\" does not match directory \"{}\"; the pack cannot be played",
+                manifest.name, pack.dir_name
+            ),
+            Some("manifest.json".into()),
+        ));
+    }
+
+    if let Some(base) = &manifest.extends {
+        if !is_valid_pack_name(base) {
+            issues.push(error(
+                "missing_base",
+                format!("extends \"{base}\", which is not a valid pack name"),
+                Some("manifest.json".into()),
+            ));
+        } else if !pack.installed.contains_key(base) {
+            issues.push(error(
+                "missing_base",
+                format!("extends \"{base}\", which is not installed"),
+                Some("manifest.json".into()),
+            ));
+        }
+    }
+
+    if manifest.categories.is_empty() && manifest.extends.is_none() {
+        issues.push(warning(
+            "no_categories",
+            "pack has no categories".into(),
+            None,
+        ));
+    }
+
+    let on_disk: HashMap<&str, &SoundFile> = pack
+        .sound_files
+        .iter()
+        .map(|f| (f.name.as_str(), f))
+        .collect();
+    let mut referenced: HashSet<&str> = HashSet::new();
+
+    let mut categories: Vec<_> = manifest.categories.iter().collect();
+    categories.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, category) in categories {
+        if !is_known_category(name) {
+            issues.push(warning(
+                "unknown_category",
+                format!(
+                    "unknown category \"{name}\" (known: {}, annoyed_<n>)",
+                    KNOWN_CATEGORIES.join(", ")
+                ),
+                Some(name.clone()),
+            ));
+        }
+        if category.sounds.is_empty() && !category.append {
+            issues.push(warning(
+                "empty_category",
+                format!("category \"{name}\" has no sounds"),
+                Some(name.clone()),
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for sound in &category.sounds {
+            let location = Some(format!("{name}/{}", sound.file));
+            if !seen.insert(sound.file.as_str()) {
+                issues.push(warning(
+                    "duplicate_sound",
+                    format!("\"{}\" is listed more than once in \"{name}\"", sound.file),
+                    location.clone(),
+                ));
+            }
+
+            if !sound.has_safe_location() {
+                issues.push(error(
+                    "missing_reference",
+                    format!(
+                        "\"{}\" must name a file directly in a pack's sounds/ directory",
+                        sound.file
+                    ),
+                    location,
+                ));
+                continue;
+            }
+
+            let (owner, file) = sound.location(&pack.dir_name);
+            if !is_supported_audio(file) {
+                issues.push(error(
+                    "unsupported_format",
+                    format!(
+                        "\"{file}\" has unsupported format {} (use wav, mp3 or ogg)",
+                        audio_extension(file).unwrap_or_else(|| "(none)".into())
+                    ),
+                    location.clone(),
+                ));
+            }
+
+            if owner != pack.dir_name {
+                match pack.installed.get(owner) {
+                    None => issues.push(error(
+                        "missing_reference",
+                        format!(
+                            "\"{}\" references pack \"{owner}\", which is not installed",
+                            sound.file
+                        ),
+                        location,
+                    )),
+                    Some(files) if !files.contains(file) => issues.push(error(
+                        "missing_reference",
+                        format!("\"{file}\" not found in pack \"{owner}\""),
+                        location,
+                    )),
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            referenced.insert(file);
+            match on_disk.get(file) {
+                None => issues.push(error(
+                    "missing_file",
+                    format!("sounds/{file} does not exist"),
+                    location,
+                )),
+                Some(SoundFile {
+                    duration_secs: Some(secs),
+                    ..
+                }) if *secs > max_clip_secs => issues.push(warning(
+                    "clip_too_long",
+                    format!("sounds/{file} is {secs:.1}s long (limit {max_clip_secs:.0}s)"),
+                    location,
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    let mut unreferenced: Vec<&str> = on_disk
+        .keys()
+        .filter(|f| !referenced.contains(*f))
+        .copied()
+        .collect();
+    unreferenced.sort_unstable();
+    for file in unreferenced {
+        issues.push(warning(
+            "unreferenced_file",
+            "not used by any category".into(),
+            Some(format!("sounds/{file}")),
+        ));
+    }
+
+    ValidationReport {
+        pack: pack.dir_name.clone(),
+        issues,
+    }
+}
+
+fn error(code: &'static str, message: String, location: Option<String>) -> Issue {
+    Issue {
+        severity: Severity::Error,
+        code,
+        message,
+        location,
+    }
+}
+
+fn warning(code: &'static str, message: String, location: Option<String>) -> Issue {
+    Issue {
+        severity: Severity::Warning,
+        code,
+        message,
+        location,
+    }
+}
+
+/// Format a validation report for the terminal.
+pub fn format_validation(report: &ValidationReport) -> String {
+    let mut out = String::new();
+    if report.issues.is_empty() {
+        out.push_str(&format!("{}: ok\n", report.pack));
+        return out;
+    }
+
+    out.push_str(&format!(
+        "{}: {} error(s), {} warning(s)\n",
+        report.pack,
+        report.error_count(),
+        report.warning_count()
+    ));
+    for issue in &report.issues {
+        let level = match issue.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match &issue.location {
+            Some(location) => out.push_str(&format!(
+                "  {level}[{}] {location}: {}\n",
+                issue.code, issue.message
+            )),
+            None => out.push_str(&format!("  {level}[{}] {}\n", issue.code, issue.message)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<SoundFile> {
+        names
+            .iter()
+            .map(|n| SoundFile {
+                name: n.to_string(),
+                duration_secs: Some(1.0),
+            })
+            .collect()
+    }
+
+    fn pack(manifest_json: &str, sound_files: &[&str]) -> PackFiles {
+        PackFiles {
+            dir_name: "peon".into(),
+            manifest_json: manifest_json.into(),
+            sound_files: files(sound_files),
+            installed: HashMap::new(),
+        }
+    }
+
+    fn codes(report: &ValidationReport) -> Vec<&'static str> {
+        report.issues.iter().map(|i| i.code).collect()
+    }
+
+    const VALID: &str = r#"{"name": "peon", "categories": {
+        "greeting": {"sounds": [{"file": "Ready.wav", "line": "Ready"}]},
+        "complete": {"sounds": [{"file": "Ready.wav"}, {"file": "Done.mp3"}]}
+    }}"#;
+
+    #[test]
+    fn valid_pack_has_no_issues() {
+        let report = validate_pack(&pack(VALID, &["Ready.wav", "Done.mp3"]), 10.0);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn parse_error_has_location() {
+        let report = validate_pack(&pack("{\n  \"name\": \"peon\",\n  oops\n}", &[]), 10.0);
+        assert_eq!(codes(&report), vec!["parse_error"]);
+        assert_eq!(
+            report.issues[0].location.as_deref(),
+            Some("manifest.json:3:3")
+        );
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn name_mismatch_is_error() {
+        let json =
+            r#"{"name": "orc", "categories": {"greeting": {"sounds": [{"file": "a.wav"}]}}}"#;
+        let report = validate_pack(&pack(json, &["a.wav"]), 10.0);
+        assert_eq!(codes(&report), vec!["name_mismatch"]);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn missing_and_unreferenced_files() {
+        let report = validate_pack(&pack(VALID, &["Ready.wav", "Extra.wav"]), 10.0);
+        assert_eq!(codes(&report), vec!["missing_file", "unreferenced_file"]);
+        assert_eq!(report.error_count(), 1);
+        assert_eq!(report.warning_count(), 1);
+    }
+
+    #[test]
+    fn unsupported_format_is_error() {
+        let json =
+            r#"{"name": "peon", "categories": {"greeting": {"sounds": [{"file": "a.flac"}]}}}"#;
+        let report = validate_pack(&pack(json, &["a.flac"]), 10.0);
+        assert_eq!(codes(&report), vec!["unsupported_format"]);
+    }
+
+    #[test]
+    fn duplicates_empty_and_unknown_categories() {
+        let json = r#"{"name": "peon", "categories": {
+            "greeting": {"sounds": [{"file": "a.wav"}, {"file": "a.wav"}]},
+            "complete": {"sounds": []},
+            "victory": {"sounds": [{"file": "a.wav"}]}
+        }}"#;
+        let report = validate_pack(&pack(json, &["a.wav"]), 10.0);
+        assert_eq!(
+            codes(&report),
+            vec!["empty_category", "duplicate_sound", "unknown_category"]
+        );
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn long_clips_warned() {
+        let mut p = pack(VALID, &["Ready.wav", "Done.mp3"]);
+        p.sound_files[1].duration_secs = Some(42.0);
+        let report = validate_pack(&p, 10.0);
+        assert_eq!(codes(&report), vec!["clip_too_long"]);
+        assert!(report.issues[0].message.contains("42.0s"));
+    }
+
+    #[test]
+    fn references_to_other_packs_are_checked() {
+        let json = r#"{"name": "peon", "extends": "base", "categories": {
+            "permission": {"sounds": [{"file": "sc_kerrigan:Yes.mp3"}, {"file": "sc_kerrigan:Nope.mp3"}, {"file": "ghost:Boo.wav"}]}
+        }}"#;
+        let mut p = pack(json, &[]);
+        p.installed
+            .insert("sc_kerrigan".into(), HashSet::from(["Yes.mp3".to_string()]));
+        let report = validate_pack(&p, 10.0);
+        assert_eq!(
+            codes(&report),
+            vec!["missing_base", "missing_reference", "missing_reference"]
+        );
+    }
+
+    #[test]
+    fn invalid_base_names_are_rejected() {
+        let json = r#"{"name": "peon", "extends": "../../x", "categories": {
+            "complete": {"sounds": [{"file": "Done.mp3"}]}
+        }}"#;
+        let report = validate_pack(&pack(json, &["Done.mp3"]), 10.0);
+        assert_eq!(codes(&report), vec!["missing_base"]);
+        assert!(report.issues[0].message.contains("not a valid pack name"));
+    }
+
+    #[test]
+    fn references_outside_sounds_dirs_are_rejected() {
+        let json = r#"{"name": "peon", "categories": {
+            "complete": {"sounds": [{"file": "../../x:evil.wav"}, {"file": "/abs:f.wav"}, {"file": "../Done.mp3"}, {"file": "sfx/Ding.wav"}]}
+        }}"#;
+        let report = validate_pack(&pack(json, &[]), 10.0);
+        assert_eq!(
+            codes(&report),
+            vec![
+                "missing_reference",
+                "missing_reference",
+                "missing_reference",
+                "missing_reference"
+            ]
+        );
+        assert!(report.issues[3]
+            .message
+            .contains("directly in a pack's sounds/ directory"));
+    }
+
+    #[test]
+    fn format_lists_issues() {
+        let report = validate_pack(&pack(VALID, &["Ready.wav"]), 10.0);
+        let output = format_validation(&report);
+        assert!(output.starts_with("peon: 1 error(s), 0 warning(s)\n"));
+        assert!(output
+            .contains("error[missing_file] complete/Done.mp3: sounds/Done.mp3 does not exist"));
+    }
+
+    #[test]
+    fn format_ok_pack() {
+        let report = validate_pack(&pack(VALID, &["Ready.wav", "Done.mp3"]), 10.0);
+        assert_eq!(format_validation(&report), "peon: ok\n");
+    }
+
+    #[test]
+    fn json_report_shape() {
+        let report = validate_pack(&pack(VALID, &["Ready.wav"]), 10.0);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["pack"], "peon");
+        assert_eq!(json["issues"][0]["severity"], "error");
+        assert_eq!(json["issues"][0]["code"], "missing_file");
+    }
+}
@@ -0,0 +1,238 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Files that make up a pack directory, keyed by path relative to it.
+///
+/// Takes regular files at the top level and in `sounds/`, skipping hidden files and any
+/// existing checksum list. Checksums for the collected files are added.
+pub fn collect_pack_files(
+    pack_dir: &Path,
+) -> Result<BTreeMap<String, Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut files = BTreeMap::new();
+    for (prefix, dir) in [
+        ("", pack_dir.to_path_buf()),
+        ("sounds/", pack_dir.join("sounds")),
+    ] {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') || !entry.file_type()?.is_file() {
+                continue;
+            }
+            let path = format!("{prefix}{name}");
+            if path == peon_core::CHECKSUMS_FILE {
+                continue;
+            }
+            files.insert(path, std::fs::read(entry.path())?);
+        }
+    }
+    let checksums = peon_core::format_checksums(&files);
+    files.insert(
+        peon_core::CHECKSUMS_FILE.to_string(),
+        checksums.into_bytes(),
+    );
+    Ok(files)
+}
+
+/// Write files to a gzip-compressed tar archive with fixed metadata, so exporting the
+/// same pack twice gives the same bytes.
+pub fn write_archive(
+    files: &BTreeMap<String, Vec<u8>>,
+    dest: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let out = std::fs::File::create(dest)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+    for (path, bytes) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_entry_type(tar::EntryType::Regular);
+        builder.append_data(&mut header, path, bytes.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Read every file of an archive into memory without touching the filesystem.
+///
+/// Entry paths are checked for traversal; links and other special entries are refused.
+pub fn read_archive(path: &Path) -> Result<BTreeMap<String, Vec<u8>>, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(path)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => {}
+            _ => return Err(format!("unsupported archive entry: {entry_path}").into()),
+        }
+        peon_core::check_entry_path(&entry_path)?;
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        files.insert(entry_path, bytes);
+    }
+    Ok(files)
+}
+
+/// Install verified pack files as `packs_dir/<name>`.
+///
+/// Files are written to a hidden staging directory first and renamed into place, so a
+/// failed install never leaves a half-written pack. An existing pack is only replaced
+/// when `force` is set.
+pub fn install_files(
+    packs_dir: &Path,
+    name: &str,
+    files: &BTreeMap<String, Vec<u8>>,
+    force: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dest = packs_dir.join(name);
+    if dest.exists() && !force {
+        return Err(
+            format!("pack \"{name}\" is already installed (use --force to replace it)").into(),
+        );
+    }
+
+    std::fs::create_dir_all(packs_dir)?;
+    let pid = std::process::id();
+    let staging = packs_dir.join(format!(".{name}.installing-{pid}"));
+    if let Err(e) = write_tree(&staging, files) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    if dest.exists() {
+        let previous = packs_dir.join(format!(".{name}.replaced-{pid}"));
+        std::fs::rename(&dest, &previous)?;
+        if let Err(e) = std::fs::rename(&staging, &dest) {
+            let _ = std::fs::rename(&previous, &dest);
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e.into());
+        }
+        std::fs::remove_dir_all(&previous)?;
+    } else {
+        std::fs::rename(&staging, &dest)?;
+    }
+    Ok(dest)
+}
+
+fn write_tree(
+    root: &Path,
+    files: &BTreeMap<String, Vec<u8>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (path, bytes) in files {
+        peon_core::check_entry_path(path)?;
+        let target = root.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(target, bytes)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str =
+        r#"{"name": "mine", "categories": {"greeting": {"sounds": [{"file": "Hi.wav"}]}}}"#;
+
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("peon-archive-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn export_then_install_round_trips() {
+        let dir = temp_dir("round-trip");
+        let pack_dir = dir.join("source/mine");
+        std::fs::create_dir_all(pack_dir.join("sounds")).unwrap();
+        std::fs::write(pack_dir.join("manifest.json"), MANIFEST).unwrap();
+        std::fs::write(pack_dir.join("sounds/Hi.wav"), "RIFF").unwrap();
+        std::fs::write(pack_dir.join(".DS_Store"), "junk").unwrap();
+
+        let archive = dir.join("mine.peonpack");
+        write_archive(&collect_pack_files(&pack_dir).unwrap(), &archive).unwrap();
+        let files = read_archive(&archive).unwrap();
+        assert_eq!(peon_core::verify_archive(&files).unwrap().name, "mine");
+
+        let packs_dir = dir.join("packs");
+        let installed = install_files(&packs_dir, "mine", &files, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(installed.join("manifest.json")).unwrap(),
+            MANIFEST
+        );
+        assert_eq!(
+            std::fs::read(installed.join("sounds/Hi.wav")).unwrap(),
+            b"RIFF"
+        );
+        assert!(!installed.join(".DS_Store").exists());
+
+        assert!(install_files(&packs_dir, "mine", &files, false).is_err());
+        install_files(&packs_dir, "mine", &files, true).unwrap();
+        assert_eq!(entries(&packs_dir), vec!["mine"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_install_keeps_previous_pack() {
+        let dir = temp_dir("rollback");
+        std::fs::create_dir_all(dir.join("mine")).unwrap();
+        std::fs::write(dir.join("mine/manifest.json"), "old").unwrap();
+        let files = BTreeMap::from([
+            ("manifest.json".to_string(), MANIFEST.as_bytes().to_vec()),
+            ("../escape.wav".to_string(), b"RIFF".to_vec()),
+        ]);
+
+        assert!(install_files(&dir, "mine", &files, true).is_err());
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("mine/manifest.json")).unwrap(),
+            "old"
+        );
+        assert_eq!(entries(&dir), vec!["mine"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn archives_with_links_are_refused() {
+        let dir = temp_dir("symlink");
+        let archive = dir.join("evil.peonpack");
+        let out = std::fs::File::create(&archive).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(0);
+        header.set_mode(0o777);
+        header.set_entry_type(tar::EntryType::Symlink);
+        builder
+            .append_link(&mut header, "sounds/Hi.wav", "/etc/passwd")
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let err = read_archive(&archive).unwrap_err();
+
+        assert_eq!(err.to_string(), "unsupported archive entry: sounds/Hi.wav");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
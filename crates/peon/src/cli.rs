@@ -1,4 +1,4 @@
-use crate::upgrade;
+use crate::{config, pack, upgrade};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -28,17 +28,29 @@ pub enum Commands {
     /// Check if paused or active
     Status,
     /// List available sound packs
-    Packs,
-    /// Switch to a specific pack (or cycle if no name given)
+    Packs {
+        /// List packs in the registry instead, marking installed and outdated ones
+        #[arg(long)]
+        remote: bool,
+    },
+    /// Switch to a specific pack (or cycle if no name given), or manage packs
+    #[command(args_conflicts_with_subcommands = true)]
     Pack {
         /// Pack name to switch to. Omit to cycle to next pack.
         name: Option<String>,
+        #[command(subcommand)]
+        command: Option<pack::PackCommands>,
     },
     /// Show categories and voice lines for a pack
     Sounds {
         /// Pack name to show. Omit for active pack.
         name: Option<String>,
     },
+    /// Show author, license and source attribution for installed packs
+    Credits {
+        /// Pack name to show. Omit for all installed packs.
+        name: Option<String>,
+    },
     /// Play a random sound from a pack
     Play {
         /// Sound category (e.g., greeting, complete, annoyed). Omit for random.
@@ -47,14 +59,34 @@ pub enum Commands {
         #[arg(long)]
         pack: Option<String>,
     },
-    /// Download sound packs from GitHub
+    /// Download sound packs from the registry
     Pull {
-        /// Pack name to download. Required unless --all is used.
+        /// Pack name or source (owner/repo[@ref][:pack], git URL[#ref][:pack], or a path).
+        /// Required unless --all or --update is used.
         name: Option<String>,
         /// Download all available packs
-        #[arg(long)]
+        #[arg(long, conflicts_with = "update")]
         all: bool,
+        /// Refresh installed packs (or just NAME) whose files changed upstream
+        #[arg(long)]
+        update: bool,
+    },
+    /// List installed packs that have updates available
+    Outdated,
+    /// Uninstall sound packs
+    Remove {
+        /// Pack names to remove
+        #[arg(required = true)]
+        packs: Vec<String>,
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Remove packs in use, switching the active pack and updating pack_rotation
+        #[arg(long)]
+        force: bool,
     },
     /// Self-update peon from GitHub releases
     Upgrade(upgrade::App),
+    /// Inspect the effective configuration
+    Config(config::App),
 }
@@ -0,0 +1,31 @@
+use crate::{paths, state_io};
+
+/// Inspect the effective configuration
+#[derive(Debug, clap::Parser)]
+pub struct App {
+    #[command(subcommand)]
+    pub command: ConfigCommands,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ConfigCommands {
+    /// Show the effective config, marking values set by PEON_* environment variables
+    Show,
+}
+
+pub fn run(app: App) -> Result<(), Box<dyn std::error::Error>> {
+    match app.command {
+        ConfigCommands::Show => {
+            let config_path = paths::config_path();
+            let resolved = state_io::load_resolved_config(&config_path);
+            let source = if config_path.exists() {
+                config_path.display().to_string()
+            } else {
+                format!("{} (not found, using defaults)", config_path.display())
+            };
+            println!("config: {source}\n");
+            print!("{}", peon_core::format_config(&resolved));
+        }
+    }
+    Ok(())
+}
@@ -1,110 +1,555 @@
 #![cfg_attr(not(test), deny(clippy::unwrap_used))]
 
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
 use std::io::Read;
-use std::path::Path;
 
-const GITHUB_API_BASE: &str = "https://api.github.com/repos/cloudbridgeuy/peon-ping/contents/packs";
+use crate::http::{self, HttpError};
+use crate::sources::{Revisions, TreeFiles};
 
 #[derive(thiserror::Error, Debug)]
 pub enum GithubError {
     #[error("HTTP request failed: {0}")]
-    Http(#[from] Box<ureq::Error>),
+    Http(#[from] HttpError),
+    #[error("{}", rate_limit_message(*.limit, *.resets_in))]
+    RateLimited {
+        limit: Option<u64>,
+        /// Seconds until the limit resets.
+        resets_in: Option<u64>,
+    },
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
     #[error("JSON parse error: {0}")]
     Json(#[from] serde_json::Error),
 }
 
+fn rate_limit_message(limit: Option<u64>, resets_in: Option<u64>) -> String {
+    let mut message = match limit {
+        Some(limit) => format!("GitHub API rate limit of {limit} requests per hour exceeded"),
+        None => "GitHub API rate limit exceeded".to_string(),
+    };
+    if let Some(seconds) = resets_in {
+        let minutes = seconds.div_ceil(60);
+        message.push_str(&format!(
+            "; resets in {minutes} minute{}",
+            if minutes == 1 { "" } else { "s" }
+        ));
+    }
+    message.push_str(". Set GITHUB_TOKEN to raise the limit.");
+    message
+}
+
+/// API URL of a repository, e.g. `https://api.github.com/repos/owner/repo`.
+pub fn repo_api(api_url: &str, owner: &str, repo: &str) -> String {
+    format!("{}/repos/{owner}/{repo}", api_url.trim_end_matches('/'))
+}
+
 #[derive(serde::Deserialize)]
-struct GithubContent {
-    name: String,
-    #[serde(rename = "type")]
-    content_type: String,
+struct GitTree {
+    sha: String,
     #[serde(default)]
-    download_url: Option<String>,
+    tree: Vec<GitTreeEntry>,
 }
 
-pub struct PullResult {
-    pub name: String,
-    pub files: usize,
+#[derive(serde::Deserialize)]
+struct GitTreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+    sha: String,
 }
 
-/// List all pack names available on GitHub.
-pub fn list_remote_packs() -> Result<Vec<String>, GithubError> {
-    let contents = fetch_contents(GITHUB_API_BASE)?;
-    let packs = contents
-        .into_iter()
-        .filter(|c| c.content_type == "dir")
-        .map(|c| c.name)
-        .collect();
-    Ok(packs)
+/// Git tree SHA of every directory in the repository at `git_ref` (default branch if
+/// `None`). A directory's SHA changes exactly when a file under it does. One API request.
+pub fn tree_revisions(repo_api: &str, git_ref: Option<&str>) -> Result<Revisions, GithubError> {
+    let url = format!(
+        "{repo_api}/git/trees/{}?recursive=1",
+        git_ref.unwrap_or("HEAD")
+    );
+    let tree: GitTree = github_get(&url)?.into_json()?;
+    Ok(Revisions {
+        root: Some(tree.sha),
+        dirs: tree
+            .tree
+            .into_iter()
+            .filter(|entry| entry.entry_type == "tree")
+            .map(|entry| (entry.path, entry.sha))
+            .collect(),
+    })
 }
 
-/// Download a single pack from GitHub to the local packs directory.
-/// Creates `<dest_dir>/<pack_name>/manifest.json` and `<dest_dir>/<pack_name>/sounds/*`.
-pub fn pull_pack(pack_name: &str, dest_dir: &Path) -> Result<PullResult, GithubError> {
-    let pack_url = format!("{GITHUB_API_BASE}/{pack_name}");
-    let contents = fetch_contents(&pack_url)?;
+/// Download the repository at `git_ref` as one tarball and keep the files that can
+/// belong to a pack (see `peon_core::is_pack_tree_file`), keyed by repository path.
+pub fn fetch_tree_files(repo_api: &str, git_ref: Option<&str>) -> Result<TreeFiles, GithubError> {
+    let url = match git_ref {
+        Some(git_ref) => format!("{repo_api}/tarball/{git_ref}"),
+        None => format!("{repo_api}/tarball"),
+    };
+    read_tarball(github_get(&url)?.into_reader())
+}
 
-    let pack_dir = dest_dir.join(pack_name);
-    std::fs::create_dir_all(&pack_dir)?;
+/// GitHub prefixes every tarball entry with a `<owner>-<repo>-<sha>/` directory, which is
+/// stripped. Links and other special entries are skipped.
+fn read_tarball(reader: impl Read) -> Result<TreeFiles, GithubError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        // `path()` applies PAX and GNU long names, which GitHub uses for deep paths
+        let entry_path = entry.path()?.to_string_lossy().into_owned();
+        let Some((_, path)) = entry_path.split_once('/') else {
+            continue;
+        };
+        if !peon_core::is_pack_tree_file(path) || peon_core::check_entry_path(path).is_err() {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        files.insert(path.to_string(), bytes);
+    }
+    Ok(files)
+}
 
-    let mut file_count = 0;
+fn github_get(url: &str) -> Result<ureq::Response, GithubError> {
+    http::get(url, &[("Accept", "application/vnd.github+json")]).map_err(request_error)
+}
 
-    for item in &contents {
-        if item.content_type == "file" {
-            // Top-level files (manifest.json)
-            if let Some(url) = &item.download_url {
-                let bytes = download_raw(url)?;
-                std::fs::write(pack_dir.join(&item.name), &bytes)?;
-                file_count += 1;
+/// Turn an exhausted rate limit into [`GithubError::RateLimited`].
+fn request_error(error: HttpError) -> GithubError {
+    if let HttpError::Request(request) = &error {
+        if let ureq::Error::Status(403 | 429, response) = request.as_ref() {
+            if response.header("X-RateLimit-Remaining") == Some("0") {
+                let header = |name| response.header(name).and_then(|v| v.parse::<u64>().ok());
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                return GithubError::RateLimited {
+                    limit: header("X-RateLimit-Limit"),
+                    resets_in: header("X-RateLimit-Reset").map(|reset| reset.saturating_sub(now)),
+                };
             }
-        } else if item.content_type == "dir" && item.name == "sounds" {
-            // Recurse into sounds/ directory
-            let sounds_url = format!("{pack_url}/sounds");
-            let sound_contents = fetch_contents(&sounds_url)?;
-            let sounds_dir = pack_dir.join("sounds");
-            std::fs::create_dir_all(&sounds_dir)?;
-
-            for sound_item in &sound_contents {
-                if sound_item.content_type == "file" {
-                    if let Some(url) = &sound_item.download_url {
-                        let bytes = download_raw(url)?;
-                        std::fs::write(sounds_dir.join(&sound_item.name), &bytes)?;
-                        file_count += 1;
-                    }
+        }
+    }
+    GithubError::Http(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{self, InstallError};
+    use flate2::write::GzEncoder;
+    use peon_core::SourceSpec;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const MANIFEST: &str =
+        r#"{"name": "mine", "categories": {"greeting": {"sounds": [{"file": "Hi.wav"}]}}}"#;
+
+    struct Reply {
+        status: &'static str,
+        headers: &'static str,
+        body: Vec<u8>,
+    }
+
+    fn ok(body: Vec<u8>) -> Reply {
+        Reply {
+            status: "200 OK",
+            headers: "",
+            body,
+        }
+    }
+
+    /// A local HTTP stand-in for the GitHub API, serving `routes` by request path.
+    /// Returns the base URL and a counter of requests served.
+    fn serve(routes: HashMap<String, Reply>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
                 }
+                let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+                let not_found = Reply {
+                    status: "404 Not Found",
+                    headers: "",
+                    body: b"not found".to_vec(),
+                };
+                let reply = routes.get(path).unwrap_or(&not_found);
+                let head = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    reply.status,
+                    reply.headers,
+                    reply.body.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&reply.body);
             }
+        });
+        (base, requests)
+    }
+
+    fn tarball(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Default::default()));
+        for (path, bytes) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            builder.append_data(&mut header, path, *bytes).unwrap();
         }
+        builder.into_inner().unwrap().finish().unwrap()
     }
 
-    Ok(PullResult {
-        name: pack_name.to_string(),
-        files: file_count,
-    })
-}
+    const REPO: &str = "/repos/acme/packs";
 
-fn github_get(url: &str) -> Result<ureq::Response, GithubError> {
-    let mut request = ureq::get(url)
-        .set("Accept", "application/vnd.github+json")
-        .set("User-Agent", "peon-ping");
+    fn acme() -> SourceSpec {
+        SourceSpec::parse("acme/packs").unwrap()
+    }
 
-    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
-        request = request.set("Authorization", &format!("Bearer {token}"));
+    /// Routes for a repository holding packs "mine" and "other".
+    fn repo_routes(mine_manifest: &str, with_sound: bool) -> HashMap<String, Reply> {
+        repo_routes_with(mine_manifest, with_sound, &[])
     }
 
-    request.call().map_err(|e| GithubError::Http(Box::new(e)))
-}
+    /// [`repo_routes`] with `extra` files added to the tarball.
+    fn repo_routes_with(
+        mine_manifest: &str,
+        with_sound: bool,
+        extra: &[(&str, &[u8])],
+    ) -> HashMap<String, Reply> {
+        let other = MANIFEST.replace("mine", "other");
+        let mut entries: Vec<(&str, &[u8])> = vec![
+            ("peon-ping-abc/README.md", b"readme"),
+            ("peon-ping-abc/packs/index.json", b"{}"),
+            (
+                "peon-ping-abc/packs/mine/manifest.json",
+                mine_manifest.as_bytes(),
+            ),
+            ("peon-ping-abc/packs/mine/.DS_Store", b"junk"),
+            ("peon-ping-abc/packs/other/manifest.json", other.as_bytes()),
+            ("peon-ping-abc/packs/other/sounds/Hi.wav", b"RIFF"),
+        ];
+        if with_sound {
+            entries.push(("peon-ping-abc/packs/mine/sounds/Hi.wav", b"RIFF"));
+        }
+        entries.extend_from_slice(extra);
+        let tree = br#"{"sha": "root", "tree": [
+            {"path": "packs", "type": "tree", "sha": "tree0"},
+            {"path": "packs/mine", "type": "tree", "sha": "tree1"},
+            {"path": "packs/other", "type": "tree", "sha": "tree2"},
+            {"path": "packs/index.json", "type": "blob", "sha": "blob"}]}"#;
+        HashMap::from([
+            (
+                format!("{REPO}/git/trees/HEAD?recursive=1"),
+                ok(tree.to_vec()),
+            ),
+            (format!("{REPO}/tarball"), ok(tarball(&entries))),
+        ])
+    }
 
-fn fetch_contents(url: &str) -> Result<Vec<GithubContent>, GithubError> {
-    let response = github_get(url)?;
-    let contents: Vec<GithubContent> = response.into_json()?;
-    Ok(contents)
-}
+    /// A fresh packs directory holding an "old" install of pack "mine".
+    fn packs_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("peon-github-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("mine")).unwrap();
+        std::fs::write(dir.join("mine/manifest.json"), "old").unwrap();
+        dir
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn assert_previous_kept(dir: &Path) {
+        assert_eq!(
+            std::fs::read_to_string(dir.join("mine/manifest.json")).unwrap(),
+            "old"
+        );
+        assert_eq!(entries(dir), vec!["mine"]);
+    }
+
+    #[test]
+    fn snapshot_installs_every_pack_from_one_download() {
+        let (base, requests) = serve(repo_routes(MANIFEST, true));
+        let dir = packs_dir("ok");
 
-fn download_raw(url: &str) -> Result<Vec<u8>, GithubError> {
-    let response = github_get(url)?;
-    let mut bytes = Vec::new();
-    response.into_reader().read_to_end(&mut bytes)?;
-    Ok(bytes)
+        let snapshot = sources::fetch(&acme(), &base).unwrap();
+        assert_eq!(snapshot.names(), vec!["mine", "other"]);
+        let result = snapshot.install("mine", &dir, None).unwrap();
+        snapshot.install("other", &dir, None).unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(result.files, 2);
+        assert_eq!(result.revision.as_deref(), Some("tree1"));
+        assert_eq!(
+            result.digests["sounds/Hi.wav"],
+            peon_core::sha256_hex(b"RIFF")
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("mine/manifest.json")).unwrap(),
+            MANIFEST
+        );
+        assert_eq!(
+            std::fs::read(dir.join("mine/sounds/Hi.wav")).unwrap(),
+            b"RIFF"
+        );
+        assert!(!dir.join("mine").join(peon_core::CHECKSUMS_FILE).exists());
+        assert!(!dir.join("mine/.DS_Store").exists());
+        assert_eq!(entries(&dir), vec!["mine", "other"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_referenced_sound_keeps_previous_version() {
+        let (base, _) = serve(repo_routes(MANIFEST, false));
+        let dir = packs_dir("missing");
+
+        let err = sources::fetch(&acme(), &base)
+            .unwrap()
+            .install("mine", &dir, None)
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                InstallError::Verify(peon_core::ArchiveError::MissingSound(_))
+            ),
+            "{err}"
+        );
+        assert_previous_kept(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn upstream_checksums_are_verified() {
+        let checksums = format!(
+            "{}  manifest.json\n{}  sounds/Hi.wav\n",
+            peon_core::sha256_hex(MANIFEST.as_bytes()),
+            peon_core::sha256_hex(b"WAVE")
+        );
+        let extra: &[(&str, &[u8])] = &[(
+            "peon-ping-abc/packs/mine/checksums.txt",
+            checksums.as_bytes(),
+        )];
+        let (base, _) = serve(repo_routes_with(MANIFEST, true, extra));
+        let dir = packs_dir("checksums");
+
+        let err = sources::fetch(&acme(), &base)
+            .unwrap()
+            .install("mine", &dir, None)
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                InstallError::Verify(peon_core::ArchiveError::ChecksumMismatch(_))
+            ),
+            "{err}"
+        );
+        assert_previous_kept(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn registry_entry_is_verified() {
+        let (base, _) = serve(repo_routes(MANIFEST, true));
+        let dir = packs_dir("registry");
+        let snapshot = sources::fetch(&acme(), &base).unwrap();
+        let manifest: peon_core::types::Manifest = serde_json::from_str(MANIFEST).unwrap();
+        let mut files = std::collections::BTreeMap::from([
+            ("manifest.json".to_string(), MANIFEST.as_bytes().to_vec()),
+            ("sounds/Hi.wav".to_string(), b"RIFF".to_vec()),
+        ]);
+
+        let listed = peon_core::index_entry(&manifest, &files);
+        snapshot.install("mine", &dir, Some(&listed)).unwrap();
+
+        files.insert("sounds/Hi.wav".to_string(), b"WAVE".to_vec());
+        let changed = peon_core::index_entry(&manifest, &files);
+        let err = snapshot.install("mine", &dir, Some(&changed)).unwrap_err();
+        assert!(matches!(err, InstallError::IndexMismatch(_)), "{err}");
+        assert_eq!(
+            std::fs::read(dir.join("mine/sounds/Hi.wav")).unwrap(),
+            b"RIFF"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn renamed_pack_keeps_previous_version() {
+        let (base, _) = serve(repo_routes(&MANIFEST.replace("mine", "theirs"), true));
+        let dir = packs_dir("renamed");
+
+        let err = sources::fetch(&acme(), &base)
+            .unwrap()
+            .install("mine", &dir, None)
+            .unwrap_err();
+
+        assert!(matches!(err, InstallError::NameMismatch { .. }), "{err}");
+        assert_previous_kept(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_pack_is_not_found() {
+        let (base, _) = serve(repo_routes(MANIFEST, true));
+        let dir = packs_dir("unknown");
+
+        let err = sources::fetch(&acme(), &base)
+            .unwrap()
+            .install("nope", &dir, None)
+            .unwrap_err();
+
+        assert!(matches!(err, InstallError::NotFound { .. }), "{err}");
+        assert_previous_kept(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lists_tree_revisions() {
+        let (base, _) = serve(repo_routes(MANIFEST, true));
+        let revisions = tree_revisions(&format!("{base}{REPO}"), None).unwrap();
+        assert_eq!(revisions.root.as_deref(), Some("root"));
+        assert_eq!(revisions.dirs.len(), 3);
+        assert_eq!(revisions.dirs["packs/other"], "tree2");
+    }
+
+    #[test]
+    fn exhausted_rate_limit_is_reported() {
+        let (base, _) = serve(HashMap::from([(
+            format!("{REPO}/git/trees/HEAD?recursive=1"),
+            Reply {
+                status: "403 Forbidden",
+                headers:
+                    "X-RateLimit-Limit: 60\r\nX-RateLimit-Remaining: 0\r\nX-RateLimit-Reset: 0\r\n",
+                body: br#"{"message": "API rate limit exceeded"}"#.to_vec(),
+            },
+        )]));
+
+        let err = tree_revisions(&format!("{base}{REPO}"), None).unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                GithubError::RateLimited {
+                    limit: Some(60),
+                    resets_in: Some(0)
+                }
+            ),
+            "{err}"
+        );
+        assert!(err.to_string().contains("GITHUB_TOKEN"));
+    }
+
+    #[test]
+    fn server_errors_are_retried() {
+        let (base, requests) = serve(HashMap::from([(
+            format!("{REPO}/tarball"),
+            Reply {
+                status: "503 Service Unavailable",
+                headers: "Retry-After: 0\r\n",
+                body: Vec::new(),
+            },
+        )]));
+        let config = peon_core::types::Config {
+            http_retries: 2,
+            ..Default::default()
+        };
+        let client = crate::http::Client::from_config(&config).unwrap();
+
+        let err = client
+            .get(&format!("{base}{REPO}/tarball"), &[])
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, HttpError::Request(e) if matches!(**e, ureq::Error::Status(503, _))),
+            "{err}"
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn dropped_connections_are_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (served, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else { continue };
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                reader.read_line(&mut request_line).unwrap();
+                // Hang up on the first request without answering
+                if served == 0 {
+                    continue;
+                }
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                );
+            }
+        });
+        let config = peon_core::types::Config {
+            http_retries: 1,
+            ..Default::default()
+        };
+        let client = crate::http::Client::from_config(&config).unwrap();
+
+        let response = client.get(&format!("{base}{REPO}/tarball"), &[]).unwrap();
+
+        assert_eq!(response.into_string().unwrap(), "ok");
+    }
+
+    #[test]
+    fn rate_limit_message_mentions_reset() {
+        assert_eq!(
+            rate_limit_message(Some(60), Some(90)),
+            "GitHub API rate limit of 60 requests per hour exceeded; resets in 2 minutes. \
+             Set GITHUB_TOKEN to raise the limit."
+        );
+        assert_eq!(
+            rate_limit_message(None, None),
+            "GitHub API rate limit exceeded. Set GITHUB_TOKEN to raise the limit."
+        );
+    }
+
+    #[test]
+    fn tarball_keeps_pack_files_only() {
+        let files = read_tarball(
+            tarball(&[
+                ("repo-abc/packs/peon/sounds/Hi.wav", b"RIFF"),
+                ("repo-abc/packs/peon/manifest.json", b"{}"),
+                ("repo-abc/packs/peon/extra/a/b.wav", b"x"),
+                ("repo-abc/packs/.hidden/manifest.json", b"{}"),
+                ("repo-abc/src/bin/main.rs", b"fn main() {}"),
+            ])
+            .as_slice(),
+        )
+        .unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec!["packs/peon/manifest.json", "packs/peon/sounds/Hi.wav"]
+        );
+    }
 }
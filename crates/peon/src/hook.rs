@@ -1,12 +1,18 @@
-use peon_core::types::{Action, HookEvent};
-use peon_core::{check_annoyed, is_agent_session, pick_sound, resolve_pack, route_event};
-use rand::thread_rng;
+use peon_core::types::{Action, AgentBehavior, HookEvent, Manifest, NotifyColor, SuppressedPlay};
+use peon_core::{
+    annoyed_category, apply_agent_behavior, check_sound_gate, classify_agent, decayed_score,
+    error_cooldown_elapsed, pick_sound, prune_sound_times, record_suppressed, register_prompt,
+    resolve_category, resolve_pack, route_event, should_acknowledge,
+};
+use rand::{thread_rng, Rng};
 use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::paths;
 use crate::platform;
 use crate::state_io;
+use crate::upgrade;
 
 #[derive(thiserror::Error, Debug)]
 pub enum HookError {
@@ -23,76 +29,155 @@ pub fn handle_hook() -> Result<(), HookError> {
     let mut input = String::new();
     std::io::stdin().read_to_string(&mut input)?;
 
+    let play = |path: &Path, volume: f64| {
+        let _ = platform::audio::play_sound(path, volume);
+    };
+    let notify = |message: &str, title: &str, color: &NotifyColor| {
+        if !platform::focus::terminal_is_focused() {
+            let _ = platform::notification::send_notification(message, title, color);
+        }
+    };
+    let env = HookEnv {
+        config_path: paths::config_path(),
+        state_path: paths::state_path(),
+        paused_path: paths::paused_path(),
+        update_cache_path: paths::update_cache_path(),
+        packs_dir: paths::packs_dir(None),
+        play: &play,
+        notify: &notify,
+    };
+    run_hook(&input, &env)
+}
+
+/// Where a hook keeps its files and how it reaches the desktop, so a hook can run
+/// against any directory without playing sounds or showing notifications.
+struct HookEnv<'a> {
+    config_path: PathBuf,
+    state_path: PathBuf,
+    paused_path: PathBuf,
+    update_cache_path: PathBuf,
+    packs_dir: PathBuf,
+    /// Play a sound file at a volume.
+    play: &'a (dyn Fn(&Path, f64) + Sync),
+    /// Show a notification (message, title, color) unless the terminal is focused.
+    notify: &'a (dyn Fn(&str, &str, &NotifyColor) + Sync),
+}
+
+/// Handle one hook event given as JSON.
+fn run_hook(input: &str, env: &HookEnv) -> Result<(), HookError> {
     if input.trim().is_empty() {
         return Ok(());
     }
 
-    let event: HookEvent = serde_json::from_str(&input)?;
-    let config = state_io::load_config(&paths::config_path());
-    let mut state = state_io::load_state(&paths::state_path());
-    let paused = state_io::is_paused(&paths::paused_path());
+    let event: HookEvent = serde_json::from_str(input)?;
+    let config = state_io::load_config(&env.config_path);
+    // Hooks from concurrent sessions take turns with the state, so cooldowns hold across
+    // sessions and no hook overwrites another's changes
+    let _lock = state_io::lock_state(&env.state_path);
+    let mut state = state_io::load_state(&env.state_path);
+    let paused = state_io::is_paused(&env.paused_path);
 
-    // Agent detection — suppress sounds for delegate sessions
-    if is_agent_session(
-        &state.agent_sessions,
-        event.session_id(),
-        event.permission_mode(),
-    ) {
-        // Record this session as agent if newly detected
-        if event.permission_mode() == "delegate"
-            && !state.agent_sessions.contains(event.session_id())
-        {
-            state.agent_sessions.insert(event.session_id().to_string());
-            let _ = state_io::save_state(&paths::state_path(), &state);
+    // Agent detection — sessions matching an agent rule are silenced or toned down
+    let agent = classify_agent(&config.agent_rules, &state.agent_sessions, &event, |name| {
+        std::env::var(name).ok()
+    })
+    .map(|(session, record)| {
+        // Remember the classification (and why) for the rest of the session
+        if record {
+            state
+                .agent_sessions
+                .insert(event.session_id().to_string(), session.clone());
+            let _ = state_io::save_state(&env.state_path, &state);
         }
-        return Ok(());
-    }
-
-    // If delegate mode, record and exit
-    if event.permission_mode() == "delegate" {
-        state.agent_sessions.insert(event.session_id().to_string());
-        let _ = state_io::save_state(&paths::state_path(), &state);
+        session
+    });
+    if agent
+        .as_ref()
+        .is_some_and(|agent| agent.behavior == AgentBehavior::Silent)
+    {
         return Ok(());
     }
 
     let mut state_dirty = false;
     let mut rng = thread_rng();
 
-    // Annoyed detection for UserPromptSubmit
-    let annoyed = if matches!(event, HookEvent::UserPromptSubmit { .. })
-        && config.categories.is_enabled("annoyed")
-    {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs_f64();
-
-        let session_id = event.session_id().to_string();
-        let timestamps = state.prompt_timestamps.entry(session_id).or_default();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    // Prompts typed by a person; agents get neither annoyed nor acknowledged
+    let user_prompt = matches!(event, HookEvent::UserPromptSubmit { .. }) && agent.is_none();
 
-        // Filter to only recent timestamps and add current
+    // Annoyance builds up with rapid prompts
+    let annoyed_tier = if user_prompt && config.categories.is_enabled("annoyed") {
+        let (annoyance, tier) =
+            register_prompt(state.annoyance.get(event.session_id()), &config, now);
+        // Forget sessions that have calmed down completely
         let window = config.annoyed_window_seconds;
-        timestamps.retain(|&t| now - t < window);
-        timestamps.push(now);
+        state
+            .annoyance
+            .retain(|_, a| decayed_score(a, window, now) > 0.0);
+        state
+            .annoyance
+            .insert(event.session_id().to_string(), annoyance);
         state_dirty = true;
-
-        check_annoyed(timestamps, config.annoyed_threshold, window, now)
+        tier
     } else {
-        false
+        None
     };
 
     // Route event to actions
     let mut actions = route_event(&event, &config, &state);
+    if let Some(agent) = &agent {
+        actions = apply_agent_behavior(actions, &agent.behavior);
+    }
 
-    // If annoyed, add a PlaySound for annoyed category
-    if annoyed && config.categories.is_enabled("annoyed") {
+    // A failing tool call alerts at most once per cooldown, however often it's retried
+    let tool_failed = matches!(event, HookEvent::PostToolUse { .. })
+        && actions.iter().any(|action| action != &Action::Skip);
+    if tool_failed {
+        let cooldown = config.error_cooldown_seconds;
+        let last = state.last_error.get(event.session_id()).copied();
+        if error_cooldown_elapsed(last, cooldown, now) {
+            state.last_error.retain(|_, &mut at| now - at < cooldown);
+            state.last_error.insert(event.session_id().to_string(), now);
+            state_dirty = true;
+        } else {
+            actions.clear();
+        }
+    }
+
+    // If annoyed, play the tier's category (falling back to plain `annoyed`); otherwise
+    // acknowledge the prompt now and then
+    if let Some(tier) = annoyed_tier {
         actions.push(Action::PlaySound {
-            category: "annoyed".into(),
+            category: annoyed_category(tier),
+        });
+    } else if user_prompt
+        && config.categories.is_enabled("acknowledge")
+        && should_acknowledge(
+            &config,
+            state.last_acknowledged.get(event.session_id()).copied(),
+            now,
+            rng.gen(),
+        )
+    {
+        let cooldown = config.acknowledge_cooldown_seconds;
+        state
+            .last_acknowledged
+            .retain(|_, &mut at| now - at < cooldown);
+        state
+            .last_acknowledged
+            .insert(event.session_id().to_string(), now);
+        state_dirty = true;
+        actions.push(Action::PlaySound {
+            category: "acknowledge".into(),
         });
     }
 
     // Resolve active pack
-    let packs = state_io::list_packs(&paths::packs_dir(None));
+    let packs_dir = &env.packs_dir;
+    let packs = state_io::list_packs(packs_dir);
     let available_pack_names: Vec<String> = packs.iter().map(|(name, _)| name.clone()).collect();
     let active_pack = resolve_pack(
         &config,
@@ -111,11 +196,18 @@ pub fn handle_hook() -> Result<(), HookError> {
         }
     }
 
-    // Load manifest for the active pack
-    let manifest = packs
-        .iter()
-        .find(|(name, _)| name == &active_pack)
-        .map(|(_, m)| m);
+    // Packs to search for sounds: the active pack, then the configured fallback pack,
+    // each with its `extends` chain resolved
+    let mut loaded: Vec<(String, Manifest)> = Vec::new();
+    for pack_name in std::iter::once(&active_pack).chain(config.fallback_pack.as_ref()) {
+        if loaded.iter().any(|(name, _)| name == pack_name) {
+            continue;
+        }
+        if let Ok(manifest) = state_io::load_pack(packs_dir, pack_name) {
+            loaded.push((pack_name.clone(), manifest));
+        }
+    }
+    let sources: Vec<(&str, &Manifest)> = loaded.iter().map(|(n, m)| (n.as_str(), m)).collect();
 
     // Execute actions
     for action in &actions {
@@ -128,22 +220,32 @@ pub fn handle_hook() -> Result<(), HookError> {
                 if paused {
                     continue;
                 }
-                if let Some(manifest) = manifest {
-                    if let Some(cat) = manifest.categories.get(category.as_str()) {
-                        let last = state.last_played.get(category.as_str()).map(|s| s.as_str());
-                        if let Some(sound) = pick_sound(&cat.sounds, last, &mut rng) {
-                            state
-                                .last_played
-                                .insert(category.clone(), sound.file.clone());
-                            state_dirty = true;
-
-                            let sound_path = paths::packs_dir(None)
-                                .join(&active_pack)
-                                .join("sounds")
-                                .join(&sound.file);
-                            if sound_path.exists() {
-                                let _ = platform::audio::play_sound(&sound_path, config.volume);
-                            }
+                // Follow category fallbacks so packs missing a category still play something
+                if let Some(resolved) = resolve_category(&sources, category, &config) {
+                    let last = state.last_played.get(resolved.category).map(|s| s.as_str());
+                    if let Some(sound) = pick_sound(&resolved.sounds.sounds, last, &mut rng) {
+                        prune_sound_times(&config, &mut state.last_sound_at, now);
+                        state_dirty = true;
+                        if let Err(reason) =
+                            check_sound_gate(&config, &state.last_sound_at, category, now)
+                        {
+                            let play = SuppressedPlay {
+                                category: category.clone(),
+                                session_id: event.session_id().to_string(),
+                                at: now,
+                                reason,
+                            };
+                            record_suppressed(&mut state.suppressed, play);
+                            continue;
+                        }
+                        state.last_sound_at.insert(category.clone(), now);
+                        state
+                            .last_played
+                            .insert(resolved.category.to_string(), sound.file.clone());
+
+                        let sound_path = state_io::sound_path(packs_dir, resolved.pack, sound);
+                        if let Some(sound_path) = sound_path.filter(|path| path.exists()) {
+                            (env.play)(&sound_path, sound.effective_volume(config.volume));
                         }
                     }
                 }
@@ -156,9 +258,7 @@ pub fn handle_hook() -> Result<(), HookError> {
                 if paused {
                     continue;
                 }
-                if !platform::focus::terminal_is_focused() {
-                    let _ = platform::notification::send_notification(message, title, color);
-                }
+                (env.notify)(message, title, color);
             }
             Action::Skip => {}
         }
@@ -169,10 +269,89 @@ pub fn handle_hook() -> Result<(), HookError> {
         eprintln!("peon-ping: sounds paused — run 'peon resume' or '/peon-ping-toggle' to unpause");
     }
 
+    // Mention a cached newer release and refresh the cache in the background if it's stale
+    if matches!(event, HookEvent::SessionStart { .. }) && config.update_check {
+        if let Some(notice) = state_io::load_update_cache(&env.update_cache_path)
+            .and_then(|cache| peon_core::update_notice(&cache, env!("CARGO_PKG_VERSION")))
+        {
+            eprintln!("{notice}");
+        }
+        upgrade::spawn_background_check(&config);
+    }
+
     // Save state if modified
     if state_dirty {
-        let _ = state_io::save_state(&paths::state_path(), &state);
+        let _ = state_io::save_state(&env.state_path, &state);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peon_core::types::{Annoyance, State};
+    use std::sync::Mutex;
+
+    const STOP: &str =
+        r#"{"hook_event_name":"Stop","cwd":"/tmp/app","permission_mode":"default","session_id":"#;
+
+    #[test]
+    fn concurrent_hooks_share_cooldowns_and_keep_each_others_state() {
+        let dir = std::env::temp_dir().join(format!("peon-hook-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("packs/peon/sounds")).unwrap();
+        std::fs::write(
+            dir.join("packs/peon/manifest.json"),
+            r#"{"name":"peon","categories":{"complete":{"sounds":[{"file":"Done.wav"}]}}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("packs/peon/sounds/Done.wav"), "RIFF").unwrap();
+        let played = Mutex::new(Vec::new());
+        let play = |path: &Path, _: f64| played.lock().unwrap().push(path.to_path_buf());
+        let notify = |_: &str, _: &str, _: &NotifyColor| {};
+        let env = HookEnv {
+            config_path: dir.join("config.json"),
+            state_path: dir.join(".state.json"),
+            paused_path: dir.join(".paused"),
+            update_cache_path: dir.join("update.json"),
+            packs_dir: dir.join("packs"),
+            play: &play,
+            notify: &notify,
+        };
+
+        // Both hooks start while another session holds the state, then run one after the other
+        std::thread::scope(|scope| {
+            let lock = state_io::lock_state(&env.state_path).unwrap();
+            for session in ["s1", "s2"] {
+                let input = format!(r#"{STOP}"{session}"}}"#);
+                let env = &env;
+                scope.spawn(move || run_hook(&input, env).unwrap());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            let mut state = State::default();
+            state.annoyance.insert("other".into(), other());
+            state_io::save_state(&env.state_path, &state).unwrap();
+            drop(lock);
+        });
+
+        let state = state_io::load_state(&env.state_path);
+        assert_eq!(
+            *played.lock().unwrap(),
+            vec![dir.join("packs/peon/sounds/Done.wav")]
+        );
+        assert!(state.last_sound_at.contains_key("complete"));
+        assert_eq!(state.suppressed.len(), 1);
+        assert_eq!(state.suppressed[0].category, "complete");
+        assert_eq!(state.annoyance.get("other"), Some(&other()));
+        assert!(state_io::lock_state(&env.state_path).is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn other() -> Annoyance {
+        Annoyance {
+            score: 2.0,
+            updated_at: 1e12,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+#![cfg_attr(not(test), deny(clippy::unwrap_used))]
+
+use peon_core::types::Config;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::{paths, state_io};
+
+const USER_AGENT: &str = concat!("peon-ping/", env!("CARGO_PKG_VERSION"));
+
+#[derive(thiserror::Error, Debug)]
+pub enum HttpError {
+    #[error("{0}")]
+    Settings(String),
+    #[error(transparent)]
+    Request(Box<ureq::Error>),
+}
+
+/// GET `url` with the shared client: proxies from `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY`
+/// (minus `NO_PROXY`), the configured timeouts, retries and CA bundle, and
+/// `GITHUB_TOKEN` for GitHub hosts. Non-2xx responses are errors, as with `ureq`.
+pub fn get(url: &str, headers: &[(&str, &str)]) -> Result<ureq::Response, HttpError> {
+    static CLIENT: OnceLock<Result<Client, String>> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| Client::from_config(&state_io::load_config(&paths::config_path())))
+        .as_ref()
+        .map_err(|e| HttpError::Settings(e.clone()))?
+        .get(url, headers)
+}
+
+pub struct Client {
+    direct: ureq::Agent,
+    https_proxy: Option<ureq::Agent>,
+    http_proxy: Option<ureq::Agent>,
+    no_proxy: String,
+    retries: u32,
+    github_api_url: String,
+    github_token: Option<String>,
+}
+
+impl Client {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let tls = config.ca_bundle.as_deref().map(tls_config).transpose()?;
+        let timeout = (config.http_timeout_seconds > 0.0)
+            .then(|| Duration::try_from_secs_f64(config.http_timeout_seconds))
+            .transpose()
+            .map_err(|_| {
+                format!(
+                    "invalid http_timeout_seconds {}",
+                    config.http_timeout_seconds
+                )
+            })?;
+        let agent = |proxy: Option<ureq::Proxy>| {
+            let mut builder = ureq::AgentBuilder::new().user_agent(USER_AGENT);
+            if let Some(timeout) = timeout {
+                builder = builder.timeout_connect(timeout).timeout_read(timeout);
+            }
+            if let Some(tls) = &tls {
+                builder = builder.tls_config(Arc::clone(tls));
+            }
+            if let Some(proxy) = proxy {
+                builder = builder.proxy(proxy);
+            }
+            builder.build()
+        };
+        let env = |name: &str| std::env::var(name).ok();
+        let proxied = |scheme| {
+            peon_core::proxy_for(scheme, env)
+                .map(|url| {
+                    ureq::Proxy::new(&url)
+                        .map(|proxy| agent(Some(proxy)))
+                        .map_err(|e| format!("invalid {scheme} proxy {url}: {e}"))
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            direct: agent(None),
+            https_proxy: proxied("https")?,
+            http_proxy: proxied("http")?,
+            no_proxy: env("NO_PROXY")
+                .or_else(|| env("no_proxy"))
+                .unwrap_or_default(),
+            retries: config.http_retries,
+            github_api_url: config.github_api_url.clone(),
+            github_token: env("GITHUB_TOKEN").filter(|token| !token.is_empty()),
+        })
+    }
+
+    pub fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<ureq::Response, HttpError> {
+        let mut attempt = 0;
+        loop {
+            let mut request = self.agent_for(url).get(url);
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            if let Some(token) = &self.github_token {
+                if peon_core::is_github_url(url, &self.github_api_url) {
+                    request = request.set("Authorization", &format!("Bearer {token}"));
+                }
+            }
+            match request.call() {
+                Err(ureq::Error::Status(status, response)) if attempt < self.retries => {
+                    let delay = peon_core::retry_delay(
+                        status,
+                        response.header("Retry-After"),
+                        response.header("X-RateLimit-Remaining") == Some("0"),
+                        attempt,
+                    );
+                    let Some(delay) = delay else {
+                        return Err(HttpError::Request(Box::new(ureq::Error::Status(
+                            status, response,
+                        ))));
+                    };
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(ureq::Error::Transport(transport))
+                    if attempt < self.retries && is_transient(&transport) =>
+                {
+                    std::thread::sleep(peon_core::backoff(attempt));
+                    attempt += 1;
+                }
+                result => return result.map_err(|e| HttpError::Request(Box::new(e))),
+            }
+        }
+    }
+
+    fn agent_for(&self, url: &str) -> &ureq::Agent {
+        let bypass = peon_core::url_host(url)
+            .is_some_and(|host| peon_core::bypasses_proxy(&self.no_proxy, &host));
+        let proxy = match url.split_once("://").map(|(scheme, _)| scheme) {
+            _ if bypass => None,
+            Some("https") => self.https_proxy.as_ref(),
+            Some("http") => self.http_proxy.as_ref(),
+            _ => None,
+        };
+        proxy.unwrap_or(&self.direct)
+    }
+}
+
+/// Failures that may go away on their own: DNS, refused or dropped connections and
+/// timeouts. Bad URLs and proxy settings won't fix themselves.
+fn is_transient(transport: &ureq::Transport) -> bool {
+    use ureq::ErrorKind;
+    matches!(
+        transport.kind(),
+        ErrorKind::Dns
+            | ErrorKind::ConnectionFailed
+            | ErrorKind::Io
+            | ErrorKind::BadStatus
+            | ErrorKind::ProxyConnect
+    )
+}
+
+/// TLS settings trusting the bundled web roots plus every certificate in `path`.
+fn tls_config(path: &str) -> Result<Arc<rustls::ClientConfig>, String> {
+    use rustls::pki_types::{pem::PemObject, CertificateDer};
+
+    let error = |e: &dyn std::fmt::Display| format!("failed to load ca_bundle {path}: {e}");
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let certs = CertificateDer::pem_file_iter(path)
+        .map_err(|e| error(&e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error(&e))?;
+    if certs.is_empty() {
+        return Err(error(&"no certificates found"));
+    }
+    for cert in certs {
+        roots.add(cert).map_err(|e| error(&e))?;
+    }
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| error(&e))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(Arc::new(config))
+}
@@ -1,25 +1,48 @@
+mod archive;
 mod cli;
+mod config;
 mod github;
 mod hook;
+mod http;
+mod pack;
 mod paths;
 mod platform;
+mod registry;
+mod sources;
 mod state_io;
 mod upgrade;
 
 use clap::Parser;
 use rand::Rng;
 use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use cli::{Cli, Commands};
 
+/// Error that ends the process with a specific exit status and no message, for commands
+/// whose status carries meaning (e.g. `peon upgrade --check`).
+#[derive(Debug)]
+pub struct SilentExit(pub u8);
+
+impl std::fmt::Display for SilentExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit status {}", self.0)
+    }
+}
+
+impl std::error::Error for SilentExit {}
+
 fn main() -> ExitCode {
     let cli = Cli::parse();
     match run(cli) {
         Ok(()) => ExitCode::SUCCESS,
-        Err(e) => {
-            eprintln!("Error: {e}");
-            ExitCode::FAILURE
-        }
+        Err(e) => match e.downcast_ref::<SilentExit>() {
+            Some(SilentExit(code)) => ExitCode::from(*code),
+            None => {
+                eprintln!("Error: {e}");
+                ExitCode::FAILURE
+            }
+        },
     }
 }
 
@@ -75,8 +98,31 @@ fn run_command(
                 println!("peon-ping: active");
             }
             println!("pack: {} ", config.active_pack);
+            if let Some(latest) = state_io::load_update_cache(&paths::update_cache_path())
+                .as_ref()
+                .and_then(|cache| peon_core::available_update(cache, env!("CARGO_PKG_VERSION")))
+            {
+                println!("update: {latest} available (run 'peon upgrade')");
+            }
+            let state = state_io::load_state(&paths::state_path());
+            if !state.suppressed.is_empty() {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                println!("recently suppressed sounds:");
+                print!(
+                    "{}",
+                    peon_core::format_suppressed(&state.suppressed, now, 5)
+                );
+            }
+        }
+        Commands::Packs { remote: true } => {
+            let config = state_io::load_config(&paths::config_path());
+            let packs_dir = paths::packs_dir(packs_dir_override.as_deref());
+            registry::list_remote(&config.registry_url, &packs_dir)?;
         }
-        Commands::Packs => {
+        Commands::Packs { remote: false } => {
             let config = state_io::load_config(&paths::config_path());
             let packs = state_io::list_packs(&paths::packs_dir(packs_dir_override.as_deref()));
             if packs.is_empty() {
@@ -99,11 +145,28 @@ fn run_command(
                     println!("  {name:24} {display}{marker}");
                 }
             }
+            for name in
+                state_io::list_invalid_packs(&paths::packs_dir(packs_dir_override.as_deref()))
+            {
+                println!("  {name:24} (invalid manifest — run `peon pack validate {name}`)");
+            }
         }
         Commands::Upgrade(app) => {
             upgrade::run(app)?;
         }
-        Commands::Pack { name } => {
+        Commands::Config(app) => {
+            config::run(app)?;
+        }
+        Commands::Pack {
+            command: Some(command),
+            ..
+        } => {
+            pack::run(command, packs_dir_override.as_deref())?;
+        }
+        Commands::Pack {
+            name,
+            command: None,
+        } => {
             let config_path = paths::config_path();
             let mut config_map = state_io::load_config_map(&config_path);
             let packs = state_io::list_packs(&paths::packs_dir(packs_dir_override.as_deref()));
@@ -157,9 +220,11 @@ fn run_command(
             let config = state_io::load_config(&paths::config_path());
 
             let pack_name = name.unwrap_or(config.active_pack);
-            let pack_path = packs_dir.join(&pack_name);
 
-            let manifest = state_io::load_manifest(&pack_path).map_err(|_| {
+            let manifest = state_io::load_pack(&packs_dir, &pack_name).map_err(|e| {
+                if let state_io::StateIoError::Inherit(e) = e {
+                    return e.to_string();
+                }
                 let available = state_io::list_packs(&packs_dir);
                 let names: Vec<&str> = available.iter().map(|(n, _)| n.as_str()).collect();
                 format!(
@@ -175,14 +240,46 @@ fn run_command(
 
             print!("{}", peon_core::format_pack_sounds(&manifest));
         }
+        Commands::Credits { name } => {
+            let packs_dir = paths::packs_dir(packs_dir_override.as_deref());
+            let packs = state_io::list_packs(&packs_dir);
+            if packs.is_empty() && name.is_none() {
+                println!("No packs found in {}", packs_dir.display());
+                return Ok(());
+            }
+            let selected: Vec<_> = match name {
+                Some(ref pack_name) => packs.iter().filter(|(n, _)| n == pack_name).collect(),
+                None => packs.iter().collect(),
+            };
+            if selected.is_empty() {
+                let names: Vec<&str> = packs.iter().map(|(n, _)| n.as_str()).collect();
+                return Err(format!(
+                    "pack \"{}\" not found. Available: {}",
+                    name.unwrap_or_default(),
+                    if names.is_empty() {
+                        "(none)".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                )
+                .into());
+            }
+            let blocks: Vec<String> = selected
+                .iter()
+                .map(|(_, m)| peon_core::format_pack_credits(m))
+                .collect();
+            print!("{}", blocks.join("\n"));
+        }
         Commands::Play { category, pack } => {
             let packs_dir = paths::packs_dir(packs_dir_override.as_deref());
             let config = state_io::load_config(&paths::config_path());
 
-            let pack_name = pack.unwrap_or(config.active_pack);
-            let pack_path = packs_dir.join(&pack_name);
+            let pack_name = pack.unwrap_or_else(|| config.active_pack.clone());
 
-            let manifest = state_io::load_manifest(&pack_path).map_err(|_| {
+            let manifest = state_io::load_pack(&packs_dir, &pack_name).map_err(|e| {
+                if let state_io::StateIoError::Inherit(e) = e {
+                    return e.to_string();
+                }
                 let available = state_io::list_packs(&packs_dir);
                 let names: Vec<&str> = available.iter().map(|(n, _)| n.as_str()).collect();
                 format!(
@@ -204,16 +301,25 @@ fn run_command(
 
             let cat = match category {
                 Some(ref cat_name) => {
-                    manifest.categories.get(cat_name.as_str()).ok_or_else(|| {
-                        let available: Vec<&str> =
-                            manifest.categories.keys().map(|k| k.as_str()).collect();
-                        format!(
-                            "category \"{}\" not found in pack \"{}\". Available: {}",
-                            cat_name,
-                            pack_name,
-                            available.join(", ")
-                        )
-                    })?
+                    let sources = [(pack_name.as_str(), &manifest)];
+                    let resolved = peon_core::resolve_category(&sources, cat_name, &config)
+                        .ok_or_else(|| {
+                            let available: Vec<&str> =
+                                manifest.categories.keys().map(|k| k.as_str()).collect();
+                            format!(
+                                "category \"{}\" not found in pack \"{}\". Available: {}",
+                                cat_name,
+                                pack_name,
+                                available.join(", ")
+                            )
+                        })?;
+                    if resolved.category != cat_name {
+                        println!(
+                            "No \"{cat_name}\" sounds in {pack_name}, using \"{}\"",
+                            resolved.category
+                        );
+                    }
+                    resolved.sounds
                 }
                 None => {
                     let keys: Vec<&String> = manifest.categories.keys().collect();
@@ -225,9 +331,13 @@ fn run_command(
             match peon_core::pick_sound(&cat.sounds, None, &mut rng) {
                 Some(sound) => {
                     println!("Playing: \"{}\" ({})", sound.line, sound.file);
-                    let sound_path = pack_path.join("sounds").join(&sound.file);
+                    let sound_path = state_io::sound_path(&packs_dir, &pack_name, sound)
+                        .ok_or_else(|| format!("invalid sound reference: {}", sound.file))?;
                     if sound_path.exists() {
-                        platform::audio::play_sound(&sound_path, config.volume)?;
+                        platform::audio::play_sound(
+                            &sound_path,
+                            sound.effective_volume(config.volume),
+                        )?;
                     } else {
                         return Err(
                             format!("sound file not found: {}", sound_path.display()).into()
@@ -239,36 +349,28 @@ fn run_command(
                 }
             }
         }
-        Commands::Pull { name, all } => {
+        Commands::Pull { name, all, update } => {
+            let config = state_io::load_config(&paths::config_path());
             let packs_dir = paths::packs_dir(packs_dir_override.as_deref());
-            std::fs::create_dir_all(&packs_dir)?;
-
-            if all {
-                println!("Pulling all packs from GitHub...");
-                let remote_packs = github::list_remote_packs()?;
-                if remote_packs.is_empty() {
-                    return Err("no packs found on GitHub".into());
-                }
-                let mut installed = 0;
-                for pack_name in &remote_packs {
-                    match github::pull_pack(pack_name, &packs_dir) {
-                        Ok(result) => {
-                            println!("  {} ({} files)", result.name, result.files);
-                            installed += 1;
-                        }
-                        Err(e) => {
-                            eprintln!("  {} — failed: {}", pack_name, e);
-                        }
-                    }
-                }
-                println!("Installed {} packs.", installed);
+            if update {
+                registry::update(name, &config, &packs_dir)?;
             } else {
-                let pack_name = name.ok_or("pack name required (or use --all)")?;
-                println!("Pulling pack '{pack_name}' from GitHub...");
-                let result = github::pull_pack(&pack_name, &packs_dir)?;
-                println!("Installed: {} ({} files)", result.name, result.files);
+                registry::pull(name, all, &config, &packs_dir)?;
             }
         }
+        Commands::Remove {
+            packs,
+            dry_run,
+            force,
+        } => {
+            let packs_dir = paths::packs_dir(packs_dir_override.as_deref());
+            pack::remove(&packs_dir, &packs, dry_run, force)?;
+        }
+        Commands::Outdated => {
+            let config = state_io::load_config(&paths::config_path());
+            let packs_dir = paths::packs_dir(packs_dir_override.as_deref());
+            registry::outdated(&config, &packs_dir)?;
+        }
     }
     Ok(())
 }
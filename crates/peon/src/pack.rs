@@ -0,0 +1,550 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{archive, paths, state_io};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum PackCommands {
+    /// Scaffold a new, empty sound pack
+    New {
+        /// Pack name (directory name under the packs directory)
+        name: String,
+        /// Human-readable pack name
+        #[arg(long)]
+        display_name: Option<String>,
+    },
+    /// Build a pack from a folder of audio files
+    Import {
+        /// Folder containing the audio files (and optional `.txt` transcripts)
+        dir: PathBuf,
+        /// Pack name (defaults to the folder name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Human-readable pack name
+        #[arg(long)]
+        display_name: Option<String>,
+        /// JSON file mapping file names or glob patterns to categories
+        #[arg(long, conflicts_with = "interactive")]
+        mapping: Option<PathBuf>,
+        /// Confirm or choose each file's categories at a prompt
+        #[arg(long, short)]
+        interactive: bool,
+        /// Replace an existing pack with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Write an installed pack to a portable .peonpack archive
+    Export {
+        /// Installed pack name or path to a pack directory
+        pack: String,
+        /// Archive path (defaults to ./<pack>.peonpack)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Install a pack from a .peonpack archive or a pack directory
+    Install {
+        /// Path to a .peonpack archive or a pack directory
+        source: PathBuf,
+        /// Replace the installed pack with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check a sound pack for problems (all installed packs if omitted)
+    Validate {
+        /// Installed pack name or path to a pack directory
+        target: Option<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Warn about clips longer than this many seconds
+        #[arg(long, default_value_t = peon_core::DEFAULT_MAX_CLIP_SECONDS)]
+        max_duration: f64,
+    },
+}
+
+pub fn run(
+    command: PackCommands,
+    packs_dir_override: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let packs_dir = paths::packs_dir(packs_dir_override);
+    match command {
+        PackCommands::New { name, display_name } => new(&packs_dir, &name, display_name),
+        PackCommands::Import {
+            dir,
+            name,
+            display_name,
+            mapping,
+            interactive,
+            force,
+        } => import(
+            &packs_dir,
+            &dir,
+            ImportOptions {
+                name,
+                display_name,
+                mapping,
+                interactive,
+                force,
+            },
+        ),
+        PackCommands::Export { pack, output } => export(&packs_dir, &pack, output),
+        PackCommands::Install { source, force } => install(&packs_dir, &source, force),
+        PackCommands::Validate {
+            target,
+            json,
+            max_duration,
+        } => validate(&packs_dir, target.as_deref(), json, max_duration),
+    }
+}
+
+fn new(
+    packs_dir: &Path,
+    name: &str,
+    display_name: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_pack_name(name)?;
+    let pack_dir = packs_dir.join(name);
+    if pack_dir.exists() {
+        return Err(format!("{} already exists", pack_dir.display()).into());
+    }
+
+    let display_name = display_name.unwrap_or_else(|| name.to_string());
+    let manifest = peon_core::scaffold_manifest(name, &display_name);
+    std::fs::create_dir_all(pack_dir.join("sounds"))?;
+    state_io::save_manifest(&pack_dir, &manifest)?;
+
+    println!("Created {}", pack_dir.display());
+    println!("Add audio files to sounds/ and list them in manifest.json, then run:");
+    println!("  peon pack validate {name}");
+    Ok(())
+}
+
+struct ImportOptions {
+    name: Option<String>,
+    display_name: Option<String>,
+    mapping: Option<PathBuf>,
+    interactive: bool,
+    force: bool,
+}
+
+fn import(
+    packs_dir: &Path,
+    source_dir: &Path,
+    options: ImportOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let folder_name = source_dir
+        .canonicalize()?
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = options
+        .name
+        .unwrap_or_else(|| pack_name_from_folder(&folder_name));
+    check_pack_name(&name)?;
+    let pack_dir = packs_dir.join(&name);
+    if pack_dir.exists() && !options.force {
+        return Err(format!(
+            "{} already exists (use --force to replace it)",
+            pack_dir.display()
+        )
+        .into());
+    }
+
+    let mapping = match &options.mapping {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            Some(
+                peon_core::CategoryMapping::parse(&json)
+                    .map_err(|e| format!("invalid mapping file {}: {e}", path.display()))?,
+            )
+        }
+        None => None,
+    };
+
+    let mut files = Vec::new();
+    let mut transcripts = HashMap::new();
+    for entry in std::fs::read_dir(source_dir)?.flatten() {
+        if !entry.path().is_file() {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        match file_name.strip_suffix(".txt") {
+            Some(stem) => {
+                transcripts.insert(stem.to_string(), std::fs::read_to_string(entry.path())?);
+            }
+            None => files.push(file_name),
+        }
+    }
+
+    let mut plan = peon_core::plan_import(&files, &transcripts, mapping.as_ref());
+    if plan.is_empty() {
+        return Err(format!("no supported audio files in {}", source_dir.display()).into());
+    }
+    if options.interactive {
+        assign_interactively(&mut plan)?;
+    }
+
+    let (entries, skipped): (Vec<_>, Vec<_>) =
+        plan.into_iter().partition(|e| !e.categories.is_empty());
+    for entry in &skipped {
+        if options.interactive {
+            eprintln!("skipped {}", entry.file);
+        } else {
+            eprintln!(
+                "skipped {} (no category; use --mapping or --interactive)",
+                entry.file
+            );
+        }
+    }
+    if entries.is_empty() {
+        return Err("no files could be assigned a category".into());
+    }
+
+    // Read everything before touching the pack directory, which may hold the source, then
+    // stage the new pack and swap it in
+    let display_name = options.display_name.unwrap_or(folder_name);
+    let manifest = peon_core::build_manifest(&name, &display_name, &entries);
+    let mut pack_files = BTreeMap::new();
+    for entry in &entries {
+        let bytes = std::fs::read(source_dir.join(&entry.file))?;
+        pack_files.insert(format!("sounds/{}", entry.file), bytes);
+    }
+    let manifest_json = serde_json::to_string_pretty(&manifest)? + "\n";
+    pack_files.insert("manifest.json".to_string(), manifest_json.into_bytes());
+    archive::install_files(packs_dir, &name, &pack_files, true)?;
+
+    println!(
+        "Imported {} sound(s) into {}",
+        entries.len(),
+        pack_dir.display()
+    );
+    let report = peon_core::validate_pack(
+        &state_io::read_pack_files(&pack_dir, packs_dir)?,
+        peon_core::DEFAULT_MAX_CLIP_SECONDS,
+    );
+    print!("{}", peon_core::format_validation(&report));
+    if report.error_count() > 0 {
+        return Err(format!("imported pack \"{name}\" failed validation").into());
+    }
+    Ok(())
+}
+
+/// Ask for each file's categories on stdin. Enter keeps the suggestion, `-` skips the file.
+fn assign_interactively(
+    plan: &mut [peon_core::ImportEntry],
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!(
+        "Categories: {}",
+        peon_core::types::KNOWN_CATEGORIES.join(", ")
+    );
+    println!("Enter a comma-separated list, press Enter to accept, or - to skip.");
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    for entry in plan.iter_mut() {
+        print!(
+            "{} \"{}\" [{}]: ",
+            entry.file,
+            entry.line,
+            entry.categories.join(",")
+        );
+        std::io::stdout().flush()?;
+        let Some(answer) = lines.next().transpose()? else {
+            break;
+        };
+        match answer.trim() {
+            "" => {}
+            "-" => entry.categories.clear(),
+            answer => {
+                entry.categories = answer
+                    .split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `"My Orc Pack"` → `"my_orc_pack"`.
+fn pack_name_from_folder(folder: &str) -> String {
+    folder
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn check_pack_name(name: &str) -> Result<(), String> {
+    if peon_core::is_valid_pack_name(name) {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid pack name \"{name}\": use letters, digits, '_', '-' or '.'"
+        ))
+    }
+}
+
+fn validate(
+    packs_dir: &Path,
+    target: Option<&str>,
+    json: bool,
+    max_duration: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pack_dirs = match target {
+        Some(target) => vec![resolve_pack_dir(packs_dir, target)?],
+        None => installed_pack_dirs(packs_dir),
+    };
+    if pack_dirs.is_empty() {
+        return Err(format!("no packs found in {}", packs_dir.display()).into());
+    }
+
+    let mut reports = Vec::new();
+    for pack_dir in &pack_dirs {
+        let files = state_io::read_pack_files(pack_dir, packs_dir)?;
+        reports.push(peon_core::validate_pack(&files, max_duration));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        let blocks: Vec<String> = reports.iter().map(peon_core::format_validation).collect();
+        print!("{}", blocks.join("\n"));
+    }
+
+    let failed = reports.iter().filter(|r| !r.is_ok()).count();
+    if failed > 0 {
+        return Err(format!("{failed} pack(s) failed validation").into());
+    }
+    Ok(())
+}
+
+fn export(
+    packs_dir: &Path,
+    pack: &str,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pack_dir = resolve_pack_dir(packs_dir, pack)?;
+    let manifest = state_io::load_manifest(&pack_dir)?;
+    let files = archive::collect_pack_files(&pack_dir)?;
+    let output = output.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{}.{}",
+            manifest.name,
+            peon_core::ARCHIVE_EXTENSION
+        ))
+    });
+    archive::write_archive(&files, &output)?;
+
+    // Sound count excludes manifest.json and checksums.txt
+    let sounds = files.keys().filter(|p| p.starts_with("sounds/")).count();
+    println!(
+        "Exported {} ({sounds} sound(s)) to {}",
+        manifest.name,
+        output.display()
+    );
+    if let Some(base) = &manifest.extends {
+        eprintln!(
+            "note: {} extends \"{base}\", which must be installed separately",
+            manifest.name
+        );
+    }
+    Ok(())
+}
+
+fn install(packs_dir: &Path, source: &Path, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let files = if source.is_dir() {
+        archive::collect_pack_files(source)?
+    } else {
+        archive::read_archive(source)
+            .map_err(|e| format!("failed to read {}: {e}", source.display()))?
+    };
+    let manifest = peon_core::verify_archive(&files)?;
+
+    let report = peon_core::validate_pack(
+        &archived_pack_files(&manifest.name, &files, packs_dir),
+        peon_core::DEFAULT_MAX_CLIP_SECONDS,
+    );
+    if !report.is_ok() {
+        print!("{}", peon_core::format_validation(&report));
+        return Err(format!(
+            "pack \"{}\" failed validation, not installed",
+            manifest.name
+        )
+        .into());
+    }
+
+    let dest = archive::install_files(packs_dir, &manifest.name, &files, force)?;
+    let mut lock = state_io::load_lock(packs_dir);
+    lock.packs.insert(
+        manifest.name.clone(),
+        peon_core::LockEntry {
+            source: peon_core::PackSource::Archive,
+            url: None,
+            version: manifest.version.clone(),
+            revision: None,
+            files: peon_core::file_digests(&files),
+        },
+    );
+    state_io::save_lock(packs_dir, &lock)?;
+    println!("Installed {} to {}", manifest.name, dest.display());
+    println!("Switch to it with: peon pack {}", manifest.name);
+    Ok(())
+}
+
+/// `peon remove`: delete installed packs and forget them in config, state and the lock file.
+///
+/// Packs referenced by `active_pack` or `pack_rotation` are refused unless `force` is
+/// set, in which case they're dropped from the rotation and the active pack switches to
+/// [`peon_core::replacement_pack`].
+pub fn remove(
+    packs_dir: &Path,
+    names: &[String],
+    dry_run: bool,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for name in names {
+        check_pack_name(name)?;
+        if !packs_dir.join(name).is_dir() {
+            return Err(format!("pack \"{name}\" is not installed").into());
+        }
+    }
+
+    // Conflicts and the new rotation come from the file alone: it's what gets rewritten,
+    // and `PEON_*` overrides only last for one command
+    let config_path = paths::config_path();
+    let config = state_io::load_file_config(&config_path);
+    let installed: Vec<String> = state_io::list_installed_sounds(packs_dir)
+        .into_keys()
+        .collect();
+    let replacement = peon_core::replacement_pack(&config, names, &installed);
+    let conflicts = peon_core::removal_conflicts(&config, names);
+    if !conflicts.is_empty() && !force {
+        let mut message = conflicts
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        match &replacement {
+            Some(pack) => message.push_str(&format!(
+                "\nswitch first with `peon pack {pack}`, or pass --force to switch to \"{pack}\""
+            )),
+            None => message.push_str("\nno other pack is installed to switch to"),
+        }
+        return Err(message.into());
+    }
+
+    let prefix = if dry_run { "Would remove" } else { "Removed" };
+    let mut config_map = state_io::load_config_map(&config_path);
+    for conflict in &conflicts {
+        match conflict {
+            peon_core::RemovalConflict::Active(name) => {
+                let pack = replacement
+                    .as_deref()
+                    .ok_or_else(|| format!("\"{name}\" is the only installed pack"))?;
+                let verb = if dry_run { "Would switch" } else { "Switched" };
+                println!("{verb} active pack from {name} to {pack}");
+                config_map.insert("active_pack".into(), serde_json::Value::String(pack.into()));
+            }
+            peon_core::RemovalConflict::Rotation(name) => {
+                println!("{prefix} {name} from pack_rotation");
+            }
+        }
+    }
+    if conflicts
+        .iter()
+        .any(|c| matches!(c, peon_core::RemovalConflict::Rotation(_)))
+    {
+        let rotation: Vec<serde_json::Value> = config
+            .pack_rotation
+            .iter()
+            .filter(|p| !names.contains(p))
+            .map(|p| serde_json::Value::String(p.clone()))
+            .collect();
+        config_map.insert("pack_rotation".into(), serde_json::Value::Array(rotation));
+    }
+
+    let state_path = paths::state_path();
+    let mut state = state_io::load_state(&state_path);
+    let pruned = peon_core::prune_state(&mut state, names);
+
+    for name in names {
+        println!("{prefix} {}", packs_dir.join(name).display());
+    }
+    if pruned > 0 {
+        println!("{prefix} {pruned} session state entries");
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if !conflicts.is_empty() {
+        state_io::save_config_map(&config_path, &config_map)?;
+    }
+    let mut lock = state_io::load_lock(packs_dir);
+    for name in names {
+        std::fs::remove_dir_all(packs_dir.join(name))?;
+        lock.packs.remove(name);
+    }
+    state_io::save_lock(packs_dir, &lock)?;
+    if pruned > 0 {
+        state_io::save_state(&state_path, &state)?;
+    }
+    Ok(())
+}
+
+/// Describe in-memory pack files the way `validate_pack` expects, as if installed as `name`.
+fn archived_pack_files(
+    name: &str,
+    files: &BTreeMap<String, Vec<u8>>,
+    packs_dir: &Path,
+) -> peon_core::PackFiles {
+    let sound_files = files
+        .iter()
+        .filter_map(|(path, bytes)| {
+            let file = path.strip_prefix("sounds/")?;
+            Some(peon_core::SoundFile {
+                name: file.to_string(),
+                duration_secs: peon_core::clip_duration(file, bytes),
+            })
+        })
+        .collect();
+    let mut installed = state_io::list_installed_sounds(packs_dir);
+    installed.remove(name);
+    peon_core::PackFiles {
+        dir_name: name.to_string(),
+        manifest_json: String::from_utf8_lossy(&files["manifest.json"]).into_owned(),
+        sound_files,
+        installed,
+    }
+}
+
+/// Interpret `target` as a path to a pack directory, or else an installed pack name.
+fn resolve_pack_dir(packs_dir: &Path, target: &str) -> Result<PathBuf, String> {
+    let as_path = PathBuf::from(target);
+    if as_path.join("manifest.json").is_file() {
+        // `.` and `..` have no name of their own; the pack is named after its directory
+        return as_path
+            .canonicalize()
+            .map_err(|e| format!("failed to resolve {target}: {e}"));
+    }
+    let installed = packs_dir.join(target);
+    if installed.join("manifest.json").is_file() {
+        return Ok(installed);
+    }
+    Err(format!(
+        "\"{target}\" is neither a pack directory nor an installed pack in {}",
+        packs_dir.display()
+    ))
+}
+
+/// Every directory in `packs_dir` that has a `manifest.json`, valid or not.
+fn installed_pack_dirs(packs_dir: &Path) -> Vec<PathBuf> {
+    state_io::pack_dirs(packs_dir)
+        .into_iter()
+        .filter(|p| p.join("manifest.json").is_file())
+        .collect()
+}
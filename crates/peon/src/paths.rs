@@ -26,6 +26,10 @@ pub fn paused_path() -> PathBuf {
     peon_dir().join(".paused")
 }
 
+pub fn update_cache_path() -> PathBuf {
+    peon_dir().join(peon_core::UPDATE_CACHE_FILE)
+}
+
 pub fn packs_dir(cli_override: Option<&std::path::Path>) -> PathBuf {
     if let Some(dir) = cli_override {
         return dir.to_path_buf();
@@ -0,0 +1,561 @@
+use peon_core::types::Config;
+use peon_core::{
+    LockEntry, PackRequest, PackSource, RegistryIndex, RegistryPack, SoundDiff, SourceSpec,
+};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+use crate::sources::{self, SourceCache};
+use crate::{archive, http, state_io};
+
+/// Cached copy of the last registry index, reused when the server answers 304.
+const INDEX_CACHE_FILE: &str = ".registry-index.json";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct IndexCache {
+    url: String,
+    etag: String,
+    index: RegistryIndex,
+}
+
+enum Fetched {
+    NotModified,
+    Body {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+    },
+}
+
+/// Fetch a URL's body. `file://` URLs are read from disk, so mirrors can live on a share.
+pub fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    match fetch_conditional(url, None)? {
+        Fetched::Body { bytes, .. } => Ok(bytes),
+        Fetched::NotModified => Err(format!("{url}: unexpected 304 Not Modified").into()),
+    }
+}
+
+/// Fetch a URL, sending `If-None-Match` when an ETag is known.
+fn fetch_conditional(url: &str, etag: Option<&str>) -> Result<Fetched, Box<dyn std::error::Error>> {
+    if let Some(path) = url.strip_prefix("file://") {
+        let bytes = std::fs::read(path).map_err(|e| format!("{url}: {e}"))?;
+        return Ok(Fetched::Body { bytes, etag: None });
+    }
+    let headers: &[(&str, &str)] = match etag {
+        Some(etag) => &[("If-None-Match", etag)],
+        None => &[],
+    };
+    let response = http::get(url, headers).map_err(|e| format!("{url}: {e}"))?;
+    if response.status() == 304 {
+        return Ok(Fetched::NotModified);
+    }
+    let etag = response.header("ETag").map(str::to_string);
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(Fetched::Body { bytes, etag })
+}
+
+/// Fetch the registry index, revalidating the cached copy in `packs_dir` by ETag.
+pub fn fetch_index(
+    url: &str,
+    packs_dir: &Path,
+) -> Result<RegistryIndex, Box<dyn std::error::Error>> {
+    let cache_path = packs_dir.join(INDEX_CACHE_FILE);
+    let cache: Option<IndexCache> = std::fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .filter(|cache: &IndexCache| cache.url == url);
+
+    match fetch_conditional(url, cache.as_ref().map(|c| c.etag.as_str()))? {
+        Fetched::NotModified => match cache {
+            Some(cache) => Ok(cache.index),
+            None => Err(format!("{url}: unexpected 304 Not Modified").into()),
+        },
+        Fetched::Body { bytes, etag } => {
+            let index = RegistryIndex::parse(&String::from_utf8_lossy(&bytes))?;
+            if let Some(etag) = etag {
+                let cache = IndexCache {
+                    url: url.to_string(),
+                    etag,
+                    index: index.clone(),
+                };
+                // The cache only saves bandwidth; failing to write it is harmless
+                if std::fs::create_dir_all(packs_dir).is_ok() {
+                    let _ = std::fs::write(&cache_path, serde_json::to_string(&cache)?);
+                }
+            }
+            Ok(index)
+        }
+    }
+}
+
+/// Whether the configured registry index lists the packs of `source`, as the default
+/// index does for the repository it is published from.
+fn index_lists(config: &Config, source: &SourceSpec) -> bool {
+    config.registry_url == peon_core::DEFAULT_REGISTRY_URL && *source == SourceSpec::default_repo()
+}
+
+/// The registry index to check packs pulled from `source` against, if it lists them.
+fn index_for(config: &Config, source: &SourceSpec, packs_dir: &Path) -> Option<RegistryIndex> {
+    if !index_lists(config, source) {
+        return None;
+    }
+    fetch_index(&config.registry_url, packs_dir)
+        .map_err(|e| eprintln!("warning: registry index unavailable ({e})"))
+        .ok()
+}
+
+/// SHA-256 digests of an installed pack's files, or `None` if it isn't installed.
+fn local_digests(pack_dir: &Path) -> Option<BTreeMap<String, String>> {
+    if !pack_dir.join("manifest.json").is_file() {
+        return None;
+    }
+    let files = archive::collect_pack_files(pack_dir).ok()?;
+    Some(peon_core::file_digests(&files))
+}
+
+/// Print the registry's packs with installed/outdated markers.
+pub fn list_remote(registry_url: &str, packs_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let index = fetch_index(registry_url, packs_dir)?;
+    let status = |pack: &RegistryPack| {
+        peon_core::pack_status(pack, local_digests(&packs_dir.join(&pack.name)).as_ref())
+    };
+    print!("{}", peon_core::format_remote_packs(&index, &status));
+    Ok(())
+}
+
+/// Download one pack listed in the index, verify every file and install it atomically.
+///
+/// Files whose digest matches the installed copy are reused instead of downloaded.
+/// Returns the digests of the installed files.
+fn pull_pack(
+    index: &RegistryIndex,
+    index_url: &str,
+    pack: &RegistryPack,
+    packs_dir: &Path,
+) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let pack_dir = packs_dir.join(&pack.name);
+    let local = local_digests(&pack_dir).unwrap_or_default();
+
+    let mut files = BTreeMap::new();
+    for file in &pack.files {
+        peon_core::check_entry_path(&file.path)?;
+        let unchanged = local
+            .get(&file.path)
+            .is_some_and(|digest| digest.eq_ignore_ascii_case(&file.sha256));
+        let bytes = if unchanged {
+            std::fs::read(pack_dir.join(&file.path))?
+        } else {
+            fetch(&index.file_url(index_url, &pack.name, &file.path))?
+        };
+        peon_core::verify_download(file, &bytes)?;
+        files.insert(file.path.clone(), bytes);
+    }
+
+    let manifest = peon_core::check_pack_contents(&files)?;
+    if manifest.name != pack.name {
+        return Err(format!(
+            "index entry \"{}\" contains pack \"{}\"",
+            pack.name, manifest.name
+        )
+        .into());
+    }
+    archive::install_files(packs_dir, &pack.name, &files, true)?;
+    Ok(peon_core::file_digests(&files))
+}
+
+/// `peon pull`: install packs from a source, the configured `pack_sources` or the
+/// registry index.
+///
+/// `spec` is parsed with `peon_core::parse_pack_request`. A bare pack name is looked up
+/// in each of `pack_sources` in order, then in the registry. When the index cannot be
+/// fetched, packs come from the default GitHub repository instead.
+pub fn pull(
+    spec: Option<String>,
+    all: bool,
+    config: &Config,
+    packs_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(packs_dir)?;
+    let request = spec
+        .as_deref()
+        .map(peon_core::parse_pack_request)
+        .transpose()?;
+    let mut lock = state_io::load_lock(packs_dir);
+
+    let name = match request {
+        Some(PackRequest {
+            source: Some(source),
+            pack,
+        }) => {
+            let index = index_for(config, &source, packs_dir);
+            let result = sources::pull(
+                &source,
+                pack.as_deref(),
+                all,
+                &config.github_api_url,
+                packs_dir,
+                &mut lock,
+                index.as_ref(),
+            );
+            state_io::save_lock(packs_dir, &lock)?;
+            return result;
+        }
+        Some(PackRequest { source: None, pack }) => pack,
+        None => None,
+    };
+
+    if let (Some(name), false) = (&name, all) {
+        for spec in &config.pack_sources {
+            let source = match SourceSpec::parse(spec) {
+                Ok(source) => source,
+                Err(e) => {
+                    eprintln!("warning: skipping pack_sources entry: {e}");
+                    continue;
+                }
+            };
+            match sources::fetch(&source, &config.github_api_url) {
+                Ok(snapshot) if snapshot.contains(name) => {
+                    let index = index_for(config, &source, packs_dir);
+                    let expected = index.as_ref().and_then(|index| index.find(name));
+                    let result = snapshot.install(name, packs_dir, expected)?;
+                    println!(
+                        "Installed: {} ({} files) from {source}",
+                        result.name, result.files
+                    );
+                    lock.packs
+                        .insert(name.clone(), sources::lock_entry(&source, result));
+                    state_io::save_lock(packs_dir, &lock)?;
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("warning: {source} unavailable ({e})"),
+            }
+        }
+    }
+
+    let registry_url = config.registry_url.as_str();
+    let index = match fetch_index(registry_url, packs_dir) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("warning: registry index unavailable ({e}); using GitHub");
+            if name.is_none() && !all {
+                return Err("pack name required (or use --all)".into());
+            }
+            let result = sources::pull(
+                &SourceSpec::default_repo(),
+                name.as_deref(),
+                all,
+                &config.github_api_url,
+                packs_dir,
+                &mut lock,
+                None,
+            );
+            state_io::save_lock(packs_dir, &lock)?;
+            return result;
+        }
+    };
+
+    let selected: Vec<&RegistryPack> = if all {
+        index.packs.iter().collect()
+    } else {
+        let name = name.ok_or("pack name required (or use --all)")?;
+        let pack = index.find(&name).ok_or_else(|| {
+            format!("pack '{name}' is not in the registry (see `peon packs --remote`)")
+        })?;
+        vec![pack]
+    };
+    if selected.is_empty() {
+        return Err("no packs in the registry".into());
+    }
+
+    let mut installed = 0;
+    let mut failed = 0;
+    for pack in selected {
+        match pull_pack(&index, registry_url, pack, packs_dir) {
+            Ok(digests) => {
+                println!(
+                    "Installed: {}{} ({} files, {})",
+                    pack.name,
+                    version_suffix(pack.version.as_deref()),
+                    pack.files.len(),
+                    peon_core::format_size(pack.size)
+                );
+                lock.packs.insert(
+                    pack.name.clone(),
+                    registry_lock_entry(registry_url, pack, digests),
+                );
+                installed += 1;
+            }
+            Err(e) if !all => return Err(format!("{}: {e}", pack.name).into()),
+            Err(e) => {
+                eprintln!("  {} — failed: {e}", pack.name);
+                failed += 1;
+            }
+        }
+    }
+    state_io::save_lock(packs_dir, &lock)?;
+    if all {
+        println!("Installed {installed} packs.");
+    }
+    if failed > 0 && installed == 0 {
+        return Err("no packs were installed".into());
+    }
+    Ok(())
+}
+
+fn registry_lock_entry(
+    registry_url: &str,
+    pack: &RegistryPack,
+    files: BTreeMap<String, String>,
+) -> LockEntry {
+    LockEntry {
+        source: PackSource::Registry,
+        url: Some(registry_url.to_string()),
+        version: pack.version.clone(),
+        revision: None,
+        files,
+    }
+}
+
+/// How an installed pack compares with where it came from.
+enum UpdateCheck {
+    UpToDate,
+    /// Newer files are available. `diff` is only known for registry packs.
+    Outdated {
+        latest: Option<String>,
+        diff: Option<SoundDiff>,
+    },
+    /// Installed from an archive, or not listed anywhere we can check.
+    Untracked,
+}
+
+fn check_pack(
+    name: &str,
+    entry: Option<&LockEntry>,
+    index: Option<&RegistryIndex>,
+    cache: &mut SourceCache,
+    packs_dir: &Path,
+) -> Result<UpdateCheck, Box<dyn std::error::Error>> {
+    let source = entry.and_then(sources::entry_source);
+    match (entry, &source) {
+        (Some(entry), _) if entry.source == PackSource::Archive => {
+            return Ok(UpdateCheck::Untracked)
+        }
+        (Some(entry), Some(source @ SourceSpec::Local { .. })) => {
+            let Some(latest) = cache.digests(source, name)? else {
+                return Ok(UpdateCheck::Untracked);
+            };
+            let diff =
+                peon_core::diff_sounds(&installed_digests(Some(entry), packs_dir, name), &latest);
+            return Ok(if diff.is_empty() {
+                UpdateCheck::UpToDate
+            } else {
+                UpdateCheck::Outdated {
+                    latest: None,
+                    diff: Some(diff),
+                }
+            });
+        }
+        (Some(entry), Some(source)) => {
+            if let Some(installed) = &entry.revision {
+                return Ok(match cache.revision(source, name)? {
+                    None => UpdateCheck::Untracked,
+                    Some(latest) if latest == *installed => UpdateCheck::UpToDate,
+                    Some(_) => UpdateCheck::Outdated {
+                        latest: None,
+                        diff: None,
+                    },
+                });
+            }
+        }
+        _ => {}
+    }
+
+    let Some(remote) = index.and_then(|index| index.find(name)) else {
+        return Ok(UpdateCheck::Untracked);
+    };
+    let diff = peon_core::diff_sounds(
+        &installed_digests(entry, packs_dir, name),
+        &peon_core::registry_digests(remote),
+    );
+    Ok(if diff.is_empty() {
+        UpdateCheck::UpToDate
+    } else {
+        UpdateCheck::Outdated {
+            latest: remote.version.clone(),
+            diff: Some(diff),
+        }
+    })
+}
+
+/// Digests recorded in the lock file, or computed from disk for untracked packs.
+fn installed_digests(
+    entry: Option<&LockEntry>,
+    packs_dir: &Path,
+    name: &str,
+) -> BTreeMap<String, String> {
+    match entry {
+        Some(entry) if !entry.files.is_empty() => entry.files.clone(),
+        _ => local_digests(&packs_dir.join(name)).unwrap_or_default(),
+    }
+}
+
+fn installed_version(entry: Option<&LockEntry>, packs_dir: &Path, name: &str) -> Option<String> {
+    entry.and_then(|e| e.version.clone()).or_else(|| {
+        state_io::load_manifest(&packs_dir.join(name))
+            .ok()
+            .and_then(|m| m.version)
+    })
+}
+
+fn installed_names(packs_dir: &Path) -> Vec<String> {
+    state_io::pack_dirs(packs_dir)
+        .iter()
+        .filter(|dir| dir.join("manifest.json").is_file())
+        .filter_map(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .collect()
+}
+
+fn version_suffix(version: Option<&str>) -> String {
+    version.map(|v| format!(" v{v}")).unwrap_or_default()
+}
+
+/// `v1.0.0 → v1.1.0`, or whichever side is known.
+fn version_change(current: Option<&str>, latest: Option<&str>) -> String {
+    match (current, latest) {
+        (Some(current), Some(latest)) if current != latest => format!("v{current} → v{latest}"),
+        (_, Some(latest)) => format!("v{latest}"),
+        (Some(current), None) => format!("v{current}"),
+        (None, None) => String::new(),
+    }
+}
+
+/// `peon outdated`: list installed packs that have newer files upstream.
+pub fn outdated(config: &Config, packs_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let registry_url = config.registry_url.as_str();
+    let lock = state_io::load_lock(packs_dir);
+    let index = fetch_index(registry_url, packs_dir)
+        .map_err(|e| eprintln!("warning: registry index unavailable ({e})"))
+        .ok();
+
+    let names = installed_names(packs_dir);
+    if names.is_empty() {
+        println!("No packs installed in {}", packs_dir.display());
+        return Ok(());
+    }
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+
+    let mut cache = SourceCache::new(&config.github_api_url);
+    let mut outdated = 0;
+    let mut untracked = Vec::new();
+    for name in &names {
+        let entry = lock.packs.get(name);
+        match check_pack(name, entry, index.as_ref(), &mut cache, packs_dir) {
+            Ok(UpdateCheck::UpToDate) => {}
+            Ok(UpdateCheck::Untracked) => untracked.push(name.as_str()),
+            Ok(UpdateCheck::Outdated { latest, diff }) => {
+                let current = installed_version(entry, packs_dir, name);
+                let change = version_change(current.as_deref(), latest.as_deref());
+                let summary = match diff {
+                    Some(diff) => diff.summary(),
+                    None => match entry.and_then(|e| e.url.as_deref()) {
+                        Some(url) => format!("changed in {url}"),
+                        None => "changed on GitHub".to_string(),
+                    },
+                };
+                println!("  {name:width$}  {change:18}  {summary}");
+                outdated += 1;
+            }
+            Err(e) => eprintln!("  {name:width$}  check failed: {e}"),
+        }
+    }
+
+    if outdated == 0 {
+        println!("All packs are up to date.");
+    } else {
+        println!("{outdated} pack(s) can be updated with `peon pull --update`.");
+    }
+    if !untracked.is_empty() {
+        println!("Not tracked remotely: {}", untracked.join(", "));
+    }
+    Ok(())
+}
+
+/// `peon pull --update`: re-download installed packs whose upstream files changed.
+pub fn update(
+    name: Option<String>,
+    config: &Config,
+    packs_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let registry_url = config.registry_url.as_str();
+    let mut lock = state_io::load_lock(packs_dir);
+    let index = fetch_index(registry_url, packs_dir)
+        .map_err(|e| eprintln!("warning: registry index unavailable ({e})"))
+        .ok();
+
+    let names = match name {
+        Some(name) if !packs_dir.join(&name).join("manifest.json").is_file() => {
+            return Err(format!("pack '{name}' is not installed").into());
+        }
+        Some(name) => vec![name],
+        None => installed_names(packs_dir),
+    };
+
+    let mut cache = SourceCache::new(&config.github_api_url);
+    let mut updated = 0;
+    for name in &names {
+        let entry = lock.packs.get(name).cloned();
+        let check = match check_pack(name, entry.as_ref(), index.as_ref(), &mut cache, packs_dir) {
+            Ok(check) => check,
+            Err(e) => {
+                eprintln!("  {name} — check failed: {e}");
+                continue;
+            }
+        };
+        if !matches!(check, UpdateCheck::Outdated { .. }) {
+            continue;
+        }
+
+        let before = installed_digests(entry.as_ref(), packs_dir, name);
+        let current = installed_version(entry.as_ref(), packs_dir, name);
+        let remote = index
+            .as_ref()
+            .and_then(|index| Some((index, index.find(name)?)));
+        let source = entry.as_ref().and_then(sources::entry_source);
+        let result = match (source, remote) {
+            (Some(source), _) => {
+                let expected = index
+                    .as_ref()
+                    .filter(|_| index_lists(config, &source))
+                    .and_then(|index| index.find(name));
+                cache.install(&source, name, packs_dir, expected)
+            }
+            (_, Some((index, remote))) => pull_pack(index, registry_url, remote, packs_dir)
+                .map(|digests| registry_lock_entry(registry_url, remote, digests)),
+            (_, None) => continue,
+        };
+        match result {
+            Ok(new_entry) => {
+                let diff = peon_core::diff_sounds(&before, &new_entry.files);
+                let change = version_change(current.as_deref(), new_entry.version.as_deref());
+                let change = if change.is_empty() {
+                    change
+                } else {
+                    format!(" {change}")
+                };
+                println!("Updated {name}{change} ({})", diff.summary());
+                print!("{}", peon_core::format_sound_diff(&diff));
+                lock.packs.insert(name.clone(), new_entry);
+                updated += 1;
+            }
+            Err(e) => eprintln!("  {name} — failed: {e}"),
+        }
+    }
+
+    state_io::save_lock(packs_dir, &lock)?;
+    if updated == 0 {
+        println!("All packs are up to date.");
+    }
+    Ok(())
+}
@@ -0,0 +1,473 @@
+use peon_core::{LockEntry, PackSource, RegistryIndex, RegistryPack, SourceSpec, TreePack};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{archive, github};
+
+/// Files of a source keyed by path relative to its root.
+pub type TreeFiles = BTreeMap<String, Vec<u8>>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum InstallError {
+    #[error("pack \"{pack}\" was not found in {origin}")]
+    NotFound { pack: String, origin: String },
+    #[error("downloaded pack is invalid: {0}")]
+    Verify(#[from] peon_core::ArchiveError),
+    #[error("downloaded pack doesn't match the registry index: {0}")]
+    IndexMismatch(String),
+    #[error("downloaded pack is named \"{found}\", expected \"{expected}\"")]
+    NameMismatch { expected: String, found: String },
+    #[error("failed to install pack: {0}")]
+    Install(String),
+}
+
+#[derive(Debug)]
+pub struct PullResult {
+    pub name: String,
+    pub files: usize,
+    pub version: Option<String>,
+    /// Upstream revision of the pack (see [`Revisions`]).
+    pub revision: Option<String>,
+    /// SHA-256 of each installed file (see `peon_core::file_digests`).
+    pub digests: BTreeMap<String, String>,
+}
+
+/// Upstream revisions of the directories in a source: git tree SHAs for GitHub, the
+/// commit for other git remotes. Local directories have none.
+#[derive(Debug, Clone, Default)]
+pub struct Revisions {
+    pub root: Option<String>,
+    /// Revision of each directory, keyed by path relative to the source root.
+    pub dirs: BTreeMap<String, String>,
+}
+
+impl Revisions {
+    fn for_prefix(&self, prefix: &str) -> Option<String> {
+        self.dirs.get(prefix).or(self.root.as_ref()).cloned()
+    }
+
+    /// Revision of a pack without knowing the source's layout.
+    fn for_pack(&self, name: &str) -> Option<String> {
+        self.dirs
+            .get(&format!("packs/{name}"))
+            .or_else(|| self.dirs.get(name))
+            .or(self.root.as_ref())
+            .cloned()
+    }
+}
+
+/// Every pack in a source, fetched in one go (one tarball, clone or directory read).
+pub struct PackSnapshot {
+    source: SourceSpec,
+    packs: BTreeMap<String, TreePack>,
+    revisions: Revisions,
+}
+
+impl PackSnapshot {
+    pub fn new(source: SourceSpec, files: &TreeFiles, revisions: Revisions) -> Self {
+        Self {
+            source,
+            packs: peon_core::packs_in_tree(files),
+            revisions,
+        }
+    }
+
+    /// Names of the packs in the snapshot.
+    pub fn names(&self) -> Vec<String> {
+        self.packs.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.packs.contains_key(name)
+    }
+
+    /// Install one pack from the snapshot into `dest_dir`.
+    ///
+    /// The pack's files are checked against `expected`, its registry index entry, when
+    /// the index lists the source's packs, and otherwise against the `checksums.txt` the
+    /// pack ships, if any. Then the pack is checked (manifest, referenced sounds) before
+    /// anything is written, staged and renamed into place, so a failed install leaves any
+    /// previously installed version untouched.
+    pub fn install(
+        &self,
+        pack_name: &str,
+        dest_dir: &Path,
+        expected: Option<&RegistryPack>,
+    ) -> Result<PullResult, InstallError> {
+        let pack = self
+            .packs
+            .get(pack_name)
+            .ok_or_else(|| InstallError::NotFound {
+                pack: pack_name.to_string(),
+                origin: self.source.to_string(),
+            })?;
+        let mut files = pack.files.clone();
+        let checksums = files.remove(peon_core::CHECKSUMS_FILE);
+        let file_count = files.len();
+
+        match (expected, checksums) {
+            (Some(expected), _) => peon_core::verify_pack_files(expected, &files)
+                .map_err(InstallError::IndexMismatch)?,
+            (None, Some(checksums)) => peon_core::check_checksums(
+                &files,
+                &peon_core::parse_checksums(&String::from_utf8_lossy(&checksums))?,
+            )?,
+            (None, None) => {}
+        }
+        let manifest = peon_core::check_pack_contents(&files)?;
+        if manifest.name != pack_name {
+            return Err(InstallError::NameMismatch {
+                expected: pack_name.to_string(),
+                found: manifest.name,
+            });
+        }
+
+        archive::install_files(dest_dir, pack_name, &files, true)
+            .map_err(|e| InstallError::Install(e.to_string()))?;
+
+        Ok(PullResult {
+            name: pack_name.to_string(),
+            files: file_count,
+            version: manifest.version,
+            revision: self.revisions.for_prefix(&pack.prefix),
+            digests: peon_core::file_digests(&files),
+        })
+    }
+
+    /// Digests of a pack's files as they would be installed.
+    fn digests(&self, name: &str) -> Option<BTreeMap<String, String>> {
+        let mut files = self.packs.get(name)?.files.clone();
+        files.remove(peon_core::CHECKSUMS_FILE);
+        Some(peon_core::file_digests(&files))
+    }
+}
+
+/// Fetch every pack in `source`.
+pub fn fetch(
+    source: &SourceSpec,
+    api_url: &str,
+) -> Result<PackSnapshot, Box<dyn std::error::Error>> {
+    let (files, revisions) = match source {
+        SourceSpec::Github {
+            owner,
+            repo,
+            git_ref,
+        } => {
+            let repo_api = github::repo_api(api_url, owner, repo);
+            let revisions = github::tree_revisions(&repo_api, git_ref.as_deref())?;
+            let files = github::fetch_tree_files(&repo_api, git_ref.as_deref())?;
+            (files, revisions)
+        }
+        SourceSpec::Git { url, git_ref } => clone(url, git_ref.as_deref())?,
+        SourceSpec::Local { path } => {
+            let root = expand_home(path);
+            if !root.is_dir() {
+                return Err(format!("{} is not a directory", root.display()).into());
+            }
+            (read_tree(&root)?, Revisions::default())
+        }
+    };
+    Ok(PackSnapshot::new(source.clone(), &files, revisions))
+}
+
+/// Current revisions of `source` without downloading its packs.
+fn revisions(source: &SourceSpec, api_url: &str) -> Result<Revisions, Box<dyn std::error::Error>> {
+    match source {
+        SourceSpec::Github {
+            owner,
+            repo,
+            git_ref,
+        } => Ok(github::tree_revisions(
+            &github::repo_api(api_url, owner, repo),
+            git_ref.as_deref(),
+        )?),
+        SourceSpec::Git { url, git_ref } => Ok(Revisions {
+            root: Some(remote_commit(url, git_ref.as_deref())?),
+            dirs: BTreeMap::new(),
+        }),
+        SourceSpec::Local { .. } => Ok(Revisions::default()),
+    }
+}
+
+/// Lock file entry for a pack pulled from `source`.
+pub fn lock_entry(source: &SourceSpec, result: PullResult) -> LockEntry {
+    let kind = match source {
+        SourceSpec::Github { .. } => PackSource::Github,
+        SourceSpec::Git { .. } => PackSource::Git,
+        SourceSpec::Local { .. } => PackSource::Local,
+    };
+    LockEntry {
+        source: kind,
+        url: Some(source.to_string()),
+        version: result.version,
+        revision: result.revision,
+        files: result.digests,
+    }
+}
+
+/// The source a locked pack came from, if it was pulled from one.
+pub fn entry_source(entry: &LockEntry) -> Option<SourceSpec> {
+    match (entry.source, &entry.url) {
+        (PackSource::Github | PackSource::Git | PackSource::Local, Some(url)) => {
+            SourceSpec::parse(url).ok()
+        }
+        // Entries written before sources were configurable
+        (PackSource::Github, None) => Some(SourceSpec::default_repo()),
+        _ => None,
+    }
+}
+
+/// Install packs from one source: `pack`, every pack with `all`, or the source's only pack.
+///
+/// `index` is the registry index that lists the source's packs, if there is one.
+pub fn pull(
+    source: &SourceSpec,
+    pack: Option<&str>,
+    all: bool,
+    api_url: &str,
+    packs_dir: &Path,
+    lock: &mut peon_core::PackLock,
+    index: Option<&RegistryIndex>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Fetching packs from {source}...");
+    let snapshot = fetch(source, api_url)?;
+    let names = match (pack, all) {
+        (Some(pack), _) => vec![pack.to_string()],
+        (None, true) => snapshot.names(),
+        (None, false) if snapshot.packs.len() == 1 => snapshot.names(),
+        (None, false) => {
+            return Err(format!(
+                "{source} has {} packs ({}); name one with `{}` or use --all",
+                snapshot.packs.len(),
+                snapshot.names().join(", "),
+                pack_spec_example(source),
+            )
+            .into())
+        }
+    };
+    if names.is_empty() {
+        return Err(format!("no packs found in {source}").into());
+    }
+
+    let mut installed = 0;
+    for name in &names {
+        let expected = index.and_then(|index| index.find(name));
+        match snapshot.install(name, packs_dir, expected) {
+            Ok(result) => {
+                println!("Installed: {} ({} files)", result.name, result.files);
+                lock.packs
+                    .insert(result.name.clone(), lock_entry(source, result));
+                installed += 1;
+            }
+            Err(e) if !all => return Err(e.into()),
+            Err(e) => eprintln!("  {name} — failed: {e}"),
+        }
+    }
+    if all {
+        println!("Installed {installed} packs.");
+    }
+    if installed == 0 {
+        return Err("no packs were installed".into());
+    }
+    Ok(())
+}
+
+fn pack_spec_example(source: &SourceSpec) -> String {
+    match source {
+        SourceSpec::Github { .. } => format!("{source}:<pack>"),
+        SourceSpec::Git { url, git_ref } => {
+            format!("{url}#{}:<pack>", git_ref.as_deref().unwrap_or_default())
+        }
+        SourceSpec::Local { path } => format!("{}/<pack>", path.trim_end_matches('/')),
+    }
+}
+
+/// Snapshots and revisions shared by every pack a command checks, each fetched once.
+pub struct SourceCache<'a> {
+    api_url: &'a str,
+    revisions: HashMap<String, Revisions>,
+    snapshots: HashMap<String, PackSnapshot>,
+}
+
+impl<'a> SourceCache<'a> {
+    pub fn new(api_url: &'a str) -> Self {
+        Self {
+            api_url,
+            revisions: HashMap::new(),
+            snapshots: HashMap::new(),
+        }
+    }
+
+    /// The pack's current upstream revision, or `None` if the source has no revisions.
+    pub fn revision(
+        &mut self,
+        source: &SourceSpec,
+        name: &str,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let key = source.to_string();
+        if !self.revisions.contains_key(&key) {
+            let revisions = revisions(source, self.api_url)?;
+            self.revisions.insert(key.clone(), revisions);
+        }
+        Ok(self.revisions.get(&key).and_then(|r| r.for_pack(name)))
+    }
+
+    fn snapshot(
+        &mut self,
+        source: &SourceSpec,
+    ) -> Result<&PackSnapshot, Box<dyn std::error::Error>> {
+        let key = source.to_string();
+        if !self.snapshots.contains_key(&key) {
+            let snapshot = fetch(source, self.api_url)?;
+            self.snapshots.insert(key.clone(), snapshot);
+        }
+        self.snapshots
+            .get(&key)
+            .ok_or_else(|| format!("{source}: snapshot missing").into())
+    }
+
+    /// Digests of the pack's files in `source`, or `None` if the source doesn't have it.
+    pub fn digests(
+        &mut self,
+        source: &SourceSpec,
+        name: &str,
+    ) -> Result<Option<BTreeMap<String, String>>, Box<dyn std::error::Error>> {
+        Ok(self.snapshot(source)?.digests(name))
+    }
+
+    pub fn install(
+        &mut self,
+        source: &SourceSpec,
+        name: &str,
+        packs_dir: &Path,
+        expected: Option<&RegistryPack>,
+    ) -> Result<LockEntry, Box<dyn std::error::Error>> {
+        let result = self.snapshot(source)?.install(name, packs_dir, expected)?;
+        Ok(lock_entry(source, result))
+    }
+}
+
+/// Shallow-clone a git remote and read its pack files.
+fn clone(
+    url: &str,
+    git_ref: Option<&str>,
+) -> Result<(TreeFiles, Revisions), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("peon-clone-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut command = Command::new("git");
+    command.args(["clone", "--quiet", "--depth", "1"]);
+    if let Some(git_ref) = git_ref {
+        command.args(["--branch", git_ref]);
+    }
+    if is_repo_path(url) {
+        // Clone a repository path like a remote so --depth applies
+        command.arg("--no-local");
+    }
+    let status = command
+        .arg("--")
+        .arg(remote_arg(url))
+        .arg(&dir)
+        .status()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !status.success() {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(format!("git clone {url} failed").into());
+    }
+
+    let result = git_output(
+        Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["rev-parse", "HEAD"]),
+    )
+    .and_then(|commit| {
+        let files = read_tree(&dir)?;
+        Ok((
+            files,
+            Revisions {
+                root: Some(commit),
+                dirs: BTreeMap::new(),
+            },
+        ))
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+fn is_repo_path(url: &str) -> bool {
+    !url.contains("://") && !url.starts_with("git@")
+}
+
+/// The remote as git expects it, with `~` expanded in repository paths.
+fn remote_arg(url: &str) -> PathBuf {
+    if is_repo_path(url) {
+        expand_home(url)
+    } else {
+        PathBuf::from(url)
+    }
+}
+
+/// The commit a remote ref points at, without cloning.
+fn remote_commit(url: &str, git_ref: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    let output = git_output(
+        Command::new("git")
+            .arg("ls-remote")
+            .arg("--")
+            .arg(remote_arg(url))
+            .arg(git_ref.unwrap_or("HEAD")),
+    )?;
+    // Annotated tags list the tag object and the peeled commit (`^{}`); prefer the commit
+    let lines: Vec<(&str, &str)> = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect();
+    lines
+        .iter()
+        .find(|(_, name)| name.ends_with("^{}"))
+        .or(lines.first())
+        .map(|(commit, _)| commit.to_string())
+        .ok_or_else(|| format!("{url} has no ref {}", git_ref.unwrap_or("HEAD")).into())
+}
+
+fn git_output(command: &mut Command) -> Result<String, Box<dyn std::error::Error>> {
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .to_string()
+            .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Read the files under `root` that can belong to a pack (see `peon_core::packs_in_tree`).
+fn read_tree(root: &Path) -> Result<TreeFiles, Box<dyn std::error::Error>> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)?.flatten() {
+            let path = format!("{prefix}{}", entry.file_name().to_string_lossy());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() && peon_core::is_pack_tree_dir(&path) {
+                dirs.push((entry.path(), format!("{path}/")));
+            } else if file_type.is_file() && peon_core::is_pack_tree_file(&path) {
+                files.insert(path, std::fs::read(entry.path())?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = std::env::var("HOME").unwrap_or_default();
+            PathBuf::from(format!("{home}{rest}"))
+        }
+        _ => PathBuf::from(path),
+    }
+}
@@ -1,5 +1,9 @@
-use peon_core::types::{Config, ConfigMap, Manifest, State};
-use std::path::Path;
+use peon_core::types::{Config, ConfigMap, Manifest, Sound, State};
+use peon_core::upgrade::PreviousBinary;
+use peon_core::{PackFiles, PackLock, ResolvedConfig, SoundFile, UpdateCache};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(thiserror::Error, Debug)]
 pub enum StateIoError {
@@ -21,10 +25,26 @@ pub enum StateIoError {
         #[source]
         source: serde_json::Error,
     },
+    #[error("Failed to resolve pack: {0}")]
+    Inherit(#[from] peon_core::InheritError),
 }
 
-/// Load config from disk. Returns default if file doesn't exist or is invalid.
+/// Load the effective config: the file on disk with `PEON_*` environment overrides applied.
 pub fn load_config(path: &Path) -> Config {
+    load_resolved_config(path).config
+}
+
+/// Load config from disk and layer `PEON_*` environment variables on top, keeping track
+/// of which fields came from the environment.
+pub fn load_resolved_config(path: &Path) -> ResolvedConfig {
+    let vars: Vec<(String, String)> = std::env::vars().collect();
+    peon_core::resolve_config(&load_file_config(path), &vars)
+}
+
+/// Load config from disk only, without `PEON_*` overrides. Use this when writing config
+/// back, so temporary overrides aren't saved. Returns default if the file doesn't exist
+/// or is invalid.
+pub fn load_file_config(path: &Path) -> Config {
     match std::fs::read_to_string(path) {
         Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
         Err(_) => Config::default(),
@@ -83,6 +103,107 @@ pub fn save_state(path: &Path, state: &State) -> Result<(), StateIoError> {
     })
 }
 
+/// How long to wait for another hook to release the state lock before going without it.
+const STATE_LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Exclusive hold on the state file, released when dropped.
+///
+/// The lock is the operating system's lock on `.state.lock`, so a hook that crashes
+/// releases it with its process and there is never a stale lock to break.
+pub struct StateLock {
+    _file: std::fs::File,
+}
+
+/// Lock the state file so a read-modify-write can't interleave with another hook's.
+///
+/// Waits up to [`STATE_LOCK_TIMEOUT`]. Returns `None` if the lock can't be taken, so
+/// callers carry on unlocked rather than hold up Claude Code.
+pub fn lock_state(path: &Path) -> Option<StateLock> {
+    let lock_path = path.with_extension("lock");
+    if let Some(parent) = lock_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .ok()?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Some(StateLock { _file: file }),
+            Err(std::fs::TryLockError::WouldBlock) if started.elapsed() < STATE_LOCK_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Load the cached result of the last update check, if any.
+pub fn load_update_cache(path: &Path) -> Option<UpdateCache> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Save the result of an update check.
+pub fn save_update_cache(path: &Path, cache: &UpdateCache) -> Result<(), StateIoError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| StateIoError::WriteFile {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+    }
+    let content = serde_json::to_string(cache).map_err(|e| StateIoError::ParseJson {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    std::fs::write(path, content).map_err(|e| StateIoError::WriteFile {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Load the record of the binary kept by the last upgrade, if any.
+pub fn load_previous_binary(path: &Path) -> Option<PreviousBinary> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Record which version the kept previous binary is.
+pub fn save_previous_binary(path: &Path, previous: &PreviousBinary) -> Result<(), StateIoError> {
+    let content = serde_json::to_string(previous).map_err(|e| StateIoError::ParseJson {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    std::fs::write(path, content).map_err(|e| StateIoError::WriteFile {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Load the pack lock file from the packs directory, or an empty lock if missing or corrupt.
+pub fn load_lock(packs_dir: &Path) -> PackLock {
+    match std::fs::read_to_string(packs_dir.join(peon_core::LOCK_FILE)) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => PackLock::default(),
+    }
+}
+
+/// Save the pack lock file to the packs directory.
+pub fn save_lock(packs_dir: &Path, lock: &PackLock) -> Result<(), StateIoError> {
+    let path = packs_dir.join(peon_core::LOCK_FILE);
+    let content = serde_json::to_string_pretty(lock).map_err(|e| StateIoError::ParseJson {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    std::fs::write(&path, content + "\n").map_err(|e| StateIoError::WriteFile {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
 /// Check if the paused file exists.
 pub fn is_paused(path: &Path) -> bool {
     path.exists()
@@ -101,19 +222,152 @@ pub fn load_manifest(pack_dir: &Path) -> Result<Manifest, StateIoError> {
     })
 }
 
-/// List all available pack names by scanning the packs directory for manifest.json files.
-pub fn list_packs(packs_dir: &Path) -> Vec<(String, Manifest)> {
-    let mut packs = Vec::new();
-    if let Ok(entries) = std::fs::read_dir(packs_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                if let Ok(manifest) = load_manifest(&path) {
-                    packs.push((manifest.name.clone(), manifest));
-                }
-            }
+/// Write a manifest to `pack_dir/manifest.json`, creating the directory if needed.
+pub fn save_manifest(pack_dir: &Path, manifest: &Manifest) -> Result<(), StateIoError> {
+    let manifest_path = pack_dir.join("manifest.json");
+    std::fs::create_dir_all(pack_dir).map_err(|e| StateIoError::WriteFile {
+        path: pack_dir.display().to_string(),
+        source: e,
+    })?;
+    let content = serde_json::to_string_pretty(manifest).map_err(|e| StateIoError::ParseJson {
+        path: manifest_path.display().to_string(),
+        source: e,
+    })?;
+    std::fs::write(&manifest_path, content + "\n").map_err(|e| StateIoError::WriteFile {
+        path: manifest_path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Load a pack's manifest with its `extends` chain resolved.
+///
+/// Sounds inherited from (or referencing) other packs carry a `"pack:File.wav"` file name;
+/// use [`sound_path`] to locate them.
+pub fn load_pack(packs_dir: &Path, name: &str) -> Result<Manifest, StateIoError> {
+    let manifest = load_manifest(&packs_dir.join(name))?;
+    let loader = |pack: &str| {
+        if pack == name {
+            Some(manifest.clone())
+        } else {
+            load_manifest(&packs_dir.join(pack)).ok()
         }
+    };
+    Ok(peon_core::resolve_manifest(name, &loader)?)
+}
+
+/// Path to a sound file of `pack`, following `"other_pack:File.wav"` references.
+///
+/// Returns `None` for references that would resolve outside `packs_dir`.
+pub fn sound_path(packs_dir: &Path, pack: &str, sound: &Sound) -> Option<PathBuf> {
+    if !sound.has_safe_location() {
+        return None;
     }
+    let (pack, file) = sound.location(pack);
+    Some(packs_dir.join(pack).join("sounds").join(file))
+}
+
+/// Pack directories in `packs_dir`, sorted.
+///
+/// Hidden directories are skipped: they hold installs in progress and replaced packs.
+pub fn pack_dirs(packs_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(packs_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// List all available pack names by scanning the packs directory for manifest.json files.
+pub fn list_packs(packs_dir: &Path) -> Vec<(String, Manifest)> {
+    let mut packs: Vec<(String, Manifest)> = pack_dirs(packs_dir)
+        .iter()
+        .filter_map(|path| load_manifest(path).ok())
+        .map(|manifest| (manifest.name.clone(), manifest))
+        .collect();
     packs.sort_by(|a, b| a.0.cmp(&b.0));
     packs
 }
+
+/// Directory names of packs whose `manifest.json` exists but cannot be parsed.
+/// These are skipped by [`list_packs`].
+pub fn list_invalid_packs(packs_dir: &Path) -> Vec<String> {
+    pack_dirs(packs_dir)
+        .iter()
+        .filter(|path| path.join("manifest.json").is_file() && load_manifest(path).is_err())
+        .map(|path| dir_name(path))
+        .collect()
+}
+
+/// Collect what `peon_core::validate_pack` needs to lint the pack at `pack_dir`.
+///
+/// `packs_dir` is scanned for the other installed packs that `extends` and
+/// `"pack:File.wav"` references may point at.
+pub fn read_pack_files(pack_dir: &Path, packs_dir: &Path) -> Result<PackFiles, StateIoError> {
+    let manifest_path = pack_dir.join("manifest.json");
+    let manifest_json =
+        std::fs::read_to_string(&manifest_path).map_err(|e| StateIoError::ReadFile {
+            path: manifest_path.display().to_string(),
+            source: e,
+        })?;
+
+    let mut sound_files = Vec::new();
+    for (name, path) in list_sound_files(&pack_dir.join("sounds")) {
+        let duration_secs = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| peon_core::clip_duration(&name, &bytes));
+        sound_files.push(SoundFile {
+            name,
+            duration_secs,
+        });
+    }
+
+    Ok(PackFiles {
+        dir_name: dir_name(pack_dir),
+        manifest_json,
+        sound_files,
+        installed: list_installed_sounds(packs_dir),
+    })
+}
+
+/// Sound file names of every pack directory in `packs_dir`, keyed by directory name.
+pub fn list_installed_sounds(packs_dir: &Path) -> HashMap<String, HashSet<String>> {
+    pack_dirs(packs_dir)
+        .iter()
+        .filter(|path| path.join("manifest.json").is_file())
+        .map(|path| {
+            let files = list_sound_files(&path.join("sounds"))
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            (dir_name(path), files)
+        })
+        .collect()
+}
+
+/// Files directly inside a `sounds/` directory, sorted by name.
+fn list_sound_files(sounds_dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files: Vec<(String, PathBuf)> = std::fs::read_dir(sounds_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.path().is_file())
+                .map(|e| (e.file_name().to_string_lossy().into_owned(), e.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
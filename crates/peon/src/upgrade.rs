@@ -1,35 +1,113 @@
+use flate2::read::GzDecoder;
+use peon_core::types::Config;
 use peon_core::upgrade::{
-    find_matching_asset, get_asset_name, is_version_up_to_date, parse_version_tag, GitHubRelease,
+    detect_install_method, find_matching_asset, find_release, find_release_asset,
+    format_release_list, get_asset_name, is_version_up_to_date, latest_release,
+    parse_version_output, parse_version_tag, previous_binary_paths, verify_checksum,
+    verify_signature, AssetFormat, Channel, GitHubRelease, PreviousBinary, PublicKey,
+    CHECKSUMS_ASSET, SIGNATURE_ASSET,
 };
+use peon_core::{update_check_due, UpdateCache, Version};
+use std::cmp::Ordering;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const GITHUB_API_URL: &str = "https://api.github.com/repos/cloudbridgeuy/peon-ping/releases/latest";
+use crate::{http, paths, state_io, SilentExit};
+
+/// Exit status of `peon upgrade --check` when a newer release is available.
+const UPDATE_AVAILABLE_EXIT: u8 = 10;
+
+const RELEASES_URL: &str =
+    "https://api.github.com/repos/cloudbridgeuy/peon-ping/releases?per_page=100";
+
+/// Minisign public key that release checksums must be signed with, baked in at build time.
+/// Builds without it verify checksums only.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("PEON_RELEASE_PUBLIC_KEY");
 
 /// Self-update peon from GitHub releases
 #[derive(Debug, clap::Parser)]
 pub struct App {
-    /// Force upgrade even if already up to date
+    /// Force upgrade even if already up to date or installed by a package manager
     #[arg(long)]
     pub force: bool,
+    /// Install this release (e.g. 3.0.1 or v3.1.0-rc.1), even if it's older
+    #[arg(long, value_name = "VERSION")]
+    pub version: Option<String>,
+    /// Releases to follow: stable, or prerelease to include pre-releases
+    /// [default: update_channel from config, else stable]
+    #[arg(long)]
+    pub channel: Option<Channel>,
+    /// List available releases on the channel and exit
+    #[arg(long, conflicts_with_all = ["version", "force"])]
+    pub list: bool,
+    /// Only check for a newer release: exits 0 if up to date, 10 if an update is available
+    #[arg(long, conflicts_with_all = ["version", "force", "list"])]
+    pub check: bool,
+    /// Refresh the update cache silently (used by the background check)
+    #[arg(long, hide = true, requires = "check")]
+    pub background: bool,
+    /// Restore the binary that the last upgrade replaced
+    #[arg(long, conflicts_with_all = ["version", "force", "list", "check", "channel"])]
+    pub rollback: bool,
 }
 
 pub fn run(app: App) -> Result<(), Box<dyn std::error::Error>> {
+    let config = state_io::load_config(&paths::config_path());
+    let channel = app.channel.unwrap_or(config.update_channel);
+    if app.check {
+        return check(channel, app.background);
+    }
+
     let current_version = env!("CARGO_PKG_VERSION");
+    if app.rollback {
+        return rollback(current_version);
+    }
     println!("peon-ping: current version {current_version}");
 
     println!("peon-ping: checking for updates...");
-    let release = fetch_latest_release()?;
-    let latest_version = parse_version_tag(&release.tag_name);
+    let releases = fetch_releases()?;
 
-    if !app.force {
-        match is_version_up_to_date(current_version, latest_version) {
+    if app.list {
+        print!(
+            "{}",
+            format_release_list(&releases, current_version, channel)
+        );
+        return Ok(());
+    }
+
+    let release = match &app.version {
+        Some(version) => find_release(&releases, version)
+            .map_err(|e| format!("peon-ping: {e}"))?
+            .ok_or_else(|| format!("peon-ping: no release {version} found"))?,
+        None => latest_release(&releases, channel)
+            .ok_or("peon-ping: no releases found on this channel")?,
+    };
+    let target_version = parse_version_tag(&release.tag_name);
+
+    if app.version.is_some() {
+        let current = Version::parse(current_version).ok();
+        let order = Version::parse(target_version)
+            .ok()
+            .zip(current)
+            .map(|(target, current)| target.cmp(&current));
+        match order {
+            Some(Ordering::Equal) if !app.force => {
+                println!("peon-ping: already at {current_version}");
+                return Ok(());
+            }
+            Some(Ordering::Less) => println!("peon-ping: downgrading to {target_version}"),
+            _ => println!("peon-ping: installing {target_version}"),
+        }
+    } else if !app.force {
+        match is_version_up_to_date(current_version, target_version) {
             Ok(true) => {
                 println!("peon-ping: already up to date ({current_version})");
                 return Ok(());
             }
             Ok(false) => {
-                println!("peon-ping: new version available: {latest_version}");
+                println!("peon-ping: new version available: {target_version}");
             }
             Err(e) => {
                 eprintln!("peon-ping: warning: version comparison failed: {e}");
@@ -37,76 +115,252 @@ pub fn run(app: App) -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     } else {
-        println!("peon-ping: force upgrade to {latest_version}");
+        println!("peon-ping: force upgrade to {target_version}");
+    }
+
+    let current_exe = std::env::current_exe()?;
+    let installed_at = current_exe.canonicalize().unwrap_or(current_exe.clone());
+    let cargo_home = std::env::var_os("CARGO_HOME").map(PathBuf::from);
+    let method = detect_install_method(&installed_at, cargo_home.as_deref());
+    if let Some(hint) = method.upgrade_hint() {
+        if !app.force {
+            eprintln!(
+                "peon-ping: warning: {} was {hint}. Pass --force to replace it anyway.",
+                installed_at.display()
+            );
+            return Ok(());
+        }
+        eprintln!("peon-ping: warning: replacing a binary {hint}");
     }
 
-    let asset_name = get_asset_name(std::env::consts::OS, std::env::consts::ARCH)
+    let target_env = if cfg!(target_env = "musl") {
+        "musl"
+    } else {
+        "gnu"
+    };
+    let asset_name = get_asset_name(std::env::consts::OS, std::env::consts::ARCH, target_env)
         .map_err(|e| format!("peon-ping: {e}"))?;
 
-    let asset = find_matching_asset(&release, &asset_name)
+    let (asset, format) = find_release_asset(release, &asset_name)
         .ok_or_else(|| format!("peon-ping: no asset found for {asset_name}"))?;
 
-    let current_exe = std::env::current_exe()?;
     let download_path = current_exe.with_extension("download");
-    let backup_path = current_exe.with_extension("backup");
 
-    println!("peon-ping: downloading {asset_name}...");
-    download_file(&asset.browser_download_url, &download_path)?;
+    let checksums = fetch_checksums(release)?;
+    println!("peon-ping: downloading {}...", asset.name);
+    let bytes = download(&asset.browser_download_url)?;
+    verify_checksum(&checksums, &asset.name, &bytes).map_err(|e| format!("peon-ping: {e}"))?;
+    println!("peon-ping: checksum verified");
+    let binary = match format {
+        AssetFormat::Binary => bytes,
+        AssetFormat::TarGz => extract_binary(&bytes)
+            .map_err(|e| format!("peon-ping: failed to unpack {}: {e}", asset.name))?,
+    };
+    std::fs::write(&download_path, &binary)?;
 
-    // Backup current binary
-    if current_exe.exists() {
-        std::fs::copy(&current_exe, &backup_path)?;
+    let swapped = swap_binary(&download_path, &current_exe, current_version);
+    let _ = std::fs::remove_file(&download_path);
+    swapped?;
+    println!("peon-ping: upgraded to {target_version} (was {current_version})");
+    println!("peon-ping: run 'peon upgrade --rollback' to go back to {current_version}");
+    Ok(())
+}
+
+/// Put back the binary kept by the last upgrade. The binary being replaced is kept in
+/// turn, so a rollback can itself be undone.
+fn rollback(current_version: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let current_exe = std::env::current_exe()?;
+    let (previous_path, record_path) = previous_binary_paths(&current_exe);
+    let previous = state_io::load_previous_binary(&record_path)
+        .filter(|_| previous_path.exists())
+        .ok_or("peon-ping: no previous version to roll back to")?;
+
+    println!(
+        "peon-ping: rolling back to {} (was {current_version})...",
+        previous.version
+    );
+    swap_binary(&previous_path, &current_exe, current_version)?;
+    println!("peon-ping: rolled back to {}", previous.version);
+    Ok(())
+}
+
+/// Install the binary at `new` over `exe`, then smoke-test it with `--version`. If either
+/// step fails the old binary is restored; otherwise it is kept as the previous binary,
+/// recorded as `old_version`, for `peon upgrade --rollback`.
+fn swap_binary(
+    new: &Path,
+    exe: &Path,
+    old_version: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let backup_path = exe.with_extension("backup");
+    std::fs::copy(exe, &backup_path)?;
+
+    if let Err(e) = replace_binary(new, exe).and_then(|()| smoke_test(exe)) {
+        eprintln!("peon-ping: new binary failed: {e}");
+        eprintln!("peon-ping: restoring {old_version}...");
+        match std::fs::rename(&backup_path, exe) {
+            Ok(()) => eprintln!("peon-ping: restored {old_version}"),
+            Err(rb_err) => eprintln!("peon-ping: restore failed: {rb_err}"),
+        }
+        return Err(e);
     }
 
-    // Replace binary
-    match replace_binary(&download_path, &current_exe) {
-        Ok(()) => {
-            // Clean up backup and download
-            let _ = std::fs::remove_file(&backup_path);
-            let _ = std::fs::remove_file(&download_path);
-            println!("peon-ping: upgraded to {latest_version} (was {current_version})");
-            Ok(())
+    let (previous_path, record_path) = previous_binary_paths(exe);
+    std::fs::rename(&backup_path, &previous_path)?;
+    let previous = PreviousBinary {
+        version: old_version.to_string(),
+        replaced_at: unix_now(),
+    };
+    state_io::save_previous_binary(&record_path, &previous)?;
+    Ok(())
+}
+
+/// Check that the binary at `exe` runs and reports a peon version.
+fn smoke_test(exe: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new(exe)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{} failed to run: {e}", exe.display()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() && parse_version_output(&stdout).is_some() {
+        return Ok(());
+    }
+    let mut message = format!("{} --version failed ({})", exe.display(), output.status);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        message = format!("{message}: {}", stderr.trim());
+    }
+    Err(message.into())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Look up the newest release on `channel` and cache it for hooks and `peon status`.
+/// In `background` mode nothing is printed and failures are ignored.
+fn check(channel: Channel, background: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    let releases = match fetch_releases() {
+        Ok(releases) => releases,
+        Err(_) if background => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let latest = latest_release(&releases, channel)
+        .map(|release| parse_version_tag(&release.tag_name).to_string());
+    let cache = UpdateCache {
+        checked_at: unix_now(),
+        latest,
+    };
+    let saved = state_io::save_update_cache(&paths::update_cache_path(), &cache);
+    if background {
+        return Ok(());
+    }
+    saved?;
+
+    match peon_core::available_update(&cache, current_version) {
+        Some(latest) => {
+            println!("peon-ping: version {latest} is available (you have {current_version})");
+            Err(SilentExit(UPDATE_AVAILABLE_EXIT).into())
         }
-        Err(e) => {
-            // Rollback from backup
-            eprintln!("peon-ping: upgrade failed: {e}");
-            if backup_path.exists() {
-                eprintln!("peon-ping: rolling back...");
-                if let Err(rb_err) = std::fs::rename(&backup_path, &current_exe) {
-                    eprintln!("peon-ping: rollback failed: {rb_err}");
-                } else {
-                    eprintln!("peon-ping: rolled back successfully");
-                }
-            }
-            let _ = std::fs::remove_file(&download_path);
-            Err(e)
+        None => {
+            println!("peon-ping: up to date ({current_version})");
+            Ok(())
         }
     }
 }
 
-fn fetch_latest_release() -> Result<GitHubRelease, Box<dyn std::error::Error>> {
-    let response = ureq::get(GITHUB_API_URL)
-        .set("Accept", "application/vnd.github+json")
-        .set("User-Agent", "peon-ping-upgrade")
-        .call()?;
+/// Start a detached `peon upgrade --check --background` when the opt-in update check is
+/// due. Returns immediately, so hooks never wait on the network.
+pub fn spawn_background_check(config: &Config) {
+    if !config.update_check {
+        return;
+    }
+    let path = paths::update_cache_path();
+    let cache = state_io::load_update_cache(&path);
+    let now = unix_now();
+    if !update_check_due(cache.as_ref(), now, config.update_check_interval_hours) {
+        return;
+    }
+    // Record the attempt first so other sessions, and failed checks, wait a full interval
+    let attempt = UpdateCache {
+        checked_at: now,
+        latest: cache.and_then(|cache| cache.latest),
+    };
+    if state_io::save_update_cache(&path, &attempt).is_err() {
+        return;
+    }
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = Command::new(exe)
+            .args(["upgrade", "--check", "--background"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+    }
+}
 
-    let release: GitHubRelease = response.into_json()?;
-    Ok(release)
+fn fetch_releases() -> Result<Vec<GitHubRelease>, Box<dyn std::error::Error>> {
+    let response = http::get(RELEASES_URL, &[("Accept", "application/vnd.github+json")])?;
+
+    let releases: Vec<GitHubRelease> = response.into_json()?;
+    Ok(releases)
 }
 
-fn download_file(url: &str, dest: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    let response = ureq::get(url)
-        .set("User-Agent", "peon-ping-upgrade")
-        .call()?;
+fn download(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let response = http::get(url, &[])?;
 
     let mut bytes = Vec::new();
     response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
 
-    std::fs::write(dest, &bytes)?;
-    Ok(())
+/// Download the release's checksum manifest, checking its signature when this build
+/// carries a release key. Fails closed: a release without them can't be installed.
+fn fetch_checksums(release: &GitHubRelease) -> Result<String, Box<dyn std::error::Error>> {
+    let unverifiable = |missing: &str| {
+        format!(
+            "peon-ping: release {} has no {missing}; refusing to install an unverified binary",
+            release.tag_name
+        )
+    };
+    let asset = find_matching_asset(release, CHECKSUMS_ASSET)
+        .ok_or_else(|| unverifiable(CHECKSUMS_ASSET))?;
+    let manifest = download(&asset.browser_download_url)?;
+
+    if let Some(key) = RELEASE_PUBLIC_KEY.filter(|key| !key.trim().is_empty()) {
+        let key = PublicKey::parse(key).map_err(|e| format!("peon-ping: {e}"))?;
+        let signature = find_matching_asset(release, SIGNATURE_ASSET)
+            .ok_or_else(|| unverifiable(SIGNATURE_ASSET))?;
+        let signature = String::from_utf8(download(&signature.browser_download_url)?)?;
+        verify_signature(&key, &signature, &manifest).map_err(|e| format!("peon-ping: {e}"))?;
+        println!("peon-ping: signature verified (key {})", key.key_id());
+    }
+
+    String::from_utf8(manifest)
+        .map_err(|_| format!("peon-ping: {CHECKSUMS_ASSET} is not valid UTF-8").into())
+}
+
+/// Pull the `peon` executable out of a `.tar.gz` release asset, wherever it sits.
+fn extract_binary(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let is_peon = entry.path()?.file_name() == Some(std::ffi::OsStr::new("peon"));
+        if entry.header().entry_type() == tar::EntryType::Regular && is_peon {
+            let mut binary = Vec::new();
+            entry.read_to_end(&mut binary)?;
+            return Ok(binary);
+        }
+    }
+    Err("archive has no peon binary".into())
 }
 
-fn replace_binary(source: &PathBuf, dest: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+fn replace_binary(source: &Path, dest: &Path) -> Result<(), Box<dyn std::error::Error>> {
     std::fs::rename(source, dest)?;
 
     #[cfg(unix)]
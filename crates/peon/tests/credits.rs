@@ -0,0 +1,23 @@
+//! `peon credits` run as a subprocess against an empty packs directory.
+
+use std::process::Command;
+
+#[test]
+fn credits_without_packs_is_not_an_error() {
+    let dir = std::env::temp_dir().join(format!("peon-credits-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let packs = dir.join("packs");
+    std::fs::create_dir_all(&packs).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_peon"))
+        .env("CLAUDE_PEON_DIR", &dir)
+        .env("PEON_PACKS", &packs)
+        .arg("credits")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout, format!("No packs found in {}\n", packs.display()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,45 @@
+//! `peon remove` run as a subprocess, so environment overrides stay in the child.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const MANIFEST: &str = r#"{"name": "NAME", "categories": {}}"#;
+
+fn peon_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("peon-remove-{test}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for name in ["peon", "glados", "mine"] {
+        let pack = dir.join("packs").join(name);
+        std::fs::create_dir_all(&pack).unwrap();
+        std::fs::write(pack.join("manifest.json"), MANIFEST.replace("NAME", name)).unwrap();
+    }
+    dir
+}
+
+fn peon(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_peon"));
+    command.env("CLAUDE_PEON_DIR", dir).env_remove("PEON_PACKS");
+    command
+}
+
+#[test]
+fn env_overrides_are_not_written_to_config() {
+    let dir = peon_dir("env");
+    let config = r#"{"active_pack": "peon", "pack_rotation": ["peon", "glados"]}"#;
+    std::fs::write(dir.join("config.json"), config).unwrap();
+
+    let output = peon(&dir)
+        .env("PEON_ACTIVE_PACK", "mine")
+        .env("PEON_PACK_ROTATION", "mine,glados")
+        .args(["remove", "mine", "--force"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(!dir.join("packs/mine").exists());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("config.json")).unwrap(),
+        config
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,30 @@
+//! `peon pack validate` run as a subprocess, so it can run from inside a pack.
+
+use std::process::Command;
+
+#[test]
+fn validates_the_current_directory() {
+    let dir = std::env::temp_dir().join(format!("peon-validate-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let pack = dir.join("mine");
+    std::fs::create_dir_all(pack.join("sounds")).unwrap();
+    std::fs::write(
+        pack.join("manifest.json"),
+        r#"{"name": "mine", "categories": {"greeting": {"sounds": [{"file": "Hi.wav"}]}}}"#,
+    )
+    .unwrap();
+    std::fs::write(pack.join("sounds/Hi.wav"), "RIFF").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_peon"))
+        .env("CLAUDE_PEON_DIR", dir.join("peon"))
+        .env_remove("PEON_PACKS")
+        .current_dir(&pack)
+        .args(["pack", "validate", "."])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout, "mine: ok\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -185,7 +185,7 @@ peon_entry = {
     'hooks': [peon_hook]
 }
 
-events = ['SessionStart', 'UserPromptSubmit', 'Stop', 'Notification', 'PermissionRequest']
+events = ['SessionStart', 'UserPromptSubmit', 'Stop', 'SubagentStop', 'Notification', 'PermissionRequest', 'PreCompact', 'PostToolUse']
 
 for event in events:
     event_hooks = hooks.get(event, [])
@@ -209,7 +209,7 @@ print('Hooks registered for: ' + ', '.join(events))
 "
 elif command -v jq &>/dev/null; then
   # jq fallback for settings update
-  EVENTS="SessionStart UserPromptSubmit Stop Notification PermissionRequest"
+  EVENTS="SessionStart UserPromptSubmit Stop SubagentStop Notification PermissionRequest PreCompact PostToolUse"
   TMP_SETTINGS=$(mktemp)
   if [ -f "$SETTINGS" ]; then
     cp "$SETTINGS" "$TMP_SETTINGS"
@@ -10,6 +10,7 @@ use clap::Parser;
 
 mod docs;
 mod lint;
+mod pack_index;
 mod prelude;
 mod release;
 
@@ -52,6 +53,8 @@ enum Commands {
     Docs(docs::DocsCommand),
     /// Code quality checks and git hooks management
     Lint(lint::LintCommand),
+    /// Generate packs/index.json for the pack registry
+    PackIndex(pack_index::PackIndexCommand),
     /// Create a new release
     Release(release::ReleaseCommand),
 }
@@ -67,6 +70,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Commands::Lint(lint_cmd) => {
             lint::run(lint_cmd, cli.global).await?;
         }
+        Commands::PackIndex(pack_index_cmd) => {
+            pack_index::run(pack_index_cmd, cli.global).await?;
+        }
         Commands::Release(release_cmd) => {
             release::run(release_cmd, cli.global).await?;
         }
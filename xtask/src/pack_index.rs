@@ -0,0 +1,94 @@
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, PackIndexError>;
+
+#[derive(Error, Debug)]
+pub enum PackIndexError {
+    #[error("packs/ directory not found — run from repo root")]
+    PacksDirNotFound,
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON parse error in {path}: {source}")]
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error("JSON serialization error: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Generate packs/index.json for the pack registry
+#[derive(Debug, clap::Parser)]
+#[command(
+    long_about = "Generate packs/index.json, the registry index used by `peon pull` and
+`peon packs --remote`.
+
+Lists every packs/*/manifest.json pack with its version, total size, languages,
+tags and the size and SHA-256 of each file (manifest.json and sounds/*)."
+)]
+pub struct PackIndexCommand {}
+
+pub async fn run(_cmd: PackIndexCommand, global: crate::Global) -> Result<()> {
+    if !global.is_silent() {
+        aprintln!("{} Generating packs/index.json...", p_b("pack-index:"));
+    }
+
+    let packs_dir = Path::new("packs");
+    if !packs_dir.exists() {
+        return Err(PackIndexError::PacksDirNotFound);
+    }
+
+    let mut packs = Vec::new();
+    for entry in std::fs::read_dir(packs_dir)? {
+        let path = entry?.path();
+        let manifest_path = path.join("manifest.json");
+        if !manifest_path.is_file() {
+            continue;
+        }
+        let content = std::fs::read(&manifest_path)?;
+        let manifest: peon_core::types::Manifest =
+            serde_json::from_slice(&content).map_err(|e| PackIndexError::Json {
+                path: manifest_path.display().to_string(),
+                source: e,
+            })?;
+
+        let mut files = BTreeMap::new();
+        files.insert("manifest.json".to_string(), content);
+        if let Ok(sounds) = std::fs::read_dir(path.join("sounds")) {
+            for sound in sounds {
+                let sound = sound?;
+                if sound.file_type()?.is_file() {
+                    let name = sound.file_name().to_string_lossy().into_owned();
+                    files.insert(format!("sounds/{name}"), std::fs::read(sound.path())?);
+                }
+            }
+        }
+        packs.push(peon_core::index_entry(&manifest, &files));
+    }
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let index = peon_core::RegistryIndex {
+        packs,
+        ..Default::default()
+    };
+    std::fs::write(
+        packs_dir.join("index.json"),
+        serde_json::to_string_pretty(&index)? + "\n",
+    )?;
+
+    if !global.is_silent() {
+        aprintln!(
+            "{} Generated packs/index.json ({} packs)",
+            p_g("done:"),
+            index.packs.len()
+        );
+    }
+
+    Ok(())
+}